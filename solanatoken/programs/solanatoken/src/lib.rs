@@ -1,8 +1,13 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::solana_program::program_option::COption;
 use anchor_spl::token::{self, Mint, Token, TokenAccount};
+use anchor_spl::token::spl_token::instruction::AuthorityType;
 use anchor_spl::associated_token::AssociatedToken;
 use std::collections::HashMap;
+use std::str::FromStr;
 use pyth_sdk_solana::{load_price_feed_from_account_info, PriceStatus};
+use pyth_sdk_solana::state::{load_price_account, PriceAccount};
 
 declare_id!("7MCEfe5NNGmfv2TiGDthDPF5T4TrsWFLRHAA5WMC7sTo");
 
@@ -10,16 +15,51 @@ declare_id!("7MCEfe5NNGmfv2TiGDthDPF5T4TrsWFLRHAA5WMC7sTo");
 pub mod token_config {
     pub const NAME: &str = "Next Gen Crypto";
     pub const SYMBOL: &str = "NGC";
-    pub const DECIMALS: u8 = 6;
-    pub const TOTAL_SUPPLY: u64 = 1_000_000_000_000_000; // 1 billion with 6 decimals
+    pub const TOTAL_SUPPLY_TOKENS: u64 = 1_000_000_000; // 1 billion whole tokens
+    pub const MAX_DECIMALS: u8 = 9; // Keeps the total supply in base units within a u64
     pub const REWARDS_RATE: u64 = 500; // 5% annual rewards rate (basis points)
-    pub const MIN_HOLDING_PERIOD: i64 = 2_592_000; // 30 days in seconds
+    pub const MIN_HOLDING_PERIOD: i64 = 2_592_000; // 30 days in seconds, the default holding period
+    pub const CLAIM_COOLDOWN: i64 = 604_800; // 7 days in seconds, the default time between claims
+    pub const MAX_HOLDING_PERIOD: i64 = 31_536_000; // 1 year in seconds
+    pub const MAX_ACCRUAL_DELAY: i64 = 7_776_000; // 90 days in seconds
+    pub const MAX_HOLDING_STREAK: i64 = 31_536_000; // 1 year in seconds
+    pub const MAX_TRADING_START_DELAY: i64 = 7_776_000; // 90 days in seconds
     pub const TRANSFER_COOLDOWN: i64 = 300; // 5 minutes in seconds
     
     // Transaction limits
     pub const MIN_PURCHASE_USD: u64 = 5000; // $50.00 in cents
-    pub const MAX_TRANSACTION_SIZE: u64 = 1_000_000_000_000; // 1% of total supply
+    pub const MAX_TRANSACTION_SIZE: u64 = 1_000_000; // 0.1% of total supply, in whole tokens
     pub const MAX_DAILY_TRANSACTIONS: u64 = 10;
+    pub const MAX_DAILY_TRANSACTIONS_LIMIT: u64 = 100; // Highest configurable daily transaction limit
+    pub const FLAG_AFTER_LIMIT_DAYS: u16 = 3; // Consecutive max-out days before review
+    
+    // Reward multipliers (basis points)
+    pub const DEFAULT_REWARD_MULTIPLIER_BPS: u16 = 10_000; // 1x
+    pub const MAX_REWARD_MULTIPLIER_BPS: u16 = 30_000; // 3x
+    pub const MAX_REFERRAL_BONUS_BPS: u16 = 2_000; // 20% of the referred holder's claim
+    pub const MAX_TRANSFER_FEE_BPS: u16 = 1_000; // 10% of the transfer amount
+    pub const MAX_EARLY_TRANSFER_PENALTY_BPS: u16 = 2_000; // 20% of the transfer amount
+    pub const MAX_REWARD_RATE_BPS: u16 = 5_000; // 50% annual rewards rate
+    pub const DEFAULT_EMERGENCY_HAIRCUT_BPS: u16 = 1_000; // 10% of an emergency claim
+    pub const MAX_EMERGENCY_HAIRCUT_BPS: u16 = 5_000; // 50% of an emergency claim
+    pub const REWARD_INDEX_PRECISION: u128 = 1_000_000_000_000; // Fixed-point scale of the reward index
+    pub const MAX_BONUS_REWARD_RATE_BPS: u16 = 10_000; // 100% annual, in bonus base units per native base unit
+    
+    // Price confidence thresholds (basis points of price)
+    pub const MAX_CONFIDENCE_BPS: u64 = 100; // 1%
+    pub const VOLATILITY_MAX_CONFIDENCE_BPS: u64 = 500; // 5% while volatility mode is active
+    pub const MAX_ABSOLUTE_CONFIDENCE_CENTS: u64 = 1_000_000; // $10,000, upper bound for the absolute cap
+    pub const MAX_VOLATILITY_WINDOW: i64 = 86_400; // 24 hours in seconds
+    pub const MAX_PRICE_SLOT_AGE: u64 = 1_500; // About 10 minutes of slots
+    pub const LAST_KNOWN_PRICE_MAX_AGE: i64 = 3_600; // 1 hour in seconds
+    pub const UNPRICED_MAX_TRANSACTION_SIZE: u64 = 100_000; // Whole tokens, when no price is available
+    pub const MAX_DAILY_RESET_OFFSET: i64 = 14 * 3_600; // UTC+14 is the furthest time zone
+    pub const MINT_RATE_WINDOW: i64 = 86_400; // 24 hours in seconds
+    pub const AUDIT_LOG_CAPACITY: usize = 32; // Admin actions kept on-chain
+    pub const MAX_APPROVED_AUTHORITIES: usize = 8; // Size of the secure_transfer signer allowlist
+    pub const MAX_INTERNAL_ACCOUNTS: usize = 8; // Token accounts exempt from the USD minimum between each other
+    pub const MAX_ALLOWED_RECIPIENT_PROGRAMS: usize = 8; // Programs whose accounts may receive transfers when blocking
+    pub const MAX_HOLDERS_BATCH: usize = 15; // Keeps get_holders_batch under the 1024-byte return data limit
     pub const PYTH_PRICE_FEED: &str = "Gv2NQnFfSQgzqFoGGm4bFX5q6oBKPPXRJQDG3voqfWJt"; // Pyth SOL/USD price feed
 }
 
@@ -29,71 +69,301 @@ impl Processor {
     pub fn process_transfer(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
+        config: &mut RewardsVault,
+        holder_data: &mut HolderData,
         amount: u64,
-    ) -> ProgramResult {
+        record_rejection: bool,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<bool, ProgramError> {
         let account_info_iter = &mut accounts.iter();
         
         let from_account = next_account_info(account_info_iter)?;
         let to_account = next_account_info(account_info_iter)?;
         let authority = next_account_info(account_info_iter)?;
-        let holder_data = next_account_info(account_info_iter)?;
         let price_feed = next_account_info(account_info_iter)?;
         let token_program = next_account_info(account_info_iter)?;
+        let mint = next_account_info(account_info_iter)?;
+        let treasury = next_account_info(account_info_iter)?;
+        let to_holder_data = account_info_iter.next();
 
-        // Verify authority
-        if !authority.is_signer {
+        // Verify authority; a program-derived authority signs through
+        // signer_seeds instead
+        if !authority.is_signer && signer_seeds.is_empty() {
             return Err(ProgramError::MissingRequiredSignature);
         }
 
-        // Get current price from Pyth feed
-        let current_price = Self::get_token_price(price_feed)?;
+        let from_owner = TokenAccount::try_deserialize(&mut &from_account.data.borrow()[..])?.owner;
+
+        // A non-empty allowlist restricts who may initiate transfers. A
+        // program-derived authority only signs for a subscription whose
+        // seeds the caller has checked, so the payer who set it up is the
+        // one checked rather than the PDA
+        let initiator = if signer_seeds.is_empty() { authority.key } else { &from_owner };
+        if !config.approved_authorities.is_empty() && !config.approved_authorities.contains(initiator) {
+            return Err(TokenError::AuthorityNotApproved.into());
+        }
+
+        if config.paused {
+            return Err(TokenError::ProgramPaused.into());
+        }
+
+        // The vault authority moving its own tokens, rather than a
+        // holder's it was approved as delegate for
+        let authority_own = *authority.key == config.authority && from_owner == config.authority;
+
+        // Before trading opens only the vault authority's own tokens can move
+        if !authority_own && Clock::get()?.unix_timestamp < config.trading_start_ts {
+            return Err(TokenError::TradingNotStarted.into());
+        }
+
+        // When enabled, the vault authority's own transfers skip the
+        // cooldown, daily count, USD minimum and size limits
+        let exempt = config.authority_exempt && authority_own;
+
+        // Optionally require the recipient to be a registered holder
+        if config.require_recipient_registered && to_holder_data.is_none() {
+            return Err(TokenError::RecipientNotRegistered.into());
+        }
+
+        // Get current price from Pyth feed, falling back per the configured
+        // policy when the feed is unavailable. Only a live reading carries a
+        // confidence interval
+        let (current_price, current_conf) = match Self::read_price_feed(price_feed, config) {
+            Ok(reading) => {
+                config.last_price = reading.price;
+                config.last_price_time = Clock::get()?.unix_timestamp;
+                (Some(reading.price), Some(reading.conf))
+            }
+            Err(err) if is_price_unavailable(&err) => match config.price_unavailable_policy {
+                PriceUnavailablePolicy::Block => return Err(err),
+                PriceUnavailablePolicy::UseLastKnown => (Some(Self::last_known_price(config)?), None),
+                PriceUnavailablePolicy::AllowWithMaxLimits => (None, None),
+            },
+            Err(err) if config.halted_uses_last_known && err == TokenError::MarketHalted.into() => {
+                (Some(Self::last_known_price(config)?), None)
+            }
+            Err(err) => return Err(err),
+        };
         
-        // Get holder data
-        let mut holder_data_account = HolderData::try_from_slice(&holder_data.data.borrow())?;
+        // Flagged holders are blocked until the authority reviews them
+        if holder_data.flagged {
+            return Err(TokenError::HolderFlagged.into());
+        }
         
-        // Validate transaction limits
-        validate_transaction_limits(
-            amount,
-            current_price,
-            holder_data_account.daily_transactions,
-            Clock::get()?.unix_timestamp,
-            holder_data_account.last_transaction_date,
-        )?;
+        // Enforce the global cooldown (unless it is tracked per recipient)
+        // and the daily transaction count
+        let now = Clock::get()?.unix_timestamp;
+        let rejection = if exempt {
+            None
+        } else if !config.per_pair_cooldown
+            && now - holder_data.last_transfer < config.transfer_cooldown(holder_data.acquired_at, now)
+        {
+            Some(RejectionReason::Cooldown)
+        } else if holder_data.last_transaction_date == day_index(now, config.daily_reset_offset_secs)
+            && holder_data.daily_transactions >= config.max_daily_transactions(holder_data.is_verified)
+        {
+            Some(RejectionReason::DailyLimit)
+        } else {
+            None
+        };
+        
+        if let Some(reason) = rejection {
+            if !(record_rejection && config.record_rejections) {
+                return Err(reason.error().into());
+            }
+            
+            // Returning an error would roll back the counters, so record the
+            // rejection and finish without moving any tokens
+            match reason {
+                RejectionReason::Cooldown => {
+                    holder_data.cooldown_rejections = holder_data.cooldown_rejections.saturating_add(1);
+                }
+                RejectionReason::DailyLimit => {
+                    holder_data.daily_limit_rejections = holder_data.daily_limit_rejections.saturating_add(1);
+                }
+            }
+            emit!(TransferRejected {
+                holder: holder_data.authority,
+                reason,
+                timestamp: now,
+            });
+            return Ok(false);
+        }
+        
+        // Read decimals from the mint, which sets them at creation
+        let decimals = Mint::try_deserialize(&mut &mint.data.borrow()[..])?.decimals;
+        
+        let balance = TokenAccount::try_deserialize(&mut &from_account.data.borrow()[..])?.amount;
+        
+        // Validate transaction limits. Moves between two internal accounts
+        // skip only the USD minimum
+        if !exempt {
+            let internal = config.internal_accounts.contains(from_account.key)
+                && config.internal_accounts.contains(to_account.key);
+            validate_transaction_limits(
+                config,
+                amount,
+                balance,
+                current_price,
+                decimals,
+                holder_data.daily_transactions,
+                Clock::get()?.unix_timestamp,
+                holder_data.last_transaction_date,
+                holder_data.is_verified,
+                internal,
+            )?;
 
-        // Process the transfer
-        token::transfer(
-            CpiContext::new(
-                token_program.clone(),
-                token::Transfer {
-                    from: from_account.clone(),
-                    to: to_account.clone(),
-                    authority: authority.clone(),
-                },
-            ),
-            amount,
-        )?;
+            if let (Some(price), Some(conf)) = (current_price, current_conf) {
+                check_large_transfer_confidence(config, amount, price, conf, decimals)?;
+            }
+        }
+
+        // Process the transfer, routing the fee to the treasury and burn.
+        // transfer_checked makes the token program verify the mint account
+        // and the decimals read from it, so a wrong mint can't be silently
+        // misaccounted
+        let (net_amount, treasury_fee, burn_fee) =
+            split_transfer_fee(amount, config.transfer_fee_bps, config.fee_burn_bps)?;
+        
+        // Moving tokens before holding them min_holding_period costs an
+        // optional penalty, paid to the treasury out of the net amount
+        let penalty = if !exempt
+            && config.early_transfer_penalty_bps > 0
+            && now - holder_data.acquired_at < config.min_holding_period
+        {
+            checked_cast(net_amount as u128 * config.early_transfer_penalty_bps as u128 / 10_000)?
+        } else {
+            0
+        };
+        let net_amount = net_amount - penalty;
+        let treasury_fee = treasury_fee + penalty;
+        
+        // Cap what a single recipient may hold; the treasury and the vault
+        // authority's own accounts are exempt
+        if config.max_holder_balance > 0 && *to_account.key != config.treasury {
+            let recipient = TokenAccount::try_deserialize(&mut &to_account.data.borrow()[..])?;
+            if recipient.owner != config.authority {
+                let balance_after = recipient.amount.checked_add(net_amount)
+                    .ok_or(TokenError::ArithmeticOverflow)?;
+                if balance_after > config.max_holder_balance {
+                    return Err(TokenError::MaxHolderBalanceExceeded.into());
+                }
+            }
+        }
+        
+        // Sending to the configured burn address burns the tokens instead
+        let mut burned = burn_fee;
+        if config.burn_address != Pubkey::default() && *to_account.key == config.burn_address {
+            burned = burned.checked_add(net_amount).ok_or(TokenError::ArithmeticOverflow)?;
+        } else {
+            token::transfer_checked(
+                CpiContext::new_with_signer(
+                    token_program.clone(),
+                    token::TransferChecked {
+                        from: from_account.clone(),
+                        mint: mint.clone(),
+                        to: to_account.clone(),
+                        authority: authority.clone(),
+                    },
+                    signer_seeds,
+                ),
+                net_amount,
+                decimals,
+            )?;
+        }
+        
+        if treasury_fee > 0 {
+            token::transfer_checked(
+                CpiContext::new_with_signer(
+                    token_program.clone(),
+                    token::TransferChecked {
+                        from: from_account.clone(),
+                        mint: mint.clone(),
+                        to: treasury.clone(),
+                        authority: authority.clone(),
+                    },
+                    signer_seeds,
+                ),
+                treasury_fee,
+                decimals,
+            )?;
+        }
+        
+        if burned > 0 {
+            token::burn(
+                CpiContext::new_with_signer(
+                    token_program.clone(),
+                    token::Burn {
+                        mint: mint.clone(),
+                        from: from_account.clone(),
+                        authority: authority.clone(),
+                    },
+                    signer_seeds,
+                ),
+                burned,
+            )?;
+            config.total_burned = config.total_burned.checked_add(burned)
+                .ok_or(TokenError::ArithmeticOverflow)?;
+        }
 
-        // Update holder data
+        // Update holder data only once every CPI above has succeeded, so a
+        // transfer the token program refuses never consumes the daily count
+        // or starts a cooldown
         let current_time = Clock::get()?.unix_timestamp;
-        let today = (current_time / 86400) as i64;
+        let today = day_index(current_time, config.daily_reset_offset_secs);
         
-        if holder_data_account.last_transaction_date != today {
-            holder_data_account.daily_transactions = 0;
-            holder_data_account.last_transaction_date = today;
+        if holder_data.last_transaction_date != today {
+            holder_data.daily_transactions = 0;
+            holder_data.last_transaction_date = today;
         }
         
-        holder_data_account.daily_transactions = holder_data_account.daily_transactions
+        holder_data.daily_transactions = holder_data.daily_transactions
             .checked_add(1)
             .ok_or(ProgramError::ArithmeticOverflow)?;
         
-        holder_data_account.last_transfer = current_time;
+        // Flag holders who max out the daily limit several days in a row
+        if !exempt && holder_data.daily_transactions == config.max_daily_transactions(holder_data.is_verified) {
+            holder_data.consecutive_limit_days = if holder_data.last_limit_hit_date == today - 1 {
+                holder_data.consecutive_limit_days.saturating_add(1)
+            } else {
+                1
+            };
+            holder_data.last_limit_hit_date = today;
+            
+            if holder_data.consecutive_limit_days >= token_config::FLAG_AFTER_LIMIT_DAYS {
+                holder_data.flagged = true;
+                msg!("Holder {} flagged for review", holder_data.authority);
+            }
+        }
         
-        holder_data_account.serialize(&mut *holder_data.data.borrow_mut())?;
+        holder_data.last_transfer = current_time;
 
-        Ok(())
+        // The whole amount, fees included, leaves the sender's account
+        holder_data.record_balance(balance.saturating_sub(amount));
+
+        Ok(true)
+    }
+
+    pub fn last_known_price(config: &RewardsVault) -> Result<u64, ProgramError> {
+        if config.last_price == 0
+            || Clock::get()?.unix_timestamp - config.last_price_time > token_config::LAST_KNOWN_PRICE_MAX_AGE
+        {
+            return Err(TokenError::StalePrice.into());
+        }
+        Ok(config.last_price)
+    }
+
+    pub fn get_token_price(
+        price_feed_account: &AccountInfo,
+        config: &RewardsVault,
+    ) -> Result<u64, ProgramError> {
+        Ok(Self::read_price_feed(price_feed_account, config)?.price)
     }
 
-    pub fn get_token_price(price_feed_account: &AccountInfo) -> Result<u64, ProgramError> {
+    pub fn read_price_feed(
+        price_feed_account: &AccountInfo,
+        config: &RewardsVault,
+    ) -> Result<PriceReading, ProgramError> {
         let price_feed = load_price_feed_from_account_info(price_feed_account)
             .map_err(|_| TokenError::InvalidPriceFeed)?;
         
@@ -101,24 +371,66 @@ impl Processor {
             .ok_or(TokenError::StalePrice)?;
             
         // Verify price feed status
-        if price_data.status != PriceStatus::Trading {
-            return Err(TokenError::InvalidPriceFeed.into());
+        match price_data.status {
+            PriceStatus::Trading => {}
+            PriceStatus::Halted => return Err(TokenError::MarketHalted.into()),
+            PriceStatus::Unknown => return Err(TokenError::PriceUnknown.into()),
+            _ => return Err(TokenError::InvalidPriceFeed.into()),
         }
         
-        // Check confidence interval
-        let confidence_ratio = price_data.conf as f64 / price_data.price as f64;
-        if confidence_ratio > 0.01 { // 1% confidence interval threshold
+        // Optionally bound the age of the price in slots, so a price from a
+        // slot that could still be rolled back isn't acted on
+        if config.min_price_slot_age > 0 || config.max_price_slot_age > 0 {
+            let data = price_feed_account.try_borrow_data()?;
+            let price_account: &PriceAccount = load_price_account(&data)
+                .map_err(|_| TokenError::InvalidPriceFeed)?;
+            check_price_slot_age(
+                price_account.agg.pub_slot,
+                Clock::get()?.slot,
+                config.min_price_slot_age,
+                config.max_price_slot_age,
+            )?;
+        }
+        
+        // Check confidence interval, relaxed while volatility mode is active
+        let max_confidence_bps = if Clock::get()?.unix_timestamp < config.volatility_mode_until {
+            token_config::VOLATILITY_MAX_CONFIDENCE_BPS
+        } else {
+            token_config::MAX_CONFIDENCE_BPS
+        };
+        // A negative price can't be valued, so reject it before any math
+        let price: u64 = checked_cast(price_data.price)?;
+        let confidence_ratio = price_data.conf as f64 / price as f64;
+        if confidence_ratio > max_confidence_bps as f64 / 10_000.0 {
             return Err(TokenError::PriceConfidenceTooLow.into());
         }
         
         // Get the price in USD (6 decimals)
-        let price_in_usd = price_data.price
+        let expo_scale = pow10_exponent(price_data.expo)?;
+        let price_in_usd = price
+            .checked_mul(10u64.pow(6))
+            .ok_or(TokenError::ArithmeticOverflow)?
+            .checked_div(expo_scale)
+            .ok_or(TokenError::ArithmeticOverflow)?;
+        
+        // Scale the confidence interval the same way
+        let conf_in_usd = price_data.conf
             .checked_mul(10u64.pow(6))
             .ok_or(TokenError::ArithmeticOverflow)?
-            .checked_div(10u64.pow(price_data.expo.abs() as u32))
+            .checked_div(expo_scale)
             .ok_or(TokenError::ArithmeticOverflow)?;
+        
+        // The ratio alone lets high-priced assets carry a large absolute
+        // uncertainty, so optionally cap it in USD cents as well
+        if config.max_confidence_cents > 0 && conf_in_usd / 10_000 > config.max_confidence_cents {
+            return Err(TokenError::PriceConfidenceTooLow.into());
+        }
             
-        Ok(price_in_usd)
+        Ok(PriceReading {
+            price: price_in_usd,
+            conf: conf_in_usd,
+            publish_time: price_data.publish_time,
+        })
     }
 }
 
@@ -128,13 +440,17 @@ pub mod solanatoken {
 
     pub fn initialize_token(
         ctx: Context<InitializeToken>,
+        decimals: u8,
     ) -> Result<()> {
         msg!("Initializing Next Gen Crypto Token");
+        validate_config_value(ConfigValue::Decimals(decimals))?;
+        let total_supply = total_supply(decimals)?;
         
-        let rewards_vault = &mut ctx.accounts.rewards_vault;
-        rewards_vault.authority = ctx.accounts.authority.key();
-        rewards_vault.total_rewards = 0;
-        rewards_vault.last_update = Clock::get()?.unix_timestamp;
+        let authority = ctx.accounts.authority.key();
+        let treasury = ctx.accounts.token_account.key();
+        ctx.accounts.rewards_vault.init(authority, ctx.bumps.rewards_vault, treasury, decimals)?;
+        ctx.accounts.audit_log.init(ctx.bumps.audit_log);
+        ctx.accounts.stats.init(ctx.bumps.stats, total_supply);
         
         // Create the mint and set the mint authority
         token::mint_to(
@@ -146,215 +462,4086 @@ pub mod solanatoken {
                     authority: ctx.accounts.authority.to_account_info(),
                 },
             ),
-            token_config::TOTAL_SUPPLY,
+            total_supply,
+        )?;
+
+        msg!("Minted {} tokens to {}", total_supply, ctx.accounts.authority.key());
+        Ok(())
+    }
+
+    pub fn bootstrap(
+        ctx: Context<Bootstrap>,
+        params: BootstrapParams,
+    ) -> Result<()> {
+        msg!("Bootstrapping Next Gen Crypto Token");
+
+        // Validate the whole config up front so a bad value fails the
+        // transaction before anything is created
+        let decimals = params.decimals;
+        validate_config_value(ConfigValue::Decimals(decimals))?;
+        validate_config_value(ConfigValue::MaxTransferSize(params.max_transfer_size))?;
+        validate_config_value(ConfigValue::MaxTransferBalanceBps(params.max_transfer_balance_bps))?;
+        validate_config_value(ConfigValue::MaxRewardPerClaim(params.max_reward_per_claim, decimals))?;
+        validate_config_value(ConfigValue::MaxRewardsPerHolder(params.max_rewards_per_holder, decimals))?;
+        validate_config_value(ConfigValue::MintRateLimit(params.mint_rate_limit, decimals))?;
+        validate_config_value(ConfigValue::ReferralBonus(params.referral_bonus_bps))?;
+        validate_config_value(ConfigValue::TransferFee(params.transfer_fee_bps))?;
+        validate_config_value(ConfigValue::FeeBurnShare(params.fee_burn_bps))?;
+        validate_config_value(ConfigValue::DailyResetOffset(params.daily_reset_offset_secs))?;
+
+        let authority = ctx.accounts.authority.key();
+        let treasury = ctx.accounts.token_account.key();
+        let total_supply = total_supply(decimals)?;
+        let rewards_vault = &mut ctx.accounts.rewards_vault;
+        rewards_vault.init(authority, ctx.bumps.rewards_vault, treasury, decimals)?;
+        rewards_vault.max_transfer_size = params.max_transfer_size;
+        rewards_vault.max_transfer_balance_bps = params.max_transfer_balance_bps;
+        rewards_vault.max_reward_per_claim = params.max_reward_per_claim;
+        rewards_vault.max_rewards_per_holder = params.max_rewards_per_holder;
+        rewards_vault.mint_rate_limit = params.mint_rate_limit;
+        rewards_vault.referral_bonus_bps = params.referral_bonus_bps;
+        rewards_vault.transfer_fee_bps = params.transfer_fee_bps;
+        rewards_vault.fee_burn_bps = params.fee_burn_bps;
+        rewards_vault.daily_reset_offset_secs = params.daily_reset_offset_secs;
+        rewards_vault.price_unavailable_policy = params.price_unavailable_policy;
+        rewards_vault.require_recipient_registered = params.require_recipient_registered;
+        rewards_vault.per_pair_cooldown = params.per_pair_cooldown;
+        rewards_vault.record_rejections = params.record_rejections;
+
+        ctx.accounts.audit_log.init(ctx.bumps.audit_log);
+        ctx.accounts.stats.init(ctx.bumps.stats, total_supply);
+        ctx.accounts.stats.add_holder(ctx.accounts.rewards_vault.max_holders)?;
+        let reward_index = ctx.accounts.rewards_vault.reward_index;
+        // The authority registers holding the supply minted below
+        ctx.accounts.holder_data.init(authority, Pubkey::default(), ctx.bumps.holder_data, reward_index, total_supply)?;
+
+        // Initial distribution of the full supply to the authority
+        token::mint_to(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::MintTo {
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.token_account.to_account_info(),
+                    authority: ctx.accounts.authority.to_account_info(),
+                },
+            ),
+            total_supply,
         )?;
 
-        msg!("Minted {} tokens to {}", token_config::TOTAL_SUPPLY, ctx.accounts.authority.key());
+        msg!("Bootstrapped with {} tokens minted to {}", total_supply, authority);
         Ok(())
     }
 
     pub fn secure_transfer(
         ctx: Context<SecureTransfer>,
         amount: u64,
+        as_delegate: bool,
+        expected_to_balance_after: Option<u64>,
     ) -> Result<()> {
-        let accounts = [
-            ctx.accounts.from.to_account_info(),
-            ctx.accounts.to.to_account_info(),
-            ctx.accounts.authority.to_account_info(),
-            ctx.accounts.holder_data.to_account_info(),
-            ctx.accounts.price_feed.to_account_info(),
-            ctx.accounts.token_program.to_account_info(),
-        ];
+        run_secure_transfer(ctx, amount, as_delegate, expected_to_balance_after, false)
+    }
 
-        Processor::process_transfer(ctx.program_id, &accounts, amount)?;
-        
-        msg!("Secure transfer of {} tokens completed", amount);
-        Ok(())
+    pub fn try_secure_transfer(
+        ctx: Context<SecureTransfer>,
+        amount: u64,
+        as_delegate: bool,
+        expected_to_balance_after: Option<u64>,
+    ) -> Result<()> {
+        // Like secure_transfer, but when the vault records rejections a
+        // cooldown or daily-limit rejection is counted instead of failing
+        run_secure_transfer(ctx, amount, as_delegate, expected_to_balance_after, true)
     }
 
     pub fn initialize_rewards(
         ctx: Context<InitializeRewards>,
+        referrer: Option<Pubkey>,
     ) -> Result<()> {
-        let holder_data = &mut ctx.accounts.holder_data;
-        holder_data.authority = ctx.accounts.authority.key();
-        holder_data.rewards_earned = 0;
-        holder_data.last_claim = Clock::get()?.unix_timestamp;
-        holder_data.last_transfer = 0;
+        require!(!ctx.accounts.rewards_vault.registrations_paused, TokenError::RegistrationsPaused);
+
+        // A referrer must be another registered holder who wasn't
+        // themselves referred by this holder
+        if let Some(referrer) = referrer {
+            require_keys_neq!(referrer, ctx.accounts.authority.key(), TokenError::SelfReferral);
+            let referrer_data = ctx.accounts.referrer_data.as_ref()
+                .ok_or(TokenError::MissingReferrer)?;
+            require_keys_eq!(referrer_data.authority, referrer, TokenError::MissingReferrer);
+            require_keys_neq!(referrer_data.referrer, ctx.accounts.authority.key(), TokenError::ReferralLoop);
+        }
+
+        ctx.accounts.stats.add_holder(ctx.accounts.rewards_vault.max_holders)?;
+
+        // New holders start accruing from the current reward index. Without
+        // their token account the balance at registration is taken as zero,
+        // so minimum-balance and bonus accrual start from nothing
+        let reward_index = ctx.accounts.rewards_vault.accrue_index(Clock::get()?.unix_timestamp)?;
+        let balance = ctx.accounts.token_account.as_ref().map_or(0, |token_account| token_account.amount);
+        ctx.accounts.holder_data.init(
+            ctx.accounts.authority.key(),
+            referrer.unwrap_or_default(),
+            ctx.bumps.holder_data,
+            reward_index,
+            balance,
+        )?;
         
         msg!("Initialized rewards for holder {}", ctx.accounts.authority.key());
         Ok(())
     }
 
+    // Closes a holder's data and frees their slot under max_holders. Only
+    // holders who have never earned rewards can close, so a holder who has
+    // keeps their slot for good
+    pub fn close_holder(
+        ctx: Context<CloseHolder>,
+    ) -> Result<()> {
+        // Re-registering starts from fresh holder data, so refuse to close
+        // while any restriction or limit streak it carries is still live
+        let rewards_vault = &ctx.accounts.rewards_vault;
+        let holder_data = &ctx.accounts.holder_data;
+        let now = Clock::get()?.unix_timestamp;
+        let today = day_index(now, rewards_vault.daily_reset_offset_secs);
+        require!(
+            !holder_data.flagged
+                && !holder_data.accrual_paused
+                && now >= holder_data.locked_until
+                && now - holder_data.last_transfer >= rewards_vault.transfer_cooldown(holder_data.acquired_at, now)
+                && holder_data.last_transaction_date != today
+                && holder_data.last_limit_hit_date < today - 1,
+            TokenError::HolderCloseRestricted
+        );
+
+        // Re-registering would reset rewards_earned and with it the lifetime
+        // max_rewards_per_holder cap, so holders who have earned keep theirs
+        require!(holder_data.rewards_earned == 0, TokenError::HolderHasEarnedRewards);
+
+        // Unclaimed rewards are forfeited along with the account
+        ctx.accounts.stats.remove_holder();
+
+        msg!("Closed holder data for {}", ctx.accounts.authority.key());
+        Ok(())
+    }
+
+    pub fn reassign_holder<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ReassignHolder<'info>>,
+        old_holder: Pubkey,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        // Holder data is seeded by its authority, so recovery moves it to the
+        // new key's PDA (init fails if that key already has one) and closes
+        // the old account into the vault authority, who paid for the new one
+        let mut holder_data = HolderData::clone(&ctx.accounts.old_holder_data);
+        holder_data.authority = new_authority;
+        holder_data.bump = ctx.bumps.new_holder_data;
+        ctx.accounts.new_holder_data.set_inner(holder_data);
+
+        // Holders the old key referred come in as remaining accounts and are
+        // repointed, so their referral bonus follows the recovered holder
+        let mint_key = ctx.accounts.mint.key();
+        for info in ctx.remaining_accounts {
+            let mut referred = Account::<HolderData>::try_from(info)?;
+            require_holder_pda(ctx.program_id, &mint_key, info, &referred, TokenError::InvalidReferredHolder)?;
+            require!(referred.referrer == old_holder, TokenError::InvalidReferredHolder);
+            referred.referrer = new_authority;
+            referred.exit(ctx.program_id)?;
+        }
+
+        ctx.accounts.audit_log.record(AdminAction::ReassignHolder, ctx.accounts.authority.key(), 0)?;
+
+        emit!(HolderReassigned {
+            old_holder,
+            new_authority,
+            referrals_moved: ctx.remaining_accounts.len() as u32,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Reassigned holder data from {} to {}", old_holder, new_authority);
+        Ok(())
+    }
+
     pub fn claim_rewards(
         ctx: Context<ClaimRewards>,
     ) -> Result<()> {
+        require!(!ctx.accounts.rewards_vault.paused, TokenError::ProgramPaused);
+
         let holder_data = &mut ctx.accounts.holder_data;
         let current_time = Clock::get()?.unix_timestamp;
-        
-        // Verify minimum holding period
+        ctx.accounts.rewards_vault.check_claim_eligible(holder_data, ctx.accounts.token_account.amount, current_time)?;
+
+        // Claims are also spaced by the claim cooldown
         require!(
-            current_time - holder_data.last_claim >= token_config::MIN_HOLDING_PERIOD,
-            TokenError::MinHoldingPeriodNotMet
+            current_time - holder_data.last_claim_at >= ctx.accounts.rewards_vault.claim_cooldown,
+            TokenError::ClaimCooldownActive
         );
 
-        // Calculate rewards
-        let holding_period = (current_time - holder_data.last_claim) as u64;
         let balance = ctx.accounts.token_account.amount;
-        let rewards = calculate_rewards(balance, holding_period)?;
+        let rewards = settle_rewards(&mut ctx.accounts.rewards_vault, holder_data, balance, current_time)?;
+
+        // Credit the referrer's bonus on top of the holder's claim, paid by
+        // the referrer's next claim. The referrer's holder PDA must be
+        // passed; the bonus is only skipped when it was closed, or the
+        // referrer reassigned, leaving nothing at that address
+        let referral_bonus_bps = ctx.accounts.rewards_vault.referral_bonus_bps;
+        if holder_data.referrer != Pubkey::default() {
+            let referrer_info = ctx.accounts.referrer_data.as_ref()
+                .ok_or(TokenError::MissingReferrer)?;
+            if referrer_info.data_is_empty() {
+                msg!("Skipped referral bonus, referrer {} has no holder data", holder_data.referrer);
+            } else if referral_bonus_bps > 0 {
+                require_keys_eq!(*referrer_info.owner, crate::ID, TokenError::MissingReferrer);
+                let mut referrer_data = HolderData::try_deserialize(&mut &referrer_info.data.borrow()[..])?;
+                let bonus = checked_cast(rewards as u128 * referral_bonus_bps as u128 / 10_000)?;
+                let bonus = credit_pending_rewards(&mut referrer_data, bonus, ctx.accounts.rewards_vault.max_rewards_per_holder)?;
+                referrer_data.try_serialize(&mut &mut referrer_info.data.borrow_mut()[..])?;
+                msg!("Credited {} referral bonus to {}", bonus, referrer_data.authority);
+            }
+        }
 
-        // Update holder data
-        holder_data.rewards_earned = holder_data.rewards_earned.checked_add(rewards)
+        // Anything credited since the last claim is paid along with the accrual
+        let holder_data = &mut ctx.accounts.holder_data;
+        let rewards = rewards.checked_add(std::mem::take(&mut holder_data.pending_rewards))
             .ok_or(TokenError::ArithmeticOverflow)?;
-        holder_data.last_claim = current_time;
 
-        // Transfer rewards
-        token::mint_to(
-            CpiContext::new(
+        // Native mode mints the accrued tokens
+        if ctx.accounts.rewards_vault.reward_mint == Pubkey::default() {
+            ctx.accounts.rewards_vault.record_mint(rewards, current_time)?;
+            ctx.accounts.stats.record_mint(rewards)?;
+
+            token::mint_to(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::MintTo {
+                        mint: ctx.accounts.mint.to_account_info(),
+                        to: ctx.accounts.token_account.to_account_info(),
+                        authority: ctx.accounts.mint_authority.to_account_info(),
+                    },
+                ),
+                rewards,
+            )?;
+
+            msg!("Claimed {} reward tokens", rewards);
+            return Ok(());
+        }
+
+        // Otherwise pay the USD value of the accrual out of the reward mint
+        // reserve, treating one whole reward token as one dollar
+        let price_feed = ctx.accounts.price_feed.as_ref()
+            .ok_or(TokenError::MissingRewardMintAccounts)?;
+        let reward_mint = ctx.accounts.reward_mint.as_ref()
+            .ok_or(TokenError::MissingRewardMintAccounts)?;
+        let reward_reserve = ctx.accounts.reward_reserve.as_ref()
+            .ok_or(TokenError::MissingRewardMintAccounts)?;
+        let reward_token_account = ctx.accounts.reward_token_account.as_ref()
+            .ok_or(TokenError::MissingRewardMintAccounts)?;
+        let price = Processor::get_token_price(price_feed, &ctx.accounts.rewards_vault)?;
+        let usd_value = usd_value_micros(rewards, price, ctx.accounts.mint.decimals)?;
+        let payout = reward_units_for_usd_micros(usd_value, reward_mint.decimals)?;
+
+        let mint_key = ctx.accounts.mint.key();
+        let seeds: &[&[u8]] = &[b"rewards_vault", mint_key.as_ref(), &[ctx.accounts.rewards_vault.bump]];
+        token::transfer_checked(
+            CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
-                token::MintTo {
-                    mint: ctx.accounts.mint.to_account_info(),
-                    to: ctx.accounts.token_account.to_account_info(),
-                    authority: ctx.accounts.mint_authority.to_account_info(),
+                token::TransferChecked {
+                    from: reward_reserve.to_account_info(),
+                    mint: reward_mint.to_account_info(),
+                    to: reward_token_account.to_account_info(),
+                    authority: ctx.accounts.rewards_vault.to_account_info(),
                 },
+                &[seeds],
             ),
-            rewards,
+            payout,
+            reward_mint.decimals,
         )?;
 
-        msg!("Claimed {} reward tokens", rewards);
+        msg!("Claimed {} reward mint tokens for {} accrued", payout, rewards);
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct InitializeToken<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
-    #[account(
-        init,
-        payer = authority,
-        mint::decimals = token_config::DECIMALS,
-        mint::authority = authority.key(),
-    )]
-    pub mint: Account<'info, Mint>,
-    
-    #[account(
-        init,
-        payer = authority,
-        associated_token::mint = mint,
-        associated_token::authority = authority,
-    )]
-    pub token_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + RewardsVault::LEN
-    )]
-    pub rewards_vault: Account<'info, RewardsVault>,
-    
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub rent: Sysvar<'info, Rent>,
-}
+    pub fn emergency_claim(
+        ctx: Context<EmergencyClaim>,
+    ) -> Result<()> {
+        require!(!ctx.accounts.rewards_vault.paused, TokenError::ProgramPaused);
+        require!(
+            ctx.accounts.rewards_vault.reward_mint == Pubkey::default(),
+            TokenError::EmergencyClaimUnavailable
+        );
 
-#[derive(Accounts)]
-pub struct SecureTransfer<'info> {
-    pub authority: Signer<'info>,
-    
-    #[account(
-        mut,
-        constraint = from.owner == authority.key(),
-    )]
-    pub from: Account<'info, TokenAccount>,
-    
-    #[account(mut)]
-    pub to: Account<'info, TokenAccount>,
-    
-    #[account(
-        mut,
-        constraint = holder_data.authority == authority.key()
-    )]
-    pub holder_data: Account<'info, HolderData>,
-    
-    /// CHECK: This is safe as we validate it using Pyth SDK
-    pub price_feed: AccountInfo<'info>,
-    
-    pub token_program: Program<'info, Token>,
-}
+        let holder_data = &mut ctx.accounts.holder_data;
+        let current_time = Clock::get()?.unix_timestamp;
 
-#[derive(Accounts)]
-pub struct InitializeRewards<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + HolderData::LEN
-    )]
-    pub holder_data: Account<'info, HolderData>,
-    
-    pub system_program: Program<'info, System>,
-}
+        // Skips the claim cooldown but nothing else claim_rewards requires
+        ctx.accounts.rewards_vault.check_claim_eligible(holder_data, ctx.accounts.token_account.amount, current_time)?;
 
-#[derive(Accounts)]
-pub struct ClaimRewards<'info> {
-    pub authority: Signer<'info>,
-    
-    #[account(
-        mut,
-        constraint = holder_data.authority == authority.key()
-    )]
-    pub holder_data: Account<'info, HolderData>,
-    
-    #[account(mut)]
-    pub mint: Account<'info, Mint>,
-    
-    #[account(
-        mut,
-        constraint = token_account.owner == authority.key()
-    )]
-    pub token_account: Account<'info, TokenAccount>,
-    
-    /// CHECK: This is safe because we verify it matches the mint authority
-    #[account(
-        constraint = mint_authority.key() == mint.mint_authority.unwrap()
-    )]
-    pub mint_authority: AccountInfo<'info>,
-    
-    pub token_program: Program<'info, Token>,
-}
+        // No referral bonus is paid on emergency claims
+        let balance = ctx.accounts.token_account.amount;
+        let rewards = settle_rewards(&mut ctx.accounts.rewards_vault, holder_data, balance, current_time)?;
+        ctx.accounts.rewards_vault.record_mint(rewards, current_time)?;
+        ctx.accounts.stats.record_mint(rewards)?;
 
-#[account]
-pub struct RewardsVault {
-    pub authority: Pubkey,
-    pub total_rewards: u64,
-    pub last_update: i64,
-}
+        // The haircut is minted to the treasury instead of the holder
+        let haircut: u64 = checked_cast(rewards as u128 * ctx.accounts.rewards_vault.emergency_haircut_bps as u128 / 10_000)?;
+        for (to, amount) in [
+            (ctx.accounts.token_account.to_account_info(), rewards - haircut),
+            (ctx.accounts.treasury.to_account_info(), haircut),
+        ] {
+            if amount == 0 {
+                continue;
+            }
+            token::mint_to(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::MintTo {
+                        mint: ctx.accounts.mint.to_account_info(),
+                        to,
+                        authority: ctx.accounts.mint_authority.to_account_info(),
+                    },
+                ),
+                amount,
+            )?;
+        }
 
-impl RewardsVault {
-    pub const LEN: usize = 32 + 8 + 8;
-}
+        msg!("Emergency claim of {} reward tokens, {} to the treasury", rewards - haircut, haircut);
+        Ok(())
+    }
 
-#[account]
-pub struct HolderData {
-    pub authority: Pubkey,
-    pub rewards_earned: u64,
-    pub last_claim: i64,
-    pub last_transfer: i64,
-    pub daily_transactions: u64,
-    pub last_transaction_date: i64,
-}
+    pub fn claim_bonus_rewards(
+        ctx: Context<ClaimBonusRewards>,
+    ) -> Result<()> {
+        require!(!ctx.accounts.rewards_vault.paused, TokenError::ProgramPaused);
+
+        let holder_data = &mut ctx.accounts.holder_data;
+        let current_time = Clock::get()?.unix_timestamp;
+
+        // The bonus has the same holding period as the native claim
+        require!(
+            current_time - holder_data.registered_at >= ctx.accounts.rewards_vault.min_holding_period,
+            TokenError::MinHoldingPeriodNotMet
+        );
+
+        // Bonus rewards accrue at their own rate on the lowest native
+        // balance since the last bonus claim, so a top-up just before
+        // claiming earns nothing, and are tracked separately from the
+        // native claim
+        let balance = ctx.accounts.token_account.amount;
+        let rewards = calculate_bonus_rewards(
+            balance.min(holder_data.bonus_min_balance),
+            ctx.accounts.rewards_vault.bonus_rate_bps,
+            holder_data.bonus_last_claim,
+            current_time,
+        )?;
+        require!(rewards > 0, TokenError::NoBonusRewards);
+
+        holder_data.bonus_rewards_earned = holder_data.bonus_rewards_earned.checked_add(rewards)
+            .ok_or(TokenError::ArithmeticOverflow)?;
+        holder_data.bonus_last_claim = current_time;
+        holder_data.bonus_min_balance = balance;
+
+        // Pay out of the bonus reserve, which is owned by the vault PDA
+        let mint_key = ctx.accounts.mint.key();
+        let seeds: &[&[u8]] = &[b"rewards_vault", mint_key.as_ref(), &[ctx.accounts.rewards_vault.bump]];
+        token::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::TransferChecked {
+                    from: ctx.accounts.bonus_reserve.to_account_info(),
+                    mint: ctx.accounts.bonus_mint.to_account_info(),
+                    to: ctx.accounts.bonus_token_account.to_account_info(),
+                    authority: ctx.accounts.rewards_vault.to_account_info(),
+                },
+                &[seeds],
+            ),
+            rewards,
+            ctx.accounts.bonus_mint.decimals,
+        )?;
+
+        msg!("Claimed {} bonus reward tokens", rewards);
+        Ok(())
+    }
+
+    pub fn preview_usd_value(
+        ctx: Context<PreviewUsdValue>,
+        amount: u64,
+    ) -> Result<UsdPreview> {
+        let price = Processor::get_token_price(&ctx.accounts.price_feed, &ctx.accounts.rewards_vault)?;
+        let decimals = ctx.accounts.mint.decimals;
+
+        let preview = UsdPreview {
+            usd_value: usd_value_micros(amount, price, decimals)?,
+            meets_minimum: usd_value_cents(amount, price, decimals)? >= token_config::MIN_PURCHASE_USD,
+        };
+
+        msg!("{} tokens are worth {} (meets minimum: {})", amount, preview.usd_value, preview.meets_minimum);
+        Ok(preview)
+    }
+
+    pub fn tokens_for_usd(
+        ctx: Context<PreviewUsdValue>,
+        usd_cents: u64,
+    ) -> Result<u64> {
+        let price = Processor::get_token_price(&ctx.accounts.price_feed, &ctx.accounts.rewards_vault)?;
+        let amount = tokens_for_usd_cents(usd_cents, price, ctx.accounts.mint.decimals)?;
+
+        msg!("{} cents buys {} tokens", usd_cents, amount);
+        Ok(amount)
+    }
+
+    pub fn rewards_for_period(
+        ctx: Context<ReadVault>,
+        balance: u64,
+        start: i64,
+        end: i64,
+    ) -> Result<u64> {
+        require!(start <= end, TokenError::InvalidPeriod);
+
+        // Same accrual as claims at the current rate, including any part of
+        // the window covered by the campaign, before holder multipliers
+        let growth = reward_index_growth(&ctx.accounts.rewards_vault, start, end)?;
+        let rewards = calculate_rewards(balance, growth, 10_000)?;
+
+        msg!("{} tokens held from {} to {} accrue {} rewards", balance, start, end, rewards);
+        Ok(rewards)
+    }
+
+    pub fn project_emissions(
+        ctx: Context<ProjectEmissions>,
+        horizon_secs: i64,
+    ) -> Result<u64> {
+        require!(horizon_secs >= 0, TokenError::InvalidPeriod);
+
+        // Rewards the whole current supply would accrue from now to the
+        // horizon at the current rate, with any campaign in that window
+        let now = Clock::get()?.unix_timestamp;
+        let supply = ctx.accounts.mint.supply;
+        let emissions = projected_emissions(&ctx.accounts.rewards_vault, supply, now, horizon_secs)?;
+
+        msg!("Projected {} rewards on {} supply over {}s", emissions, supply, horizon_secs);
+        Ok(emissions)
+    }
+
+    pub fn initialize_transfer_pair(
+        ctx: Context<InitializeTransferPair>,
+    ) -> Result<()> {
+        let transfer_pair = &mut ctx.accounts.transfer_pair;
+        transfer_pair.from = ctx.accounts.from.key();
+        transfer_pair.to = ctx.accounts.to.key();
+        transfer_pair.last_transfer = 0;
+        transfer_pair.bump = ctx.bumps.transfer_pair;
+
+        msg!("Initialized transfer pair {} -> {}", transfer_pair.from, transfer_pair.to);
+        Ok(())
+    }
+
+    pub fn create_subscription(
+        ctx: Context<CreateSubscription>,
+        amount: u64,
+        interval: i64,
+        first_due: i64,
+        payments: u32,
+    ) -> Result<()> {
+        // Payments can't come faster than the transfer cooldown allows
+        require!(
+            amount > 0 && payments > 0 && interval >= token_config::TRANSFER_COOLDOWN,
+            TokenError::InvalidConfigValue
+        );
+        let total = amount.checked_mul(payments as u64)
+            .ok_or(TokenError::ArithmeticOverflow)?;
+
+        let subscription = &mut ctx.accounts.subscription;
+        subscription.payer = ctx.accounts.authority.key();
+        subscription.from = ctx.accounts.from.key();
+        subscription.payee = ctx.accounts.to.key();
+        subscription.amount = amount;
+        subscription.interval = interval;
+        subscription.next_due = first_due;
+        subscription.remaining_payments = payments;
+        subscription.bump = ctx.bumps.subscription;
+
+        // Let the subscription PDA pull every remaining payment. The fee
+        // comes out of each payment, so the allowance covers the total
+        token::approve(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Approve {
+                    to: ctx.accounts.from.to_account_info(),
+                    delegate: ctx.accounts.subscription.to_account_info(),
+                    authority: ctx.accounts.authority.to_account_info(),
+                },
+            ),
+            total,
+        )?;
+
+        msg!("Subscription of {} tokens every {}s for {} payments created", amount, interval, payments);
+        Ok(())
+    }
+
+    pub fn execute_subscription(
+        ctx: Context<ExecuteSubscription>,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let subscription = &ctx.accounts.subscription;
+        require!(subscription.remaining_payments > 0, TokenError::SubscriptionExhausted);
+        require!(current_time >= subscription.next_due, TokenError::SubscriptionNotDue);
+        require!(
+            current_time >= ctx.accounts.holder_data.locked_until,
+            TokenError::HolderLocked
+        );
+        check_recipient_owner(&ctx.accounts.rewards_vault, &ctx.accounts.to, ctx.remaining_accounts)?;
+
+        let mut accounts = vec![
+            ctx.accounts.from.to_account_info(),
+            ctx.accounts.to.to_account_info(),
+            ctx.accounts.subscription.to_account_info(),
+            ctx.accounts.price_feed.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.mint.to_account_info(),
+            ctx.accounts.treasury.to_account_info(),
+        ];
+        let mut to_holder_data = recipient_holder_data(
+            &ctx.accounts.to_holder_data,
+            &ctx.accounts.holder_data.key(),
+            &ctx.accounts.to,
+        )?;
+        if to_holder_data.is_some() {
+            accounts.push(ctx.accounts.to_holder_data.to_account_info());
+        }
+
+        // The payment goes through the same checks as secure_transfer, with
+        // the subscription PDA signing as the payer's delegate
+        let from_key = ctx.accounts.from.key();
+        let to_key = ctx.accounts.to.key();
+        let seeds: &[&[u8]] = &[
+            b"subscription",
+            from_key.as_ref(),
+            to_key.as_ref(),
+            &[subscription.bump],
+        ];
+        let amount = subscription.amount;
+        let burned_before = ctx.accounts.rewards_vault.total_burned;
+        Processor::process_transfer(
+            ctx.program_id,
+            &accounts,
+            &mut ctx.accounts.rewards_vault,
+            &mut ctx.accounts.holder_data,
+            amount,
+            false,
+            &[seeds],
+        )?;
+        ctx.accounts.stats.record_burn(ctx.accounts.rewards_vault.total_burned - burned_before)?;
+
+        let streak_floor = ctx.accounts.rewards_vault.streak_balance_floor;
+        record_send(&mut ctx.accounts.from, &mut ctx.accounts.holder_data, streak_floor, current_time)?;
+        if let Some(to_holder_data) = &mut to_holder_data {
+            record_receipt(&mut ctx.accounts.to, to_holder_data, streak_floor, current_time)?;
+            to_holder_data.try_serialize(&mut &mut ctx.accounts.to_holder_data.data.borrow_mut()[..])?;
+        }
+
+        // Advance by whole intervals so the schedule doesn't drift
+        let subscription = &mut ctx.accounts.subscription;
+        subscription.next_due = subscription.next_due.checked_add(subscription.interval)
+            .ok_or(TokenError::ArithmeticOverflow)?;
+        subscription.remaining_payments -= 1;
+
+        msg!("Subscription payment of {} tokens made, {} remaining", amount, subscription.remaining_payments);
+        Ok(())
+    }
+
+    pub fn read_price(
+        ctx: Context<ReadPrice>,
+    ) -> Result<PriceReading> {
+        let reading = Processor::read_price_feed(&ctx.accounts.price_feed, &ctx.accounts.rewards_vault)?;
+
+        msg!("Price: {} (conf {}) published at {}", reading.price, reading.conf, reading.publish_time);
+        Ok(reading)
+    }
+
+    pub fn in_cooldown(
+        ctx: Context<CheckCooldown>,
+    ) -> Result<CooldownStatus> {
+        let rewards_vault = &ctx.accounts.rewards_vault;
+        let holder_data = &ctx.accounts.holder_data;
+
+        // Mirrors process_transfer: an exempt authority has no cooldown, and
+        // in per-pair mode the sender has no global one
+        let exempt = rewards_vault.authority_exempt && holder_data.authority == rewards_vault.authority;
+        let now = Clock::get()?.unix_timestamp;
+        let seconds_remaining = if exempt || rewards_vault.per_pair_cooldown {
+            0
+        } else {
+            holder_data.last_transfer
+                .checked_add(rewards_vault.transfer_cooldown(holder_data.acquired_at, now))
+                .and_then(|cooldown_end| cooldown_end.checked_sub(now))
+                .ok_or(TokenError::ArithmeticOverflow)?
+                .max(0)
+        };
+        let status = CooldownStatus {
+            in_cooldown: seconds_remaining > 0,
+            seconds_remaining,
+        };
+
+        msg!("Holder {} in cooldown: {} ({}s remaining)", holder_data.authority, status.in_cooldown, seconds_remaining);
+        Ok(status)
+    }
+
+    pub fn get_holder_state(
+        ctx: Context<GetHolderState>,
+    ) -> Result<HolderState> {
+        let state = ctx.accounts.holder_data.state();
+
+        msg!("Holder state v{} for {}", state.version, state.authority);
+        Ok(state)
+    }
+
+    pub fn verify_supply(
+        ctx: Context<VerifySupply>,
+    ) -> Result<SupplyReconciliation> {
+        // Positive when the mint holds more than the program accounts for,
+        // e.g. tokens minted outside it; negative for untracked burns
+        let supply = ctx.accounts.mint.supply;
+        let expected_supply = ctx.accounts.stats.expected_supply()?;
+        let discrepancy: i64 = checked_cast(supply as i128 - expected_supply as i128)?;
+        let reconciliation = SupplyReconciliation {
+            supply,
+            expected_supply,
+            discrepancy,
+        };
+
+        msg!("Supply {} against {} expected, discrepancy {}", supply, expected_supply, discrepancy);
+        Ok(reconciliation)
+    }
+
+    pub fn get_holders_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, GetHoldersBatch<'info>>,
+    ) -> Result<Vec<HolderSummary>> {
+        let remaining = ctx.remaining_accounts;
+        require!(
+            !remaining.is_empty() && remaining.len() <= token_config::MAX_HOLDERS_BATCH,
+            TokenError::InvalidHoldersBatch
+        );
+
+        // Only holder PDAs of this mint are read
+        let mint_key = ctx.accounts.mint.key();
+        let mut summaries = Vec::with_capacity(remaining.len());
+        for info in remaining {
+            let holder_data = Account::<HolderData>::try_from(info)?;
+            require_holder_pda(ctx.program_id, &mint_key, info, &holder_data, TokenError::InvalidHoldersBatch)?;
+
+            summaries.push(holder_data.summary());
+        }
+
+        msg!("Read {} holders", summaries.len());
+        Ok(summaries)
+    }
+
+    pub fn distribute_pool<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DistributePool<'info>>,
+        total_pool: u64,
+    ) -> Result<()> {
+        let remaining = ctx.remaining_accounts;
+        require!(
+            !remaining.is_empty() && remaining.len() % 2 == 0,
+            TokenError::InvalidDistributionAccounts
+        );
+
+        // Snapshot balances from (holder_data, token_account) pairs. Only
+        // holder PDAs of this mint are credited
+        let mint_key = ctx.accounts.mint.key();
+        let mut holders: Vec<Account<'info, HolderData>> = Vec::with_capacity(remaining.len() / 2);
+        let mut balances = Vec::with_capacity(remaining.len() / 2);
+        for pair in remaining.chunks(2) {
+            let holder_data = Account::<HolderData>::try_from(&pair[0])?;
+            require_holder_pda(ctx.program_id, &mint_key, &pair[0], &holder_data, TokenError::InvalidDistributionAccounts)?;
+            let token_account = Account::<TokenAccount>::try_from(&pair[1])?;
+            require!(
+                token_account.owner == holder_data.authority
+                    && token_account.mint == mint_key
+                    && holders.iter().all(|h| h.key() != holder_data.key()),
+                TokenError::InvalidDistributionAccounts
+            );
+            balances.push(token_account.amount);
+            holders.push(holder_data);
+        }
+
+        let max_rewards_per_holder = ctx.accounts.rewards_vault.max_rewards_per_holder;
+        let shares = pro_rata_shares(total_pool, &balances)?;
+        let mut distributed: u64 = 0;
+        for (holder_data, share) in holders.iter_mut().zip(shares) {
+            distributed += credit_pending_rewards(holder_data, share, max_rewards_per_holder)?;
+            holder_data.exit(ctx.program_id)?;
+        }
+
+        let rewards_vault = &mut ctx.accounts.rewards_vault;
+        rewards_vault.total_rewards = rewards_vault.total_rewards.checked_add(distributed)
+            .ok_or(TokenError::ArithmeticOverflow)?;
+        rewards_vault.last_update = Clock::get()?.unix_timestamp;
+
+        ctx.accounts.audit_log.record(AdminAction::DistributePool, ctx.accounts.authority.key(), total_pool)?;
+
+        msg!("Distributed {} reward tokens across {} holders, paid on their next claim", distributed, holders.len());
+        Ok(())
+    }
+
+    pub fn sweep_dust<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SweepDust<'info>>,
+    ) -> Result<()> {
+        let remaining = ctx.remaining_accounts;
+        require!(
+            !remaining.is_empty() && remaining.len() % 2 == 0,
+            TokenError::InvalidDistributionAccounts
+        );
+
+        let dust_threshold = ctx.accounts.rewards_vault.dust_threshold;
+        require!(dust_threshold > 0, TokenError::DustSweepDisabled);
+
+        let mint_key = ctx.accounts.mint.key();
+        let vault_key = ctx.accounts.rewards_vault.key();
+        let seeds: &[&[u8]] = &[b"rewards_vault", mint_key.as_ref(), &[ctx.accounts.rewards_vault.bump]];
+
+        // Sweep (holder_data, token_account) pairs whose holder opted in and
+        // approved the vault as delegate; everything else is skipped
+        let mut swept: u64 = 0;
+        let mut swept_accounts = 0;
+        for pair in remaining.chunks(2) {
+            let holder_data = Account::<HolderData>::try_from(&pair[0])?;
+            require_holder_pda(ctx.program_id, &mint_key, &pair[0], &holder_data, TokenError::InvalidDistributionAccounts)?;
+            let token_account = Account::<TokenAccount>::try_from(&pair[1])?;
+            require!(
+                token_account.owner == holder_data.authority && token_account.mint == mint_key,
+                TokenError::InvalidDistributionAccounts
+            );
+
+            let amount = token_account.amount;
+            if amount == 0
+                || amount >= dust_threshold
+                || !holder_data.dust_sweep_consent
+                || token_account.delegate != COption::Some(vault_key)
+                || token_account.delegated_amount < amount
+            {
+                continue;
+            }
+
+            token::transfer_checked(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::TransferChecked {
+                        from: pair[1].clone(),
+                        mint: ctx.accounts.mint.to_account_info(),
+                        to: ctx.accounts.treasury.to_account_info(),
+                        authority: ctx.accounts.rewards_vault.to_account_info(),
+                    },
+                    &[seeds],
+                ),
+                amount,
+                ctx.accounts.mint.decimals,
+            )?;
+            swept = swept.checked_add(amount).ok_or(TokenError::ArithmeticOverflow)?;
+            swept_accounts += 1;
+        }
+
+        ctx.accounts.audit_log.record(AdminAction::SweepDust, ctx.accounts.authority.key(), swept)?;
+
+        msg!("Swept {} dust tokens from {} accounts to the treasury", swept, swept_accounts);
+        Ok(())
+    }
+
+    pub fn set_dust_sweep_consent(
+        ctx: Context<UpdateHolder>,
+        consent: bool,
+    ) -> Result<()> {
+        ctx.accounts.holder_data.dust_sweep_consent = consent;
+
+        msg!("Dust sweep consent for {} set to {}", ctx.accounts.authority.key(), consent);
+        Ok(())
+    }
+
+    pub fn verify_allowlist(
+        ctx: Context<VerifyAllowlist>,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let root = ctx.accounts.rewards_vault.allowlist_root;
+        let holder = ctx.accounts.authority.key();
+        require!(
+            root != [0u8; 32] && verify_merkle_proof(&proof, root, holder),
+            TokenError::InvalidAllowlistProof
+        );
+        ctx.accounts.holder_data.is_verified = true;
+
+        msg!("Verified {} against the allowlist", holder);
+        Ok(())
+    }
+
+    pub fn repair_counters(
+        ctx: Context<RepairCounters>,
+    ) -> Result<()> {
+        let today = day_index(
+            Clock::get()?.unix_timestamp,
+            ctx.accounts.rewards_vault.daily_reset_offset_secs,
+        );
+        let holder_data = &mut ctx.accounts.holder_data;
+
+        // Counters dated in the future would never roll over, so restart them today
+        if holder_data.last_transaction_date <= today {
+            msg!("Counters for {} are consistent", holder_data.authority);
+            return Ok(());
+        }
+
+        holder_data.last_transaction_date = today;
+        holder_data.daily_transactions = 0;
+        if holder_data.last_limit_hit_date > today {
+            holder_data.last_limit_hit_date = 0;
+            holder_data.consecutive_limit_days = 0;
+        }
+
+        msg!("Repaired daily counters for {}", holder_data.authority);
+        Ok(())
+    }
+
+    // Brings the mint's vault PDA up to the current layout in place,
+    // growing the account and defaulting the fields each version added.
+    // A vault already at RewardsVault::VERSION is left as is
+    pub fn migrate_rewards_vault(
+        ctx: Context<MigrateRewardsVault>,
+    ) -> Result<()> {
+        let vault_info = ctx.accounts.rewards_vault.to_account_info();
+        let authority = ctx.accounts.authority.key();
+
+        // Read the header by hand since older layouts won't deserialize
+        let version = {
+            let data = vault_info.try_borrow_data()?;
+            require!(
+                data.len() > 8 + RewardsVault::VERSION_OFFSET && data[..8] == RewardsVault::DISCRIMINATOR,
+                TokenError::InvalidVaultLayout
+            );
+            require_keys_eq!(Pubkey::try_from(&data[8..40]).unwrap(), authority, TokenError::Unauthorized);
+            data[8 + RewardsVault::VERSION_OFFSET]
+        };
+        if version == RewardsVault::VERSION {
+            msg!("Rewards vault already at version {}", version);
+            return Ok(());
+        }
+        require!(
+            (1..RewardsVault::VERSION).contains(&version),
+            TokenError::InvalidVaultLayout
+        );
+
+        // Grow the account to the current layout, topping up rent from the
+        // authority. The new space is zeroed, so fields added since are read
+        // as zero until defaulted below
+        let new_len = 8 + RewardsVault::LEN;
+        let rent_due = Rent::get()?.minimum_balance(new_len).saturating_sub(vault_info.lamports());
+        if rent_due > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.authority.to_account_info(),
+                        to: vault_info.clone(),
+                    },
+                ),
+                rent_due,
+            )?;
+        }
+        vault_info.realloc(new_len, true)?;
+
+        let mut rewards_vault = RewardsVault::try_deserialize(&mut &vault_info.try_borrow_data()?[..])?;
+        if version < 2 {
+            rewards_vault.min_holding_period = token_config::MIN_HOLDING_PERIOD;
+            rewards_vault.reward_index_remainder = 0;
+        }
+        rewards_vault.version = RewardsVault::VERSION;
+        rewards_vault.try_serialize(&mut &mut vault_info.try_borrow_mut_data()?[..])?;
+
+        ctx.accounts.audit_log.record(AdminAction::MigrateRewardsVault, authority, RewardsVault::VERSION as u64)?;
+
+        msg!("Migrated rewards vault from version {} to {}", version, RewardsVault::VERSION);
+        Ok(())
+    }
+
+    // Moves a vault from the original keypair account (authority,
+    // total_rewards, last_update) to the mint's vault PDA, creating its
+    // audit log and stats, and closes the old account into the authority.
+    // Only the mint authority can claim a legacy vault for a mint
+    pub fn migrate_legacy_vault(
+        ctx: Context<MigrateLegacyVault>,
+    ) -> Result<()> {
+        let legacy_info = ctx.accounts.legacy_vault.to_account_info();
+        let legacy = {
+            let data = legacy_info.try_borrow_data()?;
+            require!(
+                data.len() == 8 + LegacyRewardsVault::LEN && data[..8] == RewardsVault::DISCRIMINATOR,
+                TokenError::InvalidVaultLayout
+            );
+            LegacyRewardsVault::deserialize(&mut &data[8..])?
+        };
+        let authority = ctx.accounts.authority.key();
+        require_keys_eq!(legacy.authority, authority, TokenError::Unauthorized);
+
+        // The authority's wallet holds the initial supply, so it stays the
+        // treasury until changed
+        let mint = &ctx.accounts.mint;
+        let treasury = anchor_spl::associated_token::get_associated_token_address(&authority, &mint.key());
+        ctx.accounts.rewards_vault.init(authority, ctx.bumps.rewards_vault, treasury, mint.decimals)?;
+        ctx.accounts.rewards_vault.total_rewards = legacy.total_rewards;
+        ctx.accounts.audit_log.init(ctx.bumps.audit_log);
+
+        // Supply verification starts from the supply at migration, and
+        // holders are counted as they migrate their own accounts
+        ctx.accounts.stats.init(ctx.bumps.stats, mint.supply);
+
+        close_legacy_account(&legacy_info, &ctx.accounts.authority.to_account_info())?;
+
+        ctx.accounts.audit_log.record(AdminAction::MigrateRewardsVault, authority, RewardsVault::VERSION as u64)?;
+
+        msg!("Migrated rewards vault {} to version {}", legacy_info.key(), RewardsVault::VERSION);
+        Ok(())
+    }
+
+    // Moves a holder's original keypair account to their holder PDA under
+    // a migrated vault and closes it into the holder. Counters carry over
+    // and the holding period runs from the legacy last claim. Accrual
+    // starts at the current reward index: the old formula priced rewards
+    // on the balance at claim time, which can't be verified after the fact
+    pub fn migrate_holder_data(
+        ctx: Context<MigrateHolderData>,
+    ) -> Result<()> {
+        let legacy_info = ctx.accounts.legacy_holder_data.to_account_info();
+        let legacy = {
+            let data = legacy_info.try_borrow_data()?;
+            require!(
+                data.len() == 8 + LegacyHolderData::LEN && data[..8] == HolderData::DISCRIMINATOR,
+                TokenError::InvalidHolderLayout
+            );
+            LegacyHolderData::deserialize(&mut &data[8..])?
+        };
+        let authority = ctx.accounts.authority.key();
+        require_keys_eq!(legacy.authority, authority, TokenError::Unauthorized);
+
+        ctx.accounts.stats.add_holder(ctx.accounts.rewards_vault.max_holders)?;
+
+        let reward_index = ctx.accounts.rewards_vault.accrue_index(Clock::get()?.unix_timestamp)?;
+        // The legacy layout never tracked balances, so the minimums start
+        // from zero and the first claim accrues nothing on them
+        let holder_data = &mut ctx.accounts.holder_data;
+        holder_data.init(authority, Pubkey::default(), ctx.bumps.holder_data, reward_index, 0)?;
+        holder_data.rewards_earned = legacy.rewards_earned;
+        holder_data.registered_at = legacy.last_claim;
+        holder_data.last_claim = legacy.last_claim;
+        holder_data.last_claim_at = legacy.last_claim;
+        holder_data.bonus_last_claim = legacy.last_claim;
+        holder_data.last_transfer = legacy.last_transfer;
+        // The legacy day index is UTC, which matches a zero reset offset
+        holder_data.daily_transactions = legacy.daily_transactions;
+        holder_data.last_transaction_date = legacy.last_transaction_date;
+
+        close_legacy_account(&legacy_info, &ctx.accounts.authority.to_account_info())?;
+
+        msg!("Migrated holder data for {}", authority);
+        Ok(())
+    }
+
+    pub fn set_mint_rate_limit(
+        ctx: Context<ConfigureVault>,
+        limit: u64,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::MintRateLimit(limit, ctx.accounts.rewards_vault.decimals))?;
+        ctx.accounts.rewards_vault.mint_rate_limit = limit;
+
+        ctx.accounts.audit_log.record(AdminAction::SetMintRateLimit, ctx.accounts.authority.key(), limit)?;
+
+        msg!("Mint rate limit set to {} per window", limit);
+        Ok(())
+    }
+
+    pub fn set_referral_bonus(
+        ctx: Context<ConfigureVault>,
+        bonus_bps: u16,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::ReferralBonus(bonus_bps))?;
+        ctx.accounts.rewards_vault.referral_bonus_bps = bonus_bps;
+
+        ctx.accounts.audit_log.record(AdminAction::SetReferralBonus, ctx.accounts.authority.key(), bonus_bps as u64)?;
+
+        msg!("Referral bonus set to {} bps", bonus_bps);
+        Ok(())
+    }
+
+    pub fn set_price_unavailable_policy(
+        ctx: Context<ConfigureVault>,
+        policy: PriceUnavailablePolicy,
+    ) -> Result<()> {
+        ctx.accounts.rewards_vault.price_unavailable_policy = policy;
+
+        ctx.accounts.audit_log.record(AdminAction::SetPriceUnavailablePolicy, ctx.accounts.authority.key(), policy as u64)?;
+
+        msg!("Price unavailable policy set to {:?}", policy);
+        Ok(())
+    }
+
+    pub fn set_emergency_haircut(
+        ctx: Context<ConfigureVault>,
+        haircut_bps: u16,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::EmergencyHaircut(haircut_bps))?;
+        ctx.accounts.rewards_vault.emergency_haircut_bps = haircut_bps;
+
+        ctx.accounts.audit_log.record(AdminAction::SetEmergencyHaircut, ctx.accounts.authority.key(), haircut_bps as u64)?;
+
+        msg!("Emergency claim haircut set to {} bps", haircut_bps);
+        Ok(())
+    }
+
+    pub fn set_daily_transaction_limits(
+        ctx: Context<ConfigureVault>,
+        unverified: u64,
+        verified: u64,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::DailyTransactionLimits(unverified, verified))?;
+        let rewards_vault = &mut ctx.accounts.rewards_vault;
+        rewards_vault.max_daily_transactions_unverified = unverified;
+        rewards_vault.max_daily_transactions_verified = verified;
+
+        ctx.accounts.audit_log.record(AdminAction::SetDailyTransactionLimits, ctx.accounts.authority.key(), verified)?;
+
+        msg!("Daily transaction limits set to {} unverified, {} verified", unverified, verified);
+        Ok(())
+    }
+
+    pub fn set_max_holder_balance(
+        ctx: Context<ConfigureVault>,
+        max_holder_balance: u64,
+    ) -> Result<()> {
+        let decimals = ctx.accounts.rewards_vault.decimals;
+        validate_config_value(ConfigValue::MaxHolderBalance(max_holder_balance, decimals))?;
+        ctx.accounts.rewards_vault.max_holder_balance = max_holder_balance;
+
+        ctx.accounts.audit_log.record(AdminAction::SetMaxHolderBalance, ctx.accounts.authority.key(), max_holder_balance)?;
+
+        msg!("Max holder balance set to {}", max_holder_balance);
+        Ok(())
+    }
+
+    pub fn set_trading_start(
+        ctx: Context<ConfigureVault>,
+        trading_start_ts: i64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        validate_config_value(ConfigValue::TradingStart(trading_start_ts, now))?;
+        ctx.accounts.rewards_vault.trading_start_ts = trading_start_ts;
+
+        ctx.accounts.audit_log.record(AdminAction::SetTradingStart, ctx.accounts.authority.key(), checked_cast(trading_start_ts)?)?;
+
+        msg!("Trading start set to {}", trading_start_ts);
+        Ok(())
+    }
+
+    pub fn set_max_holders(
+        ctx: Context<ConfigureVaultWithStats>,
+        max_holders: u64,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::MaxHolders(max_holders, ctx.accounts.stats.holder_count))?;
+        ctx.accounts.rewards_vault.max_holders = max_holders;
+
+        ctx.accounts.audit_log.record(AdminAction::SetMaxHolders, ctx.accounts.authority.key(), max_holders)?;
+
+        msg!("Max holders set to {}", max_holders);
+        Ok(())
+    }
+
+    pub fn set_reward_rate(
+        ctx: Context<ConfigureVault>,
+        rate_bps: u16,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::RewardRate(rate_bps))?;
+
+        // Accrue at the old rate up to now, so the new one only applies
+        // from here on
+        let rewards_vault = &mut ctx.accounts.rewards_vault;
+        rewards_vault.accrue_index(Clock::get()?.unix_timestamp)?;
+        rewards_vault.reward_rate_bps = rate_bps;
+
+        ctx.accounts.audit_log.record(AdminAction::SetRewardRate, ctx.accounts.authority.key(), rate_bps as u64)?;
+
+        msg!("Reward rate set to {} bps", rate_bps);
+        Ok(())
+    }
+
+    pub fn set_campaign(
+        ctx: Context<ConfigureVault>,
+        multiplier_bps: u16,
+        start: i64,
+        end: i64,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::RewardMultiplier(multiplier_bps))?;
+        validate_config_value(ConfigValue::CampaignWindow(start, end))?;
+
+        // Accrue under the old campaign before the new one takes effect
+        let rewards_vault = &mut ctx.accounts.rewards_vault;
+        rewards_vault.accrue_index(Clock::get()?.unix_timestamp)?;
+        rewards_vault.campaign_multiplier_bps = multiplier_bps;
+        rewards_vault.campaign_start = start;
+        rewards_vault.campaign_end = end;
+
+        ctx.accounts.audit_log.record(AdminAction::SetCampaign, ctx.accounts.authority.key(), multiplier_bps as u64)?;
+
+        msg!("Campaign of {} bps set from {} to {}", multiplier_bps, start, end);
+        Ok(())
+    }
+
+    pub fn set_halted_uses_last_known(
+        ctx: Context<ConfigureVault>,
+        enabled: bool,
+    ) -> Result<()> {
+        ctx.accounts.rewards_vault.halted_uses_last_known = enabled;
+
+        ctx.accounts.audit_log.record(AdminAction::SetHaltedUsesLastKnown, ctx.accounts.authority.key(), enabled as u64)?;
+
+        msg!("Halted markets use last known price: {}", enabled);
+        Ok(())
+    }
+
+    pub fn update_config(
+        ctx: Context<ConfigureVaultWithStats>,
+        update: ConfigUpdate,
+    ) -> Result<()> {
+        // Validate every provided value before applying any of them
+        let now = Clock::get()?.unix_timestamp;
+        update.validate(&ctx.accounts.rewards_vault, ctx.accounts.stats.holder_count, now)?;
+
+        let applied = update.apply(
+            &mut ctx.accounts.rewards_vault,
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.authority.key(),
+            now,
+        )?;
+
+        msg!("Applied {} config updates", applied);
+        Ok(())
+    }
+
+    pub fn set_transfer_fee(
+        ctx: Context<ConfigureVault>,
+        fee_bps: u16,
+        burn_bps: u16,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::TransferFee(fee_bps))?;
+        validate_config_value(ConfigValue::FeeBurnShare(burn_bps))?;
+
+        let rewards_vault = &mut ctx.accounts.rewards_vault;
+        rewards_vault.transfer_fee_bps = fee_bps;
+        rewards_vault.fee_burn_bps = burn_bps;
+
+        let actor = ctx.accounts.authority.key();
+        ctx.accounts.audit_log.record(AdminAction::SetTransferFee, actor, fee_bps as u64)?;
+        ctx.accounts.audit_log.record(AdminAction::SetFeeBurnShare, actor, burn_bps as u64)?;
+
+        msg!("Transfer fee set to {} bps with {} bps of it burned", fee_bps, burn_bps);
+        Ok(())
+    }
+
+    pub fn set_reward_mint(
+        ctx: Context<ConfigureVault>,
+        reward_mint: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.rewards_vault.reward_mint = reward_mint;
+
+        ctx.accounts.audit_log.record(AdminAction::SetRewardMint, ctx.accounts.authority.key(), 0)?;
+
+        msg!("Reward mint set to {}", reward_mint);
+        Ok(())
+    }
+
+    pub fn set_treasury(ctx: Context<SetTreasury>) -> Result<()> {
+        let treasury = ctx.accounts.treasury.key();
+        validate_config_value(ConfigValue::BurnAddress(ctx.accounts.rewards_vault.burn_address, treasury))?;
+        ctx.accounts.rewards_vault.treasury = treasury;
+
+        ctx.accounts.audit_log.record(AdminAction::SetTreasury, ctx.accounts.authority.key(), 0)?;
+
+        msg!("Treasury set to {}", treasury);
+        Ok(())
+    }
+
+    // First half of a handoff: records the new owner. Nothing moves until
+    // they accept, so claims keep minting meanwhile. Proposing the current
+    // authority cancels a pending handoff
+    pub fn propose_full_handoff(
+        ctx: Context<ProposeHandoff>,
+        new_owner: Pubkey,
+    ) -> Result<()> {
+        require!(new_owner != Pubkey::default(), TokenError::InvalidConfigValue);
+        let authority = ctx.accounts.authority.key();
+
+        if new_owner == authority {
+            ctx.accounts.rewards_vault.pending_owner = Pubkey::default();
+            ctx.accounts.audit_log.record(AdminAction::ProposeHandoff, authority, 0)?;
+
+            msg!("Cancelled the pending handoff");
+            return Ok(());
+        }
+        ctx.accounts.rewards_vault.pending_owner = new_owner;
+
+        ctx.accounts.audit_log.record(AdminAction::ProposeHandoff, authority, 0)?;
+
+        msg!("Proposed handing off control to {}", new_owner);
+        Ok(())
+    }
+
+    // Second half of a handoff, signed by both the current authority and the
+    // proposed owner: moves the vault authority, mint authority and treasury
+    // to the new owner in one step
+    pub fn accept_full_handoff(ctx: Context<AcceptHandoff>) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        let new_owner = ctx.accounts.new_owner.key();
+
+        if ctx.accounts.mint.mint_authority == COption::Some(authority) {
+            set_token_authority(
+                &ctx.accounts.token_program,
+                ctx.accounts.mint.to_account_info(),
+                ctx.accounts.authority.to_account_info(),
+                AuthorityType::MintTokens,
+                new_owner,
+            )?;
+        }
+        if ctx.accounts.treasury.owner == authority {
+            set_token_authority(
+                &ctx.accounts.token_program,
+                ctx.accounts.treasury.to_account_info(),
+                ctx.accounts.authority.to_account_info(),
+                AuthorityType::AccountOwner,
+                new_owner,
+            )?;
+        }
+
+        let rewards_vault = &mut ctx.accounts.rewards_vault;
+        rewards_vault.authority = new_owner;
+        rewards_vault.pending_owner = Pubkey::default();
+
+        ctx.accounts.audit_log.record(AdminAction::AcceptHandoff, new_owner, 0)?;
+
+        msg!("Control handed off to {}", new_owner);
+        Ok(())
+    }
+
+    pub fn set_burn_address(
+        ctx: Context<ConfigureVault>,
+        burn_address: Pubkey,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::BurnAddress(burn_address, ctx.accounts.rewards_vault.treasury))?;
+        ctx.accounts.rewards_vault.burn_address = burn_address;
+
+        ctx.accounts.audit_log.record(AdminAction::SetBurnAddress, ctx.accounts.authority.key(), 0)?;
+
+        msg!("Burn address set to {}", burn_address);
+        Ok(())
+    }
+
+    pub fn add_approved_authority(
+        ctx: Context<ConfigureVault>,
+        approved: Pubkey,
+    ) -> Result<()> {
+        let approved_authorities = &mut ctx.accounts.rewards_vault.approved_authorities;
+        if !approved_authorities.contains(&approved) {
+            require!(
+                approved_authorities.len() < token_config::MAX_APPROVED_AUTHORITIES,
+                TokenError::ApprovedAuthoritiesFull
+            );
+            approved_authorities.push(approved);
+        }
+
+        ctx.accounts.audit_log.record(AdminAction::AddApprovedAuthority, ctx.accounts.authority.key(), 0)?;
+
+        msg!("Approved {} to initiate transfers", approved);
+        Ok(())
+    }
+
+    pub fn add_internal_account(
+        ctx: Context<ConfigureVault>,
+        account: Pubkey,
+    ) -> Result<()> {
+        let internal_accounts = &mut ctx.accounts.rewards_vault.internal_accounts;
+        if !internal_accounts.contains(&account) {
+            require!(
+                internal_accounts.len() < token_config::MAX_INTERNAL_ACCOUNTS,
+                TokenError::InternalAccountsFull
+            );
+            internal_accounts.push(account);
+        }
+
+        ctx.accounts.audit_log.record(AdminAction::AddInternalAccount, ctx.accounts.authority.key(), 0)?;
+
+        msg!("Marked {} as an internal account", account);
+        Ok(())
+    }
+
+    pub fn remove_internal_account(
+        ctx: Context<ConfigureVault>,
+        account: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.rewards_vault.internal_accounts.retain(|key| *key != account);
+
+        ctx.accounts.audit_log.record(AdminAction::RemoveInternalAccount, ctx.accounts.authority.key(), 0)?;
+
+        msg!("Removed {} from the internal accounts", account);
+        Ok(())
+    }
+
+    pub fn set_block_program_recipients(
+        ctx: Context<ConfigureVault>,
+        enabled: bool,
+    ) -> Result<()> {
+        ctx.accounts.rewards_vault.block_program_recipients = enabled;
+
+        ctx.accounts.audit_log.record(AdminAction::SetBlockProgramRecipients, ctx.accounts.authority.key(), enabled as u64)?;
+
+        msg!("Blocking program-owned recipients set to {}", enabled);
+        Ok(())
+    }
+
+    pub fn add_allowed_recipient_program(
+        ctx: Context<ConfigureVault>,
+        program: Pubkey,
+    ) -> Result<()> {
+        let allowed_programs = &mut ctx.accounts.rewards_vault.allowed_recipient_programs;
+        if !allowed_programs.contains(&program) {
+            require!(
+                allowed_programs.len() < token_config::MAX_ALLOWED_RECIPIENT_PROGRAMS,
+                TokenError::AllowedRecipientProgramsFull
+            );
+            allowed_programs.push(program);
+        }
+
+        ctx.accounts.audit_log.record(AdminAction::AddAllowedRecipientProgram, ctx.accounts.authority.key(), 0)?;
+
+        msg!("Allowed transfers to accounts owned by program {}", program);
+        Ok(())
+    }
+
+    pub fn remove_allowed_recipient_program(
+        ctx: Context<ConfigureVault>,
+        program: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.rewards_vault.allowed_recipient_programs.retain(|key| *key != program);
+
+        ctx.accounts.audit_log.record(AdminAction::RemoveAllowedRecipientProgram, ctx.accounts.authority.key(), 0)?;
+
+        msg!("Removed program {} from the allowed recipient programs", program);
+        Ok(())
+    }
+
+    pub fn remove_approved_authority(
+        ctx: Context<ConfigureVault>,
+        approved: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.rewards_vault.approved_authorities.retain(|key| *key != approved);
+
+        ctx.accounts.audit_log.record(AdminAction::RemoveApprovedAuthority, ctx.accounts.authority.key(), 0)?;
+
+        msg!("Removed {} from the approved authorities", approved);
+        Ok(())
+    }
+
+    pub fn refresh_price_feed(
+        ctx: Context<RefreshPriceFeed>,
+        new_feed: Pubkey,
+    ) -> Result<()> {
+        // The new feed must pass the same checks transfers apply, and its
+        // price replaces the cache so nothing from the old feed is reused
+        let reading = Processor::read_price_feed(&ctx.accounts.new_price_feed, &ctx.accounts.rewards_vault)?;
+
+        let rewards_vault = &mut ctx.accounts.rewards_vault;
+        let old_feed = rewards_vault.price_feed;
+        rewards_vault.price_feed = new_feed;
+        rewards_vault.last_price = reading.price;
+        rewards_vault.last_price_time = Clock::get()?.unix_timestamp;
+
+        ctx.accounts.audit_log.record(AdminAction::RefreshPriceFeed, ctx.accounts.authority.key(), reading.price)?;
+
+        msg!("Price feed moved from {} to {}", old_feed, new_feed);
+        Ok(())
+    }
+
+    pub fn set_daily_reset_offset(
+        ctx: Context<ConfigureVault>,
+        offset_secs: i64,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::DailyResetOffset(offset_secs))?;
+        ctx.accounts.rewards_vault.daily_reset_offset_secs = offset_secs;
+
+        ctx.accounts.audit_log.record(AdminAction::SetDailyResetOffset, ctx.accounts.authority.key(), offset_bits(offset_secs))?;
+
+        msg!("Daily counters now reset at UTC{:+} seconds", offset_secs);
+        Ok(())
+    }
+
+    pub fn set_paused(
+        ctx: Context<ConfigureVault>,
+        paused: bool,
+    ) -> Result<()> {
+        ctx.accounts.rewards_vault.paused = paused;
+
+        ctx.accounts.audit_log.record(AdminAction::SetPaused, ctx.accounts.authority.key(), paused as u64)?;
+
+        msg!("Program paused: {}", paused);
+        Ok(())
+    }
+
+    pub fn set_accrue_on_min_balance(
+        ctx: Context<ConfigureVault>,
+        enabled: bool,
+    ) -> Result<()> {
+        ctx.accounts.rewards_vault.accrue_on_min_balance = enabled;
+
+        ctx.accounts.audit_log.record(AdminAction::SetAccrueOnMinBalance, ctx.accounts.authority.key(), enabled as u64)?;
+
+        msg!("Accrual on the minimum balance since claim set to {}", enabled);
+        Ok(())
+    }
+
+    pub fn set_registrations_paused(
+        ctx: Context<ConfigureVault>,
+        paused: bool,
+    ) -> Result<()> {
+        ctx.accounts.rewards_vault.registrations_paused = paused;
+
+        ctx.accounts.audit_log.record(AdminAction::SetRegistrationsPaused, ctx.accounts.authority.key(), paused as u64)?;
+
+        msg!("New holder registrations paused: {}", paused);
+        Ok(())
+    }
+
+    pub fn clawback(
+        ctx: Context<Clawback>,
+        from: Pubkey,
+        amount: u64,
+    ) -> Result<()> {
+        require!(ctx.accounts.rewards_vault.paused, TokenError::NotPaused);
+
+        // The authority must be the owner or approved delegate of `from`;
+        // the token program enforces this on the burn
+        token::burn(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Burn {
+                    mint: ctx.accounts.mint.to_account_info(),
+                    from: ctx.accounts.from.to_account_info(),
+                    authority: ctx.accounts.authority.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+        ctx.accounts.stats.record_burn(amount)?;
+
+        emit!(ClawbackEvent {
+            authority: ctx.accounts.authority.key(),
+            from,
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        ctx.accounts.audit_log.record(AdminAction::Clawback, ctx.accounts.authority.key(), amount)?;
+
+        msg!("Clawed back {} tokens from {}", amount, from);
+        Ok(())
+    }
+
+    pub fn set_volatility_mode(
+        ctx: Context<ConfigureVault>,
+        duration: i64,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::VolatilityWindow(duration))?;
+
+        // A zero duration switches volatility mode off immediately
+        let until = if duration == 0 {
+            0
+        } else {
+            Clock::get()?.unix_timestamp + duration
+        };
+        ctx.accounts.rewards_vault.volatility_mode_until = until;
+
+        ctx.accounts.audit_log.record(AdminAction::SetVolatilityMode, ctx.accounts.authority.key(), checked_cast(duration)?)?;
+
+        msg!("Volatility mode active until {}", until);
+        Ok(())
+    }
+
+    pub fn set_price_slot_age(
+        ctx: Context<ConfigureVault>,
+        min_slots: u64,
+        max_slots: u64,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::PriceSlotAge(min_slots, max_slots))?;
+        let rewards_vault = &mut ctx.accounts.rewards_vault;
+        rewards_vault.min_price_slot_age = min_slots;
+        rewards_vault.max_price_slot_age = max_slots;
+
+        ctx.accounts.audit_log.record(AdminAction::SetPriceSlotAge, ctx.accounts.authority.key(), min_slots)?;
+
+        msg!("Price slot age set to between {} and {} slots", min_slots, max_slots);
+        Ok(())
+    }
+
+    pub fn set_max_confidence_cents(
+        ctx: Context<ConfigureVault>,
+        max_cents: u64,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::MaxConfidenceCents(max_cents))?;
+        ctx.accounts.rewards_vault.max_confidence_cents = max_cents;
+
+        ctx.accounts.audit_log.record(AdminAction::SetMaxConfidenceCents, ctx.accounts.authority.key(), max_cents)?;
+
+        msg!("Absolute price confidence cap set to {} cents", max_cents);
+        Ok(())
+    }
+
+    pub fn set_long_term_cooldown(
+        ctx: Context<ConfigureVault>,
+        cooldown: i64,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::LongTermCooldown(cooldown))?;
+        ctx.accounts.rewards_vault.long_term_cooldown = cooldown;
+
+        ctx.accounts.audit_log.record(AdminAction::SetLongTermCooldown, ctx.accounts.authority.key(), checked_cast(cooldown)?)?;
+
+        msg!("Long-term holder transfer cooldown set to {}s", cooldown);
+        Ok(())
+    }
+
+    pub fn set_holding_streak(
+        ctx: Context<ConfigureVault>,
+        balance_floor: u64,
+        min_streak: i64,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::HoldingStreak(balance_floor, min_streak, ctx.accounts.rewards_vault.decimals))?;
+        let rewards_vault = &mut ctx.accounts.rewards_vault;
+        rewards_vault.streak_balance_floor = balance_floor;
+        rewards_vault.min_holding_streak = min_streak;
+
+        ctx.accounts.audit_log.record(AdminAction::SetHoldingStreak, ctx.accounts.authority.key(), checked_cast(min_streak)?)?;
+
+        msg!("Claims need {}s held at or above {} tokens", min_streak, balance_floor);
+        Ok(())
+    }
+
+    pub fn set_allowlist_root(
+        ctx: Context<ConfigureVault>,
+        root: [u8; 32],
+    ) -> Result<()> {
+        ctx.accounts.rewards_vault.allowlist_root = root;
+
+        ctx.accounts.audit_log.record(AdminAction::SetAllowlistRoot, ctx.accounts.authority.key(), 0)?;
+
+        msg!("Allowlist root updated");
+        Ok(())
+    }
+
+    pub fn set_large_transfer_confidence(
+        ctx: Context<ConfigureVault>,
+        threshold_cents: u64,
+        max_confidence_bps: u16,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::LargeTransferConfidence(max_confidence_bps))?;
+        let rewards_vault = &mut ctx.accounts.rewards_vault;
+        rewards_vault.large_transfer_threshold_cents = threshold_cents;
+        rewards_vault.large_transfer_max_confidence_bps = max_confidence_bps;
+
+        ctx.accounts.audit_log.record(AdminAction::SetLargeTransferConfidence, ctx.accounts.authority.key(), max_confidence_bps as u64)?;
+
+        msg!("Transfers over {} cents need confidence within {} bps", threshold_cents, max_confidence_bps);
+        Ok(())
+    }
+
+    pub fn set_dust_threshold(
+        ctx: Context<ConfigureVault>,
+        threshold: u64,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::DustThreshold(threshold, ctx.accounts.rewards_vault.decimals))?;
+        ctx.accounts.rewards_vault.dust_threshold = threshold;
+
+        ctx.accounts.audit_log.record(AdminAction::SetDustThreshold, ctx.accounts.authority.key(), threshold)?;
+
+        msg!("Dust threshold set to {}", threshold);
+        Ok(())
+    }
+
+    pub fn set_accrual_delay(
+        ctx: Context<ConfigureVault>,
+        delay: i64,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::AccrualDelay(delay))?;
+        ctx.accounts.rewards_vault.accrual_delay = delay;
+
+        ctx.accounts.audit_log.record(AdminAction::SetAccrualDelay, ctx.accounts.authority.key(), checked_cast(delay)?)?;
+
+        msg!("Reward accrual delay set to {} seconds", delay);
+        Ok(())
+    }
+
+    pub fn set_claim_cooldown(
+        ctx: Context<ConfigureVault>,
+        cooldown: i64,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::ClaimCooldown(cooldown))?;
+        ctx.accounts.rewards_vault.claim_cooldown = cooldown;
+
+        ctx.accounts.audit_log.record(AdminAction::SetClaimCooldown, ctx.accounts.authority.key(), checked_cast(cooldown)?)?;
+
+        msg!("Claim cooldown set to {} seconds", cooldown);
+        Ok(())
+    }
+
+    pub fn set_min_holding_period(
+        ctx: Context<ConfigureVault>,
+        period: i64,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::MinHoldingPeriod(period))?;
+        ctx.accounts.rewards_vault.min_holding_period = period;
+
+        ctx.accounts.audit_log.record(AdminAction::SetMinHoldingPeriod, ctx.accounts.authority.key(), checked_cast(period)?)?;
+
+        msg!("Minimum holding period set to {} seconds", period);
+        Ok(())
+    }
+
+    pub fn set_early_transfer_penalty(
+        ctx: Context<ConfigureVault>,
+        penalty_bps: u16,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::EarlyTransferPenalty(penalty_bps))?;
+        ctx.accounts.rewards_vault.early_transfer_penalty_bps = penalty_bps;
+
+        ctx.accounts.audit_log.record(AdminAction::SetEarlyTransferPenalty, ctx.accounts.authority.key(), penalty_bps as u64)?;
+
+        msg!("Early transfer penalty set to {} bps", penalty_bps);
+        Ok(())
+    }
+
+    pub fn set_authority_exempt(
+        ctx: Context<ConfigureVault>,
+        exempt: bool,
+    ) -> Result<()> {
+        ctx.accounts.rewards_vault.authority_exempt = exempt;
+
+        ctx.accounts.audit_log.record(AdminAction::SetAuthorityExempt, ctx.accounts.authority.key(), exempt as u64)?;
+
+        msg!("Authority transfer limit exemption set to {}", exempt);
+        Ok(())
+    }
+
+    pub fn set_record_rejections(
+        ctx: Context<ConfigureVault>,
+        enabled: bool,
+    ) -> Result<()> {
+        ctx.accounts.rewards_vault.record_rejections = enabled;
+
+        ctx.accounts.audit_log.record(AdminAction::SetRecordRejections, ctx.accounts.authority.key(), enabled as u64)?;
+
+        msg!("Record rejections set to {}", enabled);
+        Ok(())
+    }
+
+    pub fn set_transfer_caps(
+        ctx: Context<ConfigureVault>,
+        max_size: u64,
+        max_balance_bps: u16,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::MaxTransferSize(max_size))?;
+        validate_config_value(ConfigValue::MaxTransferBalanceBps(max_balance_bps))?;
+
+        let rewards_vault = &mut ctx.accounts.rewards_vault;
+        rewards_vault.max_transfer_size = max_size;
+        rewards_vault.max_transfer_balance_bps = max_balance_bps;
+
+        let actor = ctx.accounts.authority.key();
+        ctx.accounts.audit_log.record(AdminAction::SetMaxTransferSize, actor, max_size)?;
+        ctx.accounts.audit_log.record(AdminAction::SetMaxTransferBalanceBps, actor, max_balance_bps as u64)?;
+
+        msg!("Transfer caps set to {} tokens / {} bps of balance", max_size, max_balance_bps);
+        Ok(())
+    }
+
+    pub fn set_require_recipient_registered(
+        ctx: Context<ConfigureVault>,
+        required: bool,
+    ) -> Result<()> {
+        ctx.accounts.rewards_vault.require_recipient_registered = required;
+
+        ctx.accounts.audit_log.record(AdminAction::SetRequireRecipientRegistered, ctx.accounts.authority.key(), required as u64)?;
+
+        msg!("Recipient registration requirement set to {}", required);
+        Ok(())
+    }
+
+    pub fn set_max_reward_per_claim(
+        ctx: Context<ConfigureVault>,
+        max_reward: u64,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::MaxRewardPerClaim(max_reward, ctx.accounts.rewards_vault.decimals))?;
+        ctx.accounts.rewards_vault.max_reward_per_claim = max_reward;
+
+        ctx.accounts.audit_log.record(AdminAction::SetMaxRewardPerClaim, ctx.accounts.authority.key(), max_reward)?;
+
+        msg!("Max reward per claim set to {}", max_reward);
+        Ok(())
+    }
+
+    pub fn set_max_rewards_per_holder(
+        ctx: Context<ConfigureVault>,
+        max_rewards: u64,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::MaxRewardsPerHolder(max_rewards, ctx.accounts.rewards_vault.decimals))?;
+        ctx.accounts.rewards_vault.max_rewards_per_holder = max_rewards;
+
+        ctx.accounts.audit_log.record(AdminAction::SetMaxRewardsPerHolder, ctx.accounts.authority.key(), max_rewards)?;
+
+        msg!("Max rewards per holder set to {}", max_rewards);
+        Ok(())
+    }
+
+    pub fn set_bonus_reward(
+        ctx: Context<ConfigureVault>,
+        bonus_mint: Pubkey,
+        rate_bps: u16,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::BonusRewardRate(rate_bps))?;
+        let rewards_vault = &mut ctx.accounts.rewards_vault;
+        rewards_vault.bonus_mint = bonus_mint;
+        rewards_vault.bonus_rate_bps = rate_bps;
+
+        ctx.accounts.audit_log.record(AdminAction::SetBonusReward, ctx.accounts.authority.key(), rate_bps as u64)?;
+
+        msg!("Bonus reward set to {} at {} bps", bonus_mint, rate_bps);
+        Ok(())
+    }
+
+    pub fn set_per_pair_cooldown(
+        ctx: Context<ConfigureVault>,
+        enabled: bool,
+    ) -> Result<()> {
+        ctx.accounts.rewards_vault.per_pair_cooldown = enabled;
+
+        ctx.accounts.audit_log.record(AdminAction::SetPerPairCooldown, ctx.accounts.authority.key(), enabled as u64)?;
+
+        msg!("Per-pair cooldown set to {}", enabled);
+        Ok(())
+    }
+
+    pub fn set_reward_multiplier(
+        ctx: Context<ConfigureHolder>,
+        holder: Pubkey,
+        multiplier_bps: u16,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::RewardMultiplier(multiplier_bps))?;
+        ctx.accounts.holder_data.reward_multiplier_bps = multiplier_bps;
+
+        ctx.accounts.audit_log.record(AdminAction::SetRewardMultiplier, ctx.accounts.authority.key(), multiplier_bps as u64)?;
+
+        msg!("Reward multiplier for {} set to {} bps", holder, multiplier_bps);
+        Ok(())
+    }
+
+    pub fn set_verified(
+        ctx: Context<ConfigureHolder>,
+        holder: Pubkey,
+        verified: bool,
+    ) -> Result<()> {
+        ctx.accounts.holder_data.is_verified = verified;
+
+        ctx.accounts.audit_log.record(AdminAction::SetVerified, ctx.accounts.authority.key(), verified as u64)?;
+
+        msg!("Verification for {} set to {}", holder, verified);
+        Ok(())
+    }
+
+    pub fn set_accrual_paused(
+        ctx: Context<ConfigureHolder>,
+        holder: Pubkey,
+        paused: bool,
+    ) -> Result<()> {
+        // Bring the index up to now so the pause starts or ends exactly here
+        let now = Clock::get()?.unix_timestamp;
+        let reward_index = ctx.accounts.rewards_vault.accrue_index(now)?;
+        let holder_data = &mut ctx.accounts.holder_data;
+        if paused {
+            holder_data.pause_accrual(now, reward_index);
+        } else {
+            holder_data.resume_accrual(reward_index)?;
+        }
+
+        ctx.accounts.audit_log.record(AdminAction::SetAccrualPaused, ctx.accounts.authority.key(), paused as u64)?;
+
+        msg!("Reward accrual for {} paused: {}", holder, paused);
+        Ok(())
+    }
+
+    pub fn set_locked_until(
+        ctx: Context<ConfigureHolder>,
+        holder: Pubkey,
+        locked_until: i64,
+    ) -> Result<()> {
+        validate_config_value(ConfigValue::LockedUntil(locked_until))?;
+        ctx.accounts.holder_data.locked_until = locked_until;
+
+        ctx.accounts.audit_log.record(AdminAction::SetLockedUntil, ctx.accounts.authority.key(), checked_cast(locked_until)?)?;
+
+        msg!("Transfers from {} locked until {}", holder, locked_until);
+        Ok(())
+    }
+
+    pub fn clear_flag(
+        ctx: Context<ConfigureHolder>,
+        holder: Pubkey,
+    ) -> Result<()> {
+        let holder_data = &mut ctx.accounts.holder_data;
+        holder_data.flagged = false;
+        holder_data.consecutive_limit_days = 0;
+
+        ctx.accounts.audit_log.record(AdminAction::ClearFlag, ctx.accounts.authority.key(), 0)?;
+
+        msg!("Cleared review flag for {}", holder);
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(decimals: u8)]
+pub struct InitializeToken<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        init,
+        payer = authority,
+        mint::decimals = decimals,
+        mint::authority = authority.key(),
+    )]
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = mint,
+        associated_token::authority = authority,
+    )]
+    pub token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + RewardsVault::LEN,
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log", rewards_vault.key().as_ref()],
+        bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Stats::LEN,
+        seeds = [b"stats", mint.key().as_ref()],
+        bump
+    )]
+    pub stats: Account<'info, Stats>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(params: BootstrapParams)]
+pub struct Bootstrap<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        init,
+        payer = authority,
+        mint::decimals = params.decimals,
+        mint::authority = authority.key(),
+    )]
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = mint,
+        associated_token::authority = authority,
+    )]
+    pub token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + RewardsVault::LEN,
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log", rewards_vault.key().as_ref()],
+        bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + HolderData::LEN,
+        seeds = [b"holder", mint.key().as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub holder_data: Account<'info, HolderData>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Stats::LEN,
+        seeds = [b"stats", mint.key().as_ref()],
+        bump
+    )]
+    pub stats: Account<'info, Stats>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, as_delegate: bool)]
+pub struct SecureTransfer<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    // The signer is the owner, or with as_delegate the approved delegate
+    #[account(
+        mut,
+        constraint = if as_delegate {
+            from.delegate == COption::Some(authority.key())
+        } else {
+            from.owner == authority.key()
+        } @ TokenError::InvalidTransferAuthority,
+    )]
+    pub from: Account<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub to: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        address = from.mint
+    )]
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        address = rewards_vault.treasury
+    )]
+    pub treasury: Account<'info, TokenAccount>,
+    
+    // Created on the sender's first transfer. The seeds tie it to the
+    // owner of `from`, and an existing account must already belong to them
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + HolderData::LEN,
+        seeds = [b"holder", mint.key().as_ref(), from.owner.as_ref()],
+        bump,
+        constraint = holder_data.authority == from.owner
+            || holder_data.authority == Pubkey::default()
+    )]
+    pub holder_data: Account<'info, HolderData>,
+    
+    /// CHECK: The recipient's holder PDA, required whether or not they
+    /// registered so a registered recipient's receipt is always recorded.
+    /// Deserialized in the handler unless the account is empty
+    #[account(
+        mut,
+        seeds = [b"holder", mint.key().as_ref(), to.owner.as_ref()],
+        bump
+    )]
+    pub to_holder_data: UncheckedAccount<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"rewards_vault", from.mint.as_ref()],
+        bump = rewards_vault.bump
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        mut,
+        seeds = [b"pair", from.key().as_ref(), to.key().as_ref()],
+        bump = transfer_pair.bump
+    )]
+    pub transfer_pair: Option<Account<'info, TransferPair>>,
+    
+    /// CHECK: This is safe as we validate it using Pyth SDK
+    #[account(address = rewards_vault.price_feed @ TokenError::InvalidPriceFeed)]
+    pub price_feed: AccountInfo<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"stats", mint.key().as_ref()],
+        bump = stats.bump
+    )]
+    pub stats: Account<'info, Stats>,
+    
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeTransferPair<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(constraint = from.owner == authority.key())]
+    pub from: Account<'info, TokenAccount>,
+    
+    pub to: Account<'info, TokenAccount>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + TransferPair::LEN,
+        seeds = [b"pair", from.key().as_ref(), to.key().as_ref()],
+        bump
+    )]
+    pub transfer_pair: Account<'info, TransferPair>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateSubscription<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = from.owner == authority.key()
+    )]
+    pub from: Account<'info, TokenAccount>,
+    
+    #[account(token::mint = from.mint)]
+    pub to: Account<'info, TokenAccount>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Subscription::LEN,
+        seeds = [b"subscription", from.key().as_ref(), to.key().as_ref()],
+        bump
+    )]
+    pub subscription: Account<'info, Subscription>,
+    
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+// Permissionless: anyone may crank a subscription once it is due
+#[derive(Accounts)]
+pub struct ExecuteSubscription<'info> {
+    #[account(
+        mut,
+        seeds = [b"subscription", from.key().as_ref(), to.key().as_ref()],
+        bump = subscription.bump
+    )]
+    pub subscription: Account<'info, Subscription>,
+    
+    #[account(mut)]
+    pub from: Account<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub to: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        address = from.mint
+    )]
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        address = rewards_vault.treasury
+    )]
+    pub treasury: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"holder", mint.key().as_ref(), subscription.payer.as_ref()],
+        bump = holder_data.bump
+    )]
+    pub holder_data: Account<'info, HolderData>,
+    
+    /// CHECK: The recipient's holder PDA, required whether or not they
+    /// registered so a registered recipient's receipt is always recorded.
+    /// Deserialized in the handler unless the account is empty
+    #[account(
+        mut,
+        seeds = [b"holder", mint.key().as_ref(), to.owner.as_ref()],
+        bump
+    )]
+    pub to_holder_data: UncheckedAccount<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"rewards_vault", from.mint.as_ref()],
+        bump = rewards_vault.bump
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    /// CHECK: This is safe as we validate it using Pyth SDK
+    #[account(address = rewards_vault.price_feed @ TokenError::InvalidPriceFeed)]
+    pub price_feed: AccountInfo<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"stats", mint.key().as_ref()],
+        bump = stats.bump
+    )]
+    pub stats: Account<'info, Stats>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRewards<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + HolderData::LEN,
+        seeds = [b"holder", mint.key().as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub holder_data: Account<'info, HolderData>,
+    
+    #[account(
+        seeds = [b"holder", mint.key().as_ref(), referrer_data.authority.as_ref()],
+        bump = referrer_data.bump
+    )]
+    pub referrer_data: Option<Account<'info, HolderData>>,
+    
+    // The holder's balance at registration; omitted, it counts as zero
+    #[account(
+        constraint = token_account.mint == mint.key(),
+        constraint = token_account.owner == authority.key()
+    )]
+    pub token_account: Option<Account<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump = rewards_vault.bump
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        mut,
+        seeds = [b"stats", mint.key().as_ref()],
+        bump = stats.bump
+    )]
+    pub stats: Account<'info, Stats>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseHolder<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"holder", mint.key().as_ref(), authority.key().as_ref()],
+        bump = holder_data.bump
+    )]
+    pub holder_data: Account<'info, HolderData>,
+    
+    #[account(
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump = rewards_vault.bump
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        mut,
+        seeds = [b"stats", mint.key().as_ref()],
+        bump = stats.bump
+    )]
+    pub stats: Account<'info, Stats>,
+}
+
+#[derive(Accounts)]
+#[instruction(old_holder: Pubkey, new_authority: Pubkey)]
+pub struct ReassignHolder<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump = rewards_vault.bump,
+        has_one = authority
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        mut,
+        seeds = [b"audit_log", rewards_vault.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"holder", mint.key().as_ref(), old_holder.as_ref()],
+        bump = old_holder_data.bump
+    )]
+    pub old_holder_data: Account<'info, HolderData>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + HolderData::LEN,
+        seeds = [b"holder", mint.key().as_ref(), new_authority.as_ref()],
+        bump
+    )]
+    pub new_holder_data: Account<'info, HolderData>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRewards<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"holder", mint.key().as_ref(), authority.key().as_ref()],
+        bump = holder_data.bump
+    )]
+    pub holder_data: Account<'info, HolderData>,
+    
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump = rewards_vault.bump
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        mut,
+        constraint = token_account.owner == authority.key(),
+        token::mint = mint
+    )]
+    pub token_account: Account<'info, TokenAccount>,
+    
+    /// CHECK: The referrer's holder PDA, deserialized in the handler
+    /// unless the account is empty because it was closed
+    #[account(
+        mut,
+        seeds = [b"holder", mint.key().as_ref(), holder_data.referrer.as_ref()],
+        bump
+    )]
+    pub referrer_data: Option<UncheckedAccount<'info>>,
+    
+    /// CHECK: This is safe because we verify it matches the mint authority
+    #[account(
+        constraint = mint_authority.key() == mint.mint_authority.unwrap()
+    )]
+    pub mint_authority: AccountInfo<'info>,
+    
+    /// CHECK: This is safe as we validate it using Pyth SDK
+    #[account(address = rewards_vault.price_feed @ TokenError::InvalidPriceFeed)]
+    pub price_feed: Option<AccountInfo<'info>>,
+    
+    #[account(address = rewards_vault.reward_mint)]
+    pub reward_mint: Option<Account<'info, Mint>>,
+    
+    #[account(
+        mut,
+        constraint = reward_reserve.mint == rewards_vault.reward_mint,
+        constraint = reward_reserve.owner == rewards_vault.key()
+    )]
+    pub reward_reserve: Option<Account<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = reward_token_account.mint == rewards_vault.reward_mint,
+        constraint = reward_token_account.owner == authority.key()
+    )]
+    pub reward_token_account: Option<Account<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        seeds = [b"stats", mint.key().as_ref()],
+        bump = stats.bump
+    )]
+    pub stats: Account<'info, Stats>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct EmergencyClaim<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"holder", mint.key().as_ref(), authority.key().as_ref()],
+        bump = holder_data.bump
+    )]
+    pub holder_data: Account<'info, HolderData>,
+    
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump = rewards_vault.bump
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        mut,
+        constraint = token_account.owner == authority.key(),
+        token::mint = mint
+    )]
+    pub token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        address = rewards_vault.treasury
+    )]
+    pub treasury: Account<'info, TokenAccount>,
+    
+    /// CHECK: This is safe because we verify it matches the mint authority
+    #[account(
+        constraint = mint_authority.key() == mint.mint_authority.unwrap()
+    )]
+    pub mint_authority: AccountInfo<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"stats", mint.key().as_ref()],
+        bump = stats.bump
+    )]
+    pub stats: Account<'info, Stats>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimBonusRewards<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"holder", mint.key().as_ref(), authority.key().as_ref()],
+        bump = holder_data.bump
+    )]
+    pub holder_data: Account<'info, HolderData>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump = rewards_vault.bump,
+        constraint = rewards_vault.bonus_mint != Pubkey::default() @ TokenError::BonusRewardNotConfigured
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        constraint = token_account.owner == authority.key(),
+        token::mint = mint
+    )]
+    pub token_account: Account<'info, TokenAccount>,
+    
+    #[account(address = rewards_vault.bonus_mint)]
+    pub bonus_mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        associated_token::mint = bonus_mint,
+        associated_token::authority = rewards_vault
+    )]
+    pub bonus_reserve: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        token::mint = bonus_mint
+    )]
+    pub bonus_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct DistributePool<'info> {
+    pub authority: Signer<'info>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump = rewards_vault.bump
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        mut,
+        seeds = [b"audit_log", rewards_vault.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+}
+
+#[derive(Accounts)]
+pub struct SweepDust<'info> {
+    pub authority: Signer<'info>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        has_one = authority,
+        has_one = treasury,
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump = rewards_vault.bump
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        mut,
+        seeds = [b"audit_log", rewards_vault.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    
+    #[account(mut)]
+    pub treasury: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateHolder<'info> {
+    pub authority: Signer<'info>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"holder", mint.key().as_ref(), authority.key().as_ref()],
+        bump = holder_data.bump
+    )]
+    pub holder_data: Account<'info, HolderData>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyAllowlist<'info> {
+    pub authority: Signer<'info>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump = rewards_vault.bump
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        mut,
+        seeds = [b"holder", mint.key().as_ref(), authority.key().as_ref()],
+        bump = holder_data.bump
+    )]
+    pub holder_data: Account<'info, HolderData>,
+}
+
+#[derive(Accounts)]
+pub struct RepairCounters<'info> {
+    pub authority: Signer<'info>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump = rewards_vault.bump
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        mut,
+        seeds = [b"holder", mint.key().as_ref(), holder_data.authority.as_ref()],
+        bump = holder_data.bump,
+        constraint = holder_data.authority == authority.key()
+            || rewards_vault.authority == authority.key() @ TokenError::Unauthorized
+    )]
+    pub holder_data: Account<'info, HolderData>,
+}
+
+#[derive(Accounts)]
+#[instruction(from_key: Pubkey)]
+pub struct Clawback<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        has_one = authority,
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump = rewards_vault.bump
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        mut,
+        seeds = [b"audit_log", rewards_vault.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    
+    #[account(
+        mut,
+        address = from_key,
+        token::mint = mint
+    )]
+    pub from: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"stats", mint.key().as_ref()],
+        bump = stats.bump
+    )]
+    pub stats: Account<'info, Stats>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateRewardsVault<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    /// CHECK: May be an older, undersized layout; the discriminator and
+    /// authority are checked by hand before it is reallocated
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump
+    )]
+    pub rewards_vault: UncheckedAccount<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"audit_log", rewards_vault.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateLegacyVault<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        constraint = mint.mint_authority == COption::Some(authority.key()) @ TokenError::Unauthorized
+    )]
+    pub mint: Account<'info, Mint>,
+    
+    /// CHECK: Legacy keypair vault in the original layout, which won't
+    /// deserialize; the discriminator and authority are checked by hand
+    #[account(mut, owner = crate::ID)]
+    pub legacy_vault: UncheckedAccount<'info>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + RewardsVault::LEN,
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log", rewards_vault.key().as_ref()],
+        bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Stats::LEN,
+        seeds = [b"stats", mint.key().as_ref()],
+        bump
+    )]
+    pub stats: Account<'info, Stats>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateHolderData<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump = rewards_vault.bump
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    /// CHECK: Legacy keypair holder data in the original layout, which
+    /// won't deserialize; the discriminator and authority are checked by hand
+    #[account(mut, owner = crate::ID)]
+    pub legacy_holder_data: UncheckedAccount<'info>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + HolderData::LEN,
+        seeds = [b"holder", mint.key().as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub holder_data: Account<'info, HolderData>,
+    
+    #[account(
+        mut,
+        seeds = [b"stats", mint.key().as_ref()],
+        bump = stats.bump
+    )]
+    pub stats: Account<'info, Stats>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReadPrice<'info> {
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    /// CHECK: This is safe as we validate it using Pyth SDK
+    #[account(address = rewards_vault.price_feed @ TokenError::InvalidPriceFeed)]
+    pub price_feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReadVault<'info> {
+    pub rewards_vault: Account<'info, RewardsVault>,
+}
+
+#[derive(Accounts)]
+pub struct ProjectEmissions<'info> {
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump = rewards_vault.bump
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+}
+
+#[derive(Accounts)]
+pub struct CheckCooldown<'info> {
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump = rewards_vault.bump
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        seeds = [b"holder", mint.key().as_ref(), holder_data.authority.as_ref()],
+        bump = holder_data.bump
+    )]
+    pub holder_data: Account<'info, HolderData>,
+}
+
+#[derive(Accounts)]
+pub struct GetHolderState<'info> {
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        seeds = [b"holder", mint.key().as_ref(), holder_data.authority.as_ref()],
+        bump = holder_data.bump
+    )]
+    pub holder_data: Account<'info, HolderData>,
+}
+
+#[derive(Accounts)]
+pub struct VerifySupply<'info> {
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        seeds = [b"stats", mint.key().as_ref()],
+        bump = stats.bump
+    )]
+    pub stats: Account<'info, Stats>,
+}
+
+#[derive(Accounts)]
+pub struct GetHoldersBatch<'info> {
+    pub mint: Account<'info, Mint>,
+}
+
+#[derive(Accounts)]
+pub struct PreviewUsdValue<'info> {
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump = rewards_vault.bump
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    /// CHECK: This is safe as we validate it using Pyth SDK
+    #[account(address = rewards_vault.price_feed @ TokenError::InvalidPriceFeed)]
+    pub price_feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(new_feed: Pubkey)]
+pub struct RefreshPriceFeed<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = authority
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        mut,
+        seeds = [b"audit_log", rewards_vault.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    
+    /// CHECK: This is safe as we validate it using Pyth SDK
+    #[account(address = new_feed)]
+    pub new_price_feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureVault<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = authority
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        mut,
+        seeds = [b"audit_log", rewards_vault.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureVaultWithStats<'info> {
+    pub authority: Signer<'info>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump = rewards_vault.bump,
+        has_one = authority
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        mut,
+        seeds = [b"audit_log", rewards_vault.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    
+    #[account(
+        seeds = [b"stats", mint.key().as_ref()],
+        bump = stats.bump
+    )]
+    pub stats: Account<'info, Stats>,
+}
+
+#[derive(Accounts)]
+pub struct SetTreasury<'info> {
+    pub authority: Signer<'info>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump = rewards_vault.bump,
+        has_one = authority
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        mut,
+        seeds = [b"audit_log", rewards_vault.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    
+    // Fees are paid in the token itself, so the treasury must hold this mint
+    #[account(constraint = treasury.mint == mint.key() @ TokenError::InvalidTreasuryMint)]
+    pub treasury: Account<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeHandoff<'info> {
+    pub authority: Signer<'info>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump = rewards_vault.bump,
+        has_one = authority
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        mut,
+        seeds = [b"audit_log", rewards_vault.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptHandoff<'info> {
+    pub authority: Signer<'info>,
+    
+    pub new_owner: Signer<'info>,
+    
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump = rewards_vault.bump,
+        has_one = authority,
+        constraint = rewards_vault.pending_owner == new_owner.key() @ TokenError::NotPendingOwner
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        mut,
+        seeds = [b"audit_log", rewards_vault.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    
+    #[account(
+        mut,
+        address = rewards_vault.treasury
+    )]
+    pub treasury: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(holder: Pubkey)]
+pub struct ConfigureHolder<'info> {
+    pub authority: Signer<'info>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"rewards_vault", mint.key().as_ref()],
+        bump = rewards_vault.bump,
+        has_one = authority
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+    
+    #[account(
+        mut,
+        seeds = [b"audit_log", rewards_vault.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    
+    #[account(
+        mut,
+        seeds = [b"holder", mint.key().as_ref(), holder.as_ref()],
+        bump = holder_data.bump
+    )]
+    pub holder_data: Account<'info, HolderData>,
+}
+
+#[account]
+pub struct RewardsVault {
+    pub authority: Pubkey,
+    pub total_rewards: u64,
+    pub last_update: i64,
+    pub bump: u8,
+    pub require_recipient_registered: bool,
+    pub max_reward_per_claim: u64, // 0 disables the cap
+    pub per_pair_cooldown: bool,
+    pub paused: bool,
+    pub volatility_mode_until: i64,
+    pub record_rejections: bool, // try_secure_transfer counts cooldown and daily-limit rejections instead of failing
+    pub max_transfer_size: u64, // Absolute cap in whole tokens
+    pub max_transfer_balance_bps: u16, // Cap relative to the sender's balance
+    pub version: u8, // RewardsVault::VERSION when written, upgraded by migrate_rewards_vault
+    pub mint_rate_limit: u64, // Max minted per window, 0 disables the limit
+    pub mint_window_start: i64,
+    pub minted_in_window: u64,
+    pub referral_bonus_bps: u16,
+    pub price_unavailable_policy: PriceUnavailablePolicy,
+    pub last_price: u64, // Last good price, for PriceUnavailablePolicy::UseLastKnown
+    pub last_price_time: i64,
+    pub campaign_multiplier_bps: u16, // Global multiplier between campaign_start and campaign_end
+    pub campaign_start: i64,
+    pub campaign_end: i64,
+    pub halted_uses_last_known: bool, // Fall back to last_price instead of blocking on a halt
+    pub treasury: Pubkey, // Token account receiving fees
+    pub transfer_fee_bps: u16,
+    pub fee_burn_bps: u16, // Share of the fee that is burned instead of sent to the treasury
+    pub price_feed: Pubkey, // Pyth account every price read must come from
+    pub daily_reset_offset_secs: i64, // UTC offset of the daily counter reset
+    pub max_rewards_per_holder: u64, // Lifetime cap on rewards_earned, 0 disables the cap
+    pub bonus_mint: Pubkey, // Partner reward token, default pubkey when disabled
+    pub bonus_rate_bps: u16, // Annual bonus rate on the native balance
+    pub max_confidence_cents: u64, // Absolute cap on the price confidence, 0 disables the cap
+    pub dust_threshold: u64, // Balances below this can be swept to the treasury, 0 disables sweeping
+    pub claim_cooldown: i64, // Minimum time between claims once the first one is made
+    pub burn_address: Pubkey, // Transfers to this token account are burned, default pubkey disables
+    pub total_burned: u64, // Burned through secure_transfer, fees included
+    pub approved_authorities: Vec<Pubkey>, // Transfer signer allowlist, empty means open. Subscription payments check the payer
+    pub min_price_slot_age: u64, // Slots a price must age before use, 0 disables
+    pub max_price_slot_age: u64, // Slots after which a price is stale, 0 disables
+    pub accrual_delay: i64, // Rewards start accruing this long after registration
+    pub authority_exempt: bool, // Vault authority's own transfers skip transfer limits
+    pub early_transfer_penalty_bps: u16, // Charged on transfers within min_holding_period of acquisition
+    pub reward_mint: Pubkey, // Stablecoin paying claim_rewards, default pubkey to mint native rewards
+    pub reward_rate_bps: u16, // Annual rewards rate
+    pub reward_index: u128, // Rewards per token accrued since launch, in REWARD_INDEX_PRECISION units
+    pub index_updated_at: i64,
+    pub max_holders: u64, // Cap on registered holders, 0 for no cap; holders who have earned rewards can't close to free a slot
+    pub internal_accounts: Vec<Pubkey>, // Token accounts that skip the USD minimum between each other
+    pub trading_start_ts: i64, // Only the authority may transfer before this, 0 to allow from launch
+    pub emergency_haircut_bps: u16, // Share of an emergency claim sent to the treasury
+    pub max_holder_balance: u64, // Most a recipient may hold after a transfer, 0 for no cap
+    pub max_daily_transactions_unverified: u64,
+    pub max_daily_transactions_verified: u64, // Applies to holders the authority marked verified
+    pub pending_owner: Pubkey, // Proposed by propose_full_handoff, default pubkey when none
+    pub large_transfer_threshold_cents: u64, // Transfers worth more than this use the tighter band
+    pub large_transfer_max_confidence_bps: u16, // Confidence band for large transfers, 0 disables
+    pub allowlist_root: [u8; 32], // Merkle root of holders verify_allowlist accepts, zero disables
+    pub long_term_cooldown: i64, // Transfer cooldown once a holder has held for min_holding_period
+    pub block_program_recipients: bool, // Reject recipients whose owner isn't a wallet, unless allowed below
+    pub allowed_recipient_programs: Vec<Pubkey>, // Programs whose accounts may receive transfers when blocking
+    pub streak_balance_floor: u64, // Dropping below this balance restarts a holder's streak
+    pub min_holding_streak: i64, // Streak needed before claiming, 0 disables
+    pub registrations_paused: bool, // Blocks initialize_rewards only; transfers and claims continue
+    pub accrue_on_min_balance: bool, // Claims accrue on the lowest balance since the last claim
+    pub decimals: u8, // The mint's decimals, for bounds on settings in base units
+    pub min_holding_period: i64, // Time from registration before the first claim, and from acquisition before penalty-free transfers
+    pub reward_index_remainder: u128, // Index growth truncated by the last accrual, carried to the next
+}
+
+impl RewardsVault {
+    // Layout version written by init and migrate_rewards_vault. Version 1
+    // predates min_holding_period and reward_index_remainder; the original
+    // keypair vault, LegacyRewardsVault, has no version
+    pub const VERSION: u8 = 2;
+    // Offset of `version`, which sits before any variable-length field
+    pub const VERSION_OFFSET: usize = 32 + 8 + 8 + 1 + 1 + 8 + 1 + 1 + 8 + 1 + 8 + 2;
+    pub const LEN: usize = 32 + 8 + 8 + 1 + 1 + 8 + 1 + 1 + 8 + 1 + 8 + 2 + 1 + 8 + 8 + 8 + 2 + 1 + 8 + 8 + 2 + 8 + 8 + 1 + 32 + 2 + 2 + 32 + 8 + 8 + 32 + 2 + 8 + 8 + 8 + 32 + 8
+        + 4 + 32 * token_config::MAX_APPROVED_AUTHORITIES + 8 + 8 + 8 + 1 + 2 + 32 + 2 + 16 + 8 + 8
+        + 4 + 32 * token_config::MAX_INTERNAL_ACCOUNTS + 8 + 2 + 8 + 8 + 8 + 32 + 8 + 2 + 32 + 8
+        + 1 + 4 + 32 * token_config::MAX_ALLOWED_RECIPIENT_PROGRAMS + 8 + 8 + 1 + 1 + 1 + 8 + 16;
+
+    // Sets every field to its default for a newly created vault
+    pub fn init(&mut self, authority: Pubkey, bump: u8, treasury: Pubkey, decimals: u8) -> Result<()> {
+        self.authority = authority;
+        self.total_rewards = 0;
+        self.last_update = Clock::get()?.unix_timestamp;
+        self.bump = bump;
+        self.require_recipient_registered = false;
+        self.max_reward_per_claim = 0;
+        self.per_pair_cooldown = false;
+        self.paused = false;
+        self.volatility_mode_until = 0;
+        self.record_rejections = false;
+        self.max_transfer_size = token_config::MAX_TRANSACTION_SIZE;
+        self.max_transfer_balance_bps = 10_000;
+        self.version = RewardsVault::VERSION;
+        self.mint_rate_limit = 0;
+        self.mint_window_start = 0;
+        self.minted_in_window = 0;
+        self.referral_bonus_bps = 0;
+        self.price_unavailable_policy = PriceUnavailablePolicy::Block;
+        self.last_price = 0;
+        self.last_price_time = 0;
+        self.campaign_multiplier_bps = 10_000;
+        self.campaign_start = 0;
+        self.campaign_end = 0;
+        self.halted_uses_last_known = false;
+        self.treasury = treasury;
+        self.transfer_fee_bps = 0;
+        self.fee_burn_bps = 0;
+        self.price_feed = Pubkey::from_str(token_config::PYTH_PRICE_FEED).unwrap();
+        self.daily_reset_offset_secs = 0;
+        self.max_rewards_per_holder = 0;
+        self.bonus_mint = Pubkey::default();
+        self.bonus_rate_bps = 0;
+        self.max_confidence_cents = 0;
+        self.dust_threshold = 0;
+        self.claim_cooldown = token_config::CLAIM_COOLDOWN;
+        self.burn_address = Pubkey::default();
+        self.total_burned = 0;
+        self.approved_authorities = Vec::new();
+        self.min_price_slot_age = 0;
+        self.max_price_slot_age = 0;
+        self.accrual_delay = 0;
+        self.authority_exempt = false;
+        self.early_transfer_penalty_bps = 0;
+        self.reward_mint = Pubkey::default();
+        self.reward_rate_bps = token_config::REWARDS_RATE as u16;
+        self.reward_index = 0;
+        self.index_updated_at = self.last_update;
+        self.max_holders = 0;
+        self.internal_accounts = Vec::new();
+        self.trading_start_ts = 0;
+        self.emergency_haircut_bps = token_config::DEFAULT_EMERGENCY_HAIRCUT_BPS;
+        self.max_holder_balance = 0;
+        self.max_daily_transactions_unverified = token_config::MAX_DAILY_TRANSACTIONS;
+        self.max_daily_transactions_verified = token_config::MAX_DAILY_TRANSACTIONS;
+        self.pending_owner = Pubkey::default();
+        self.large_transfer_threshold_cents = 0;
+        self.large_transfer_max_confidence_bps = 0;
+        self.allowlist_root = [0u8; 32];
+        self.long_term_cooldown = token_config::TRANSFER_COOLDOWN;
+        self.block_program_recipients = false;
+        self.allowed_recipient_programs = Vec::new();
+        self.streak_balance_floor = 0;
+        self.min_holding_streak = 0;
+        self.registrations_paused = false;
+        self.accrue_on_min_balance = false;
+        self.decimals = decimals;
+        self.min_holding_period = token_config::MIN_HOLDING_PERIOD;
+        self.reward_index_remainder = 0;
+
+        Ok(())
+    }
+
+    // Transfer cooldown for a holder, shortened once their tokens have been
+    // held for min_holding_period
+    pub fn transfer_cooldown(&self, acquired_at: i64, now: i64) -> i64 {
+        if now - acquired_at >= self.min_holding_period {
+            self.long_term_cooldown
+        } else {
+            token_config::TRANSFER_COOLDOWN
+        }
+    }
+
+    // Whether a holder has held at or above the streak floor for long enough
+    // to claim
+    pub fn streak_met(&self, holder_data: &HolderData, balance: u64, now: i64) -> bool {
+        self.min_holding_streak == 0
+            || (balance >= self.streak_balance_floor
+                && now - holder_data.holding_streak_start >= self.min_holding_streak)
+    }
+
+    // Requirements shared by claim_rewards and emergency_claim. Settling
+    // against a zero balance pays nothing but still resets last_claim, so
+    // refuse rather than silently drop what accrued. The streak is optional;
+    // the first claim needs the minimum holding period since registration
+    pub fn check_claim_eligible(&self, holder_data: &HolderData, balance: u64, now: i64) -> Result<()> {
+        require!(balance > 0, TokenError::NoBalanceToClaim);
+        require!(self.streak_met(holder_data, balance, now), TokenError::HoldingStreakTooShort);
+        require!(
+            now - holder_data.registered_at >= self.min_holding_period,
+            TokenError::MinHoldingPeriodNotMet
+        );
+        Ok(())
+    }
+
+    // Daily transaction limit for a holder of the given verification level
+    pub fn max_daily_transactions(&self, verified: bool) -> u64 {
+        if verified {
+            self.max_daily_transactions_verified
+        } else {
+            self.max_daily_transactions_unverified
+        }
+    }
+
+    // Brings the reward index up to `now` at the current rate and campaign,
+    // returning the new index. Call before anything that changes either
+    pub fn accrue_index(&mut self, now: i64) -> Result<u128> {
+        if now > self.index_updated_at {
+            let (growth, remainder) = reward_index_growth_with_remainder(
+                self,
+                self.index_updated_at,
+                now,
+                self.reward_index_remainder,
+            )?;
+            self.reward_index = self.reward_index.checked_add(growth)
+                .ok_or(TokenError::ArithmeticOverflow)?;
+            self.reward_index_remainder = remainder;
+            self.index_updated_at = now;
+        }
+
+        Ok(self.reward_index)
+    }
+
+    // Counts `amount` against the windowed mint limit, starting a new
+    // window once the current one has elapsed
+    pub fn record_mint(&mut self, amount: u64, now: i64) -> Result<()> {
+        if now >= self.mint_window_start + token_config::MINT_RATE_WINDOW {
+            self.mint_window_start = now;
+            self.minted_in_window = 0;
+        }
+
+        let minted = self.minted_in_window.checked_add(amount)
+            .ok_or(TokenError::ArithmeticOverflow)?;
+        require!(
+            self.mint_rate_limit == 0 || minted <= self.mint_rate_limit,
+            TokenError::MintRateLimitExceeded
+        );
+        self.minted_in_window = minted;
+
+        Ok(())
+    }
+}
+
+// How transfers behave when the price feed is stale or invalid
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PriceUnavailablePolicy {
+    Block,
+    AllowWithMaxLimits, // Skip the USD minimum but apply UNPRICED_MAX_TRANSACTION_SIZE
+    UseLastKnown, // Use the cached price if younger than LAST_KNOWN_PRICE_MAX_AGE
+}
+
+// Settings changed together by update_config; None leaves a setting as is.
+// Covers every single-valued vault setting except the treasury and price
+// feed, whose setters check the new account itself, and the handoff.
+// The approved authority, internal account and recipient program lists
+// keep their add/remove instructions
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+pub struct ConfigUpdate {
+    pub max_reward_per_claim: Option<u64>,
+    pub max_rewards_per_holder: Option<u64>,
+    pub max_transfer_size: Option<u64>,
+    pub max_transfer_balance_bps: Option<u16>,
+    pub mint_rate_limit: Option<u64>,
+    pub referral_bonus_bps: Option<u16>,
+    pub transfer_fee_bps: Option<u16>,
+    pub fee_burn_bps: Option<u16>,
+    pub daily_reset_offset_secs: Option<i64>,
+    pub claim_cooldown: Option<i64>,
+    pub min_holding_period: Option<i64>,
+    pub accrual_delay: Option<i64>,
+    pub dust_threshold: Option<u64>,
+    pub max_confidence_cents: Option<u64>,
+    pub price_unavailable_policy: Option<PriceUnavailablePolicy>,
+    pub require_recipient_registered: Option<bool>,
+    pub per_pair_cooldown: Option<bool>,
+    pub record_rejections: Option<bool>,
+    pub halted_uses_last_known: Option<bool>,
+    pub emergency_haircut_bps: Option<u16>,
+    pub max_daily_transactions_unverified: Option<u64>,
+    pub max_daily_transactions_verified: Option<u64>,
+    pub max_holder_balance: Option<u64>,
+    pub trading_start_ts: Option<i64>,
+    pub max_holders: Option<u64>,
+    pub reward_rate_bps: Option<u16>,
+    pub campaign_multiplier_bps: Option<u16>,
+    pub campaign_start: Option<i64>,
+    pub campaign_end: Option<i64>,
+    pub reward_mint: Option<Pubkey>,
+    pub bonus_mint: Option<Pubkey>,
+    pub bonus_rate_bps: Option<u16>,
+    pub burn_address: Option<Pubkey>,
+    pub volatility_duration: Option<i64>, // As set_volatility_mode: from now, 0 switches it off
+    pub min_price_slot_age: Option<u64>,
+    pub max_price_slot_age: Option<u64>,
+    pub long_term_cooldown: Option<i64>,
+    pub streak_balance_floor: Option<u64>,
+    pub min_holding_streak: Option<i64>,
+    pub allowlist_root: Option<[u8; 32]>,
+    pub large_transfer_threshold_cents: Option<u64>,
+    pub large_transfer_max_confidence_bps: Option<u16>,
+    pub early_transfer_penalty_bps: Option<u16>,
+    pub authority_exempt: Option<bool>,
+    pub block_program_recipients: Option<bool>,
+    pub paused: Option<bool>,
+    pub accrue_on_min_balance: Option<bool>,
+    pub registrations_paused: Option<bool>,
+}
+
+impl ConfigUpdate {
+    // Checks every provided value the way its set_* instruction would.
+    // Settings validated as a pair are checked with the vault's current
+    // value standing in for whichever half isn't provided
+    pub fn validate(&self, vault: &RewardsVault, holder_count: u64, now: i64) -> Result<()> {
+        let decimals = vault.decimals;
+        let daily_limits = (self.max_daily_transactions_unverified.is_some()
+            || self.max_daily_transactions_verified.is_some())
+        .then(|| ConfigValue::DailyTransactionLimits(
+            self.max_daily_transactions_unverified.unwrap_or(vault.max_daily_transactions_unverified),
+            self.max_daily_transactions_verified.unwrap_or(vault.max_daily_transactions_verified),
+        ));
+        let campaign_window = (self.campaign_start.is_some() || self.campaign_end.is_some())
+            .then(|| ConfigValue::CampaignWindow(
+                self.campaign_start.unwrap_or(vault.campaign_start),
+                self.campaign_end.unwrap_or(vault.campaign_end),
+            ));
+        let price_slot_age = (self.min_price_slot_age.is_some() || self.max_price_slot_age.is_some())
+            .then(|| ConfigValue::PriceSlotAge(
+                self.min_price_slot_age.unwrap_or(vault.min_price_slot_age),
+                self.max_price_slot_age.unwrap_or(vault.max_price_slot_age),
+            ));
+        let holding_streak = (self.streak_balance_floor.is_some() || self.min_holding_streak.is_some())
+            .then(|| ConfigValue::HoldingStreak(
+                self.streak_balance_floor.unwrap_or(vault.streak_balance_floor),
+                self.min_holding_streak.unwrap_or(vault.min_holding_streak),
+                decimals,
+            ));
+
+        let values = [
+            self.max_reward_per_claim.map(|value| ConfigValue::MaxRewardPerClaim(value, decimals)),
+            self.max_rewards_per_holder.map(|value| ConfigValue::MaxRewardsPerHolder(value, decimals)),
+            self.max_transfer_size.map(ConfigValue::MaxTransferSize),
+            self.max_transfer_balance_bps.map(ConfigValue::MaxTransferBalanceBps),
+            self.mint_rate_limit.map(|value| ConfigValue::MintRateLimit(value, decimals)),
+            self.referral_bonus_bps.map(ConfigValue::ReferralBonus),
+            self.transfer_fee_bps.map(ConfigValue::TransferFee),
+            self.fee_burn_bps.map(ConfigValue::FeeBurnShare),
+            self.daily_reset_offset_secs.map(ConfigValue::DailyResetOffset),
+            self.claim_cooldown.map(ConfigValue::ClaimCooldown),
+            self.min_holding_period.map(ConfigValue::MinHoldingPeriod),
+            self.accrual_delay.map(ConfigValue::AccrualDelay),
+            self.dust_threshold.map(|value| ConfigValue::DustThreshold(value, decimals)),
+            self.max_confidence_cents.map(ConfigValue::MaxConfidenceCents),
+            self.emergency_haircut_bps.map(ConfigValue::EmergencyHaircut),
+            daily_limits,
+            self.max_holder_balance.map(|value| ConfigValue::MaxHolderBalance(value, decimals)),
+            self.trading_start_ts.map(|value| ConfigValue::TradingStart(value, now)),
+            self.max_holders.map(|value| ConfigValue::MaxHolders(value, holder_count)),
+            self.reward_rate_bps.map(ConfigValue::RewardRate),
+            self.campaign_multiplier_bps.map(ConfigValue::RewardMultiplier),
+            campaign_window,
+            self.bonus_rate_bps.map(ConfigValue::BonusRewardRate),
+            self.burn_address.map(|value| ConfigValue::BurnAddress(value, vault.treasury)),
+            self.volatility_duration.map(ConfigValue::VolatilityWindow),
+            price_slot_age,
+            self.long_term_cooldown.map(ConfigValue::LongTermCooldown),
+            holding_streak,
+            self.large_transfer_max_confidence_bps.map(ConfigValue::LargeTransferConfidence),
+            self.early_transfer_penalty_bps.map(ConfigValue::EarlyTransferPenalty),
+        ];
+        for value in values.into_iter().flatten() {
+            validate_config_value(value)?;
+        }
+        Ok(())
+    }
+
+    // Writes the provided settings, logging each one, and returns how many
+    // were applied. Call validate first.
+    pub fn apply(&self, vault: &mut RewardsVault, audit_log: &mut AuditLog, actor: Pubkey, now: i64) -> Result<u8> {
+        // Accrue under the old rate and campaign before either changes, as
+        // set_reward_rate and set_campaign do
+        if self.reward_rate_bps.is_some()
+            || self.campaign_multiplier_bps.is_some()
+            || self.campaign_start.is_some()
+            || self.campaign_end.is_some()
+        {
+            vault.accrue_index(now)?;
+        }
+
+        let mut applied = 0;
+        let mut log = |action: AdminAction, param: u64| -> Result<()> {
+            applied += 1;
+            audit_log.record(action, actor, param)
+        };
+
+        if let Some(value) = self.max_reward_per_claim {
+            vault.max_reward_per_claim = value;
+            log(AdminAction::SetMaxRewardPerClaim, value)?;
+        }
+        if let Some(value) = self.max_rewards_per_holder {
+            vault.max_rewards_per_holder = value;
+            log(AdminAction::SetMaxRewardsPerHolder, value)?;
+        }
+        if let Some(value) = self.max_transfer_size {
+            vault.max_transfer_size = value;
+            log(AdminAction::SetMaxTransferSize, value)?;
+        }
+        if let Some(value) = self.max_transfer_balance_bps {
+            vault.max_transfer_balance_bps = value;
+            log(AdminAction::SetMaxTransferBalanceBps, value as u64)?;
+        }
+        if let Some(value) = self.mint_rate_limit {
+            vault.mint_rate_limit = value;
+            log(AdminAction::SetMintRateLimit, value)?;
+        }
+        if let Some(value) = self.referral_bonus_bps {
+            vault.referral_bonus_bps = value;
+            log(AdminAction::SetReferralBonus, value as u64)?;
+        }
+        if let Some(value) = self.transfer_fee_bps {
+            vault.transfer_fee_bps = value;
+            log(AdminAction::SetTransferFee, value as u64)?;
+        }
+        if let Some(value) = self.fee_burn_bps {
+            vault.fee_burn_bps = value;
+            log(AdminAction::SetFeeBurnShare, value as u64)?;
+        }
+        if let Some(value) = self.daily_reset_offset_secs {
+            vault.daily_reset_offset_secs = value;
+            log(AdminAction::SetDailyResetOffset, offset_bits(value))?;
+        }
+        if let Some(value) = self.claim_cooldown {
+            vault.claim_cooldown = value;
+            log(AdminAction::SetClaimCooldown, checked_cast(value)?)?;
+        }
+        if let Some(value) = self.min_holding_period {
+            vault.min_holding_period = value;
+            log(AdminAction::SetMinHoldingPeriod, checked_cast(value)?)?;
+        }
+        if let Some(value) = self.accrual_delay {
+            vault.accrual_delay = value;
+            log(AdminAction::SetAccrualDelay, checked_cast(value)?)?;
+        }
+        if let Some(value) = self.dust_threshold {
+            vault.dust_threshold = value;
+            log(AdminAction::SetDustThreshold, value)?;
+        }
+        if let Some(value) = self.max_confidence_cents {
+            vault.max_confidence_cents = value;
+            log(AdminAction::SetMaxConfidenceCents, value)?;
+        }
+        if let Some(value) = self.price_unavailable_policy {
+            vault.price_unavailable_policy = value;
+            log(AdminAction::SetPriceUnavailablePolicy, value as u64)?;
+        }
+        if let Some(value) = self.require_recipient_registered {
+            vault.require_recipient_registered = value;
+            log(AdminAction::SetRequireRecipientRegistered, value as u64)?;
+        }
+        if let Some(value) = self.per_pair_cooldown {
+            vault.per_pair_cooldown = value;
+            log(AdminAction::SetPerPairCooldown, value as u64)?;
+        }
+        if let Some(value) = self.record_rejections {
+            vault.record_rejections = value;
+            log(AdminAction::SetRecordRejections, value as u64)?;
+        }
+        if let Some(value) = self.halted_uses_last_known {
+            vault.halted_uses_last_known = value;
+            log(AdminAction::SetHaltedUsesLastKnown, value as u64)?;
+        }
+        if let Some(value) = self.emergency_haircut_bps {
+            vault.emergency_haircut_bps = value;
+            log(AdminAction::SetEmergencyHaircut, value as u64)?;
+        }
+        if self.max_daily_transactions_unverified.is_some() || self.max_daily_transactions_verified.is_some() {
+            if let Some(value) = self.max_daily_transactions_unverified {
+                vault.max_daily_transactions_unverified = value;
+            }
+            if let Some(value) = self.max_daily_transactions_verified {
+                vault.max_daily_transactions_verified = value;
+            }
+            log(AdminAction::SetDailyTransactionLimits, vault.max_daily_transactions_verified)?;
+        }
+        if let Some(value) = self.max_holder_balance {
+            vault.max_holder_balance = value;
+            log(AdminAction::SetMaxHolderBalance, value)?;
+        }
+        if let Some(value) = self.trading_start_ts {
+            vault.trading_start_ts = value;
+            log(AdminAction::SetTradingStart, checked_cast(value)?)?;
+        }
+        if let Some(value) = self.max_holders {
+            vault.max_holders = value;
+            log(AdminAction::SetMaxHolders, value)?;
+        }
+        if let Some(value) = self.reward_rate_bps {
+            vault.reward_rate_bps = value;
+            log(AdminAction::SetRewardRate, value as u64)?;
+        }
+        if self.campaign_multiplier_bps.is_some() || self.campaign_start.is_some() || self.campaign_end.is_some() {
+            if let Some(value) = self.campaign_multiplier_bps {
+                vault.campaign_multiplier_bps = value;
+            }
+            if let Some(value) = self.campaign_start {
+                vault.campaign_start = value;
+            }
+            if let Some(value) = self.campaign_end {
+                vault.campaign_end = value;
+            }
+            log(AdminAction::SetCampaign, vault.campaign_multiplier_bps as u64)?;
+        }
+        if let Some(value) = self.reward_mint {
+            vault.reward_mint = value;
+            log(AdminAction::SetRewardMint, 0)?;
+        }
+        if self.bonus_mint.is_some() || self.bonus_rate_bps.is_some() {
+            if let Some(value) = self.bonus_mint {
+                vault.bonus_mint = value;
+            }
+            if let Some(value) = self.bonus_rate_bps {
+                vault.bonus_rate_bps = value;
+            }
+            log(AdminAction::SetBonusReward, vault.bonus_rate_bps as u64)?;
+        }
+        if let Some(value) = self.burn_address {
+            vault.burn_address = value;
+            log(AdminAction::SetBurnAddress, 0)?;
+        }
+        if let Some(value) = self.volatility_duration {
+            vault.volatility_mode_until = if value == 0 { 0 } else { now + value };
+            log(AdminAction::SetVolatilityMode, checked_cast(value)?)?;
+        }
+        if self.min_price_slot_age.is_some() || self.max_price_slot_age.is_some() {
+            if let Some(value) = self.min_price_slot_age {
+                vault.min_price_slot_age = value;
+            }
+            if let Some(value) = self.max_price_slot_age {
+                vault.max_price_slot_age = value;
+            }
+            log(AdminAction::SetPriceSlotAge, vault.min_price_slot_age)?;
+        }
+        if let Some(value) = self.long_term_cooldown {
+            vault.long_term_cooldown = value;
+            log(AdminAction::SetLongTermCooldown, checked_cast(value)?)?;
+        }
+        if self.streak_balance_floor.is_some() || self.min_holding_streak.is_some() {
+            if let Some(value) = self.streak_balance_floor {
+                vault.streak_balance_floor = value;
+            }
+            if let Some(value) = self.min_holding_streak {
+                vault.min_holding_streak = value;
+            }
+            log(AdminAction::SetHoldingStreak, checked_cast(vault.min_holding_streak)?)?;
+        }
+        if let Some(value) = self.allowlist_root {
+            vault.allowlist_root = value;
+            log(AdminAction::SetAllowlistRoot, 0)?;
+        }
+        if self.large_transfer_threshold_cents.is_some() || self.large_transfer_max_confidence_bps.is_some() {
+            if let Some(value) = self.large_transfer_threshold_cents {
+                vault.large_transfer_threshold_cents = value;
+            }
+            if let Some(value) = self.large_transfer_max_confidence_bps {
+                vault.large_transfer_max_confidence_bps = value;
+            }
+            log(AdminAction::SetLargeTransferConfidence, vault.large_transfer_max_confidence_bps as u64)?;
+        }
+        if let Some(value) = self.early_transfer_penalty_bps {
+            vault.early_transfer_penalty_bps = value;
+            log(AdminAction::SetEarlyTransferPenalty, value as u64)?;
+        }
+        if let Some(value) = self.authority_exempt {
+            vault.authority_exempt = value;
+            log(AdminAction::SetAuthorityExempt, value as u64)?;
+        }
+        if let Some(value) = self.block_program_recipients {
+            vault.block_program_recipients = value;
+            log(AdminAction::SetBlockProgramRecipients, value as u64)?;
+        }
+        if let Some(value) = self.paused {
+            vault.paused = value;
+            log(AdminAction::SetPaused, value as u64)?;
+        }
+        if let Some(value) = self.accrue_on_min_balance {
+            vault.accrue_on_min_balance = value;
+            log(AdminAction::SetAccrueOnMinBalance, value as u64)?;
+        }
+        if let Some(value) = self.registrations_paused {
+            vault.registrations_paused = value;
+            log(AdminAction::SetRegistrationsPaused, value as u64)?;
+        }
+
+        Ok(applied)
+    }
+}
+
+// Vault settings applied by bootstrap on top of the defaults
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct BootstrapParams {
+    pub decimals: u8,
+    pub max_transfer_size: u64,
+    pub max_transfer_balance_bps: u16,
+    pub max_reward_per_claim: u64,
+    pub max_rewards_per_holder: u64,
+    pub mint_rate_limit: u64,
+    pub referral_bonus_bps: u16,
+    pub transfer_fee_bps: u16,
+    pub fee_burn_bps: u16,
+    pub daily_reset_offset_secs: i64,
+    pub price_unavailable_policy: PriceUnavailablePolicy,
+    pub require_recipient_registered: bool,
+    pub per_pair_cooldown: bool,
+    pub record_rejections: bool,
+}
+
+// The original vault, a keypair account with no mint of its own. Read by
+// migrate_legacy_vault only
+#[derive(AnchorDeserialize)]
+pub struct LegacyRewardsVault {
+    pub authority: Pubkey,
+    pub total_rewards: u64,
+    pub last_update: i64,
+}
+
+impl LegacyRewardsVault {
+    pub const LEN: usize = 32 + 8 + 8;
+}
+
+#[account]
+pub struct HolderData {
+    pub authority: Pubkey,
+    pub rewards_earned: u64,
+    pub last_claim: i64,
+    pub last_transfer: i64,
+    pub daily_transactions: u64,
+    pub last_transaction_date: i64,
+    pub reward_multiplier_bps: u16,
+    pub flagged: bool,
+    pub consecutive_limit_days: u16,
+    pub last_limit_hit_date: i64,
+    pub cooldown_rejections: u32,
+    pub daily_limit_rejections: u32,
+    pub referrer: Pubkey, // Default pubkey when the holder wasn't referred
+    pub bonus_rewards_earned: u64, // Bonus token rewards, kept apart from rewards_earned
+    pub bonus_last_claim: i64,
+    pub locked_until: i64, // Outbound transfers blocked until this timestamp
+    pub dust_sweep_consent: bool, // Holder allows sweep_dust to move a dust balance
+    pub registered_at: i64, // Start of the holding period for the first claim
+    pub bump: u8, // PDA seeded by [b"holder", mint, authority]
+    pub acquired_at: i64, // Balance-weighted time the holder's tokens were received
+    pub last_index: u128, // Reward index as of the last claim
+    pub is_verified: bool, // Set by the authority after KYC, for the higher daily limit
+    pub accrual_paused: bool, // Rewards stop accruing while the holder is under review
+    pub accrual_paused_at: i64,
+    pub accrual_paused_index: u128, // Reward index when accrual was paused
+    pub rounding_remainder: u128, // Accrual carried to the next claim: the truncated fraction plus anything a capped claim didn't pay
+    pub holding_streak_start: i64, // Last time the balance was seen below the vault's streak floor
+    pub min_balance_since_claim: u64, // Lowest balance seen since the last claim, starting from the balance at registration
+    pub pending_rewards: u64, // Credited outside accrual, e.g. by distribute_pool; paid by the next claim
+    pub last_claim_at: i64, // When the last claim was made; last_claim lags it after a capped claim
+    pub bonus_min_balance: u64, // Lowest balance seen since the last bonus claim, starting from the balance at registration
+}
 
 impl HolderData {
+    pub const LEN: usize = 32 + 8 + 8 + 8 + 8 + 8 + 2 + 1 + 2 + 8 + 4 + 4 + 32 + 8 + 8 + 8 + 1 + 8 + 1 + 8 + 16 + 1 + 1 + 8 + 16 + 16 + 8 + 8 + 8 + 8 + 8;
+
+    // `balance` is the holder's token balance at registration, the first
+    // observation the minimum balances start from
+    pub fn init(&mut self, authority: Pubkey, referrer: Pubkey, bump: u8, reward_index: u128, balance: u64) -> Result<()> {
+        self.authority = authority;
+        self.rewards_earned = 0;
+        self.registered_at = Clock::get()?.unix_timestamp;
+        self.last_claim = self.registered_at;
+        self.last_transfer = 0;
+        self.reward_multiplier_bps = token_config::DEFAULT_REWARD_MULTIPLIER_BPS;
+        self.flagged = false;
+        self.consecutive_limit_days = 0;
+        self.last_limit_hit_date = 0;
+        self.cooldown_rejections = 0;
+        self.daily_limit_rejections = 0;
+        self.referrer = referrer;
+        self.bonus_rewards_earned = 0;
+        self.bonus_last_claim = self.last_claim;
+        self.locked_until = 0;
+        self.dust_sweep_consent = false;
+        self.bump = bump;
+        self.acquired_at = self.registered_at;
+        self.last_index = reward_index;
+        self.is_verified = false;
+        self.accrual_paused = false;
+        self.accrual_paused_at = 0;
+        self.accrual_paused_index = 0;
+        self.rounding_remainder = 0;
+        self.holding_streak_start = self.registered_at;
+        self.min_balance_since_claim = balance;
+        self.pending_rewards = 0;
+        self.last_claim_at = self.registered_at;
+        self.bonus_min_balance = balance;
+
+        Ok(())
+    }
+
+    // Lowers the native and bonus minimum balances to `balance`, for a
+    // balance seen between claims
+    pub fn record_balance(&mut self, balance: u64) {
+        self.min_balance_since_claim = self.min_balance_since_claim.min(balance);
+        self.bonus_min_balance = self.bonus_min_balance.min(balance);
+    }
+
+    // Stops reward accrual at `reward_index`; pausing twice keeps the
+    // original pause point
+    pub fn pause_accrual(&mut self, now: i64, reward_index: u128) {
+        if !self.accrual_paused {
+            self.accrual_paused = true;
+            self.accrual_paused_at = now;
+            self.accrual_paused_index = reward_index;
+        }
+    }
+
+    // Resumes accrual, moving last_index past the index growth while paused
+    // so that interval earns nothing
+    pub fn resume_accrual(&mut self, reward_index: u128) -> Result<()> {
+        if self.accrual_paused {
+            let paused_growth = reward_index.checked_sub(self.accrual_paused_index)
+                .ok_or(TokenError::ArithmeticOverflow)?;
+            self.last_index = self.last_index.checked_add(paused_growth)
+                .ok_or(TokenError::ArithmeticOverflow)?;
+            self.accrual_paused = false;
+            self.accrual_paused_at = 0;
+            self.accrual_paused_index = 0;
+        }
+
+        Ok(())
+    }
+
+    // Reward index the holder has accrued up to, frozen while paused
+    pub fn accrued_index(&self, reward_index: u128) -> u128 {
+        if self.accrual_paused {
+            self.accrual_paused_index
+        } else {
+            reward_index
+        }
+    }
+
+    // Key fields returned per holder by get_holders_batch
+    pub fn summary(&self) -> HolderSummary {
+        HolderSummary {
+            authority: self.authority,
+            rewards_earned: self.rewards_earned,
+            last_claim: self.last_claim,
+            last_transfer: self.last_transfer,
+            daily_transactions: self.daily_transactions,
+            flagged: self.flagged,
+        }
+    }
+
+    // Snapshot returned by get_holder_state
+    pub fn state(&self) -> HolderState {
+        HolderState {
+            version: HolderState::VERSION,
+            authority: self.authority,
+            rewards_earned: self.rewards_earned,
+            last_claim: self.last_claim,
+            last_transfer: self.last_transfer,
+            daily_transactions: self.daily_transactions,
+            last_transaction_date: self.last_transaction_date,
+            reward_multiplier_bps: self.reward_multiplier_bps,
+            flagged: self.flagged,
+            referrer: self.referrer,
+            bonus_rewards_earned: self.bonus_rewards_earned,
+            locked_until: self.locked_until,
+            dust_sweep_consent: self.dust_sweep_consent,
+            registered_at: self.registered_at,
+            acquired_at: self.acquired_at,
+        }
+    }
+}
+
+// The original holder data, a keypair account with no mint of its own.
+// Read by migrate_holder_data only
+#[derive(AnchorDeserialize)]
+pub struct LegacyHolderData {
+    pub authority: Pubkey,
+    pub rewards_earned: u64,
+    pub last_claim: i64,
+    pub last_transfer: i64,
+    pub daily_transactions: u64,
+    pub last_transaction_date: i64,
+}
+
+impl LegacyHolderData {
     pub const LEN: usize = 32 + 8 + 8 + 8 + 8 + 8;
 }
 
+// Holder state returned by get_holder_state. New fields are appended and
+// bump VERSION so clients can tell which layout they received
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct HolderState {
+    pub version: u8,
+    pub authority: Pubkey,
+    pub rewards_earned: u64,
+    pub last_claim: i64,
+    pub last_transfer: i64,
+    pub daily_transactions: u64,
+    pub last_transaction_date: i64,
+    pub reward_multiplier_bps: u16,
+    pub flagged: bool,
+    pub referrer: Pubkey,
+    pub bonus_rewards_earned: u64,
+    pub locked_until: i64,
+    pub dust_sweep_consent: bool,
+    pub registered_at: i64,
+    pub acquired_at: i64, // v2
+}
+
+impl HolderState {
+    pub const VERSION: u8 = 2;
+}
+
+#[account]
+pub struct AuditLog {
+    pub entries: Vec<AuditEntry>, // Ring buffer, oldest overwritten once full
+    pub next_index: u16,
+    pub total_entries: u64,
+    pub bump: u8,
+}
+
+impl AuditLog {
+    pub const LEN: usize = 4 + AuditEntry::LEN * token_config::AUDIT_LOG_CAPACITY + 2 + 8 + 1;
+
+    pub fn init(&mut self, bump: u8) {
+        self.entries = Vec::with_capacity(token_config::AUDIT_LOG_CAPACITY);
+        self.next_index = 0;
+        self.total_entries = 0;
+        self.bump = bump;
+    }
+
+    pub fn record(&mut self, action: AdminAction, actor: Pubkey, param: u64) -> Result<()> {
+        let entry = AuditEntry {
+            action,
+            actor,
+            timestamp: Clock::get()?.unix_timestamp,
+            param,
+        };
+
+        let index = self.next_index as usize;
+        if index < self.entries.len() {
+            self.entries[index] = entry.clone();
+        } else {
+            self.entries.push(entry.clone());
+        }
+        self.next_index = ((index + 1) % token_config::AUDIT_LOG_CAPACITY) as u16;
+        self.total_entries = self.total_entries.saturating_add(1);
+
+        // Indexers rebuild the full history from these once entries wrap
+        emit!(AdminActionLogged {
+            sequence: self.total_entries,
+            entry,
+        });
+        Ok(())
+    }
+}
+
+// Program-wide counters, a PDA seeded by [b"stats", mint]
+#[account]
+pub struct Stats {
+    pub holder_count: u64,
+    pub bump: u8,
+    pub initial_supply: u64, // Supply when the stats were created, the initial distribution
+    pub total_minted: u64, // Reward tokens minted by the program since
+    pub total_burned: u64, // Tokens burned by the program since
+}
+
+impl Stats {
+    pub const LEN: usize = 8 + 1 + 8 + 8 + 8;
+
+    pub fn init(&mut self, bump: u8, initial_supply: u64) {
+        self.holder_count = 0;
+        self.bump = bump;
+        self.initial_supply = initial_supply;
+        self.total_minted = 0;
+        self.total_burned = 0;
+    }
+
+    pub fn record_mint(&mut self, amount: u64) -> Result<()> {
+        self.total_minted = self.total_minted.checked_add(amount)
+            .ok_or(TokenError::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    pub fn record_burn(&mut self, amount: u64) -> Result<()> {
+        self.total_burned = self.total_burned.checked_add(amount)
+            .ok_or(TokenError::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    // Supply the program's own mints and burns account for
+    pub fn expected_supply(&self) -> Result<u64> {
+        self.initial_supply
+            .checked_add(self.total_minted)
+            .and_then(|supply| supply.checked_sub(self.total_burned))
+            .ok_or(TokenError::ArithmeticOverflow.into())
+    }
+
+    // Counts a newly registered holder, up to `max_holders` (0 for no cap)
+    pub fn add_holder(&mut self, max_holders: u64) -> Result<()> {
+        require!(
+            max_holders == 0 || self.holder_count < max_holders,
+            TokenError::MaxHoldersReached
+        );
+        self.holder_count = self.holder_count.checked_add(1)
+            .ok_or(TokenError::ArithmeticOverflow)?;
+
+        Ok(())
+    }
+
+    pub fn remove_holder(&mut self) {
+        self.holder_count = self.holder_count.saturating_sub(1);
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct AuditEntry {
+    pub action: AdminAction,
+    pub actor: Pubkey,
+    pub timestamp: i64,
+    pub param: u64,
+}
+
+impl AuditEntry {
+    pub const LEN: usize = 1 + 32 + 8 + 8;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdminAction {
+    SetPaused,
+    Clawback,
+    DistributePool,
+    SetVolatilityMode,
+    SetRecordRejections,
+    SetMaxTransferSize,
+    SetMaxTransferBalanceBps,
+    SetRequireRecipientRegistered,
+    SetMaxRewardPerClaim,
+    SetPerPairCooldown,
+    SetRewardMultiplier,
+    ClearFlag,
+    MigrateRewardsVault,
+    SetMintRateLimit,
+    SetReferralBonus,
+    SetPriceUnavailablePolicy,
+    SetCampaign,
+    SetHaltedUsesLastKnown,
+    SetTransferFee,
+    SetFeeBurnShare,
+    RefreshPriceFeed,
+    SetDailyResetOffset,
+    SetMaxRewardsPerHolder,
+    SetBonusReward,
+    SetMaxConfidenceCents,
+    SetLockedUntil,
+    SetDustThreshold,
+    SweepDust,
+    SetClaimCooldown,
+    SetBurnAddress,
+    AddApprovedAuthority,
+    RemoveApprovedAuthority,
+    SetPriceSlotAge,
+    SetAccrualDelay,
+    SetAuthorityExempt,
+    SetEarlyTransferPenalty,
+    SetRewardMint,
+    SetRewardRate,
+    SetMaxHolders,
+    AddInternalAccount,
+    RemoveInternalAccount,
+    SetTradingStart,
+    SetEmergencyHaircut,
+    SetMaxHolderBalance,
+    SetDailyTransactionLimits,
+    SetVerified,
+    SetTreasury,
+    SetAccrualPaused,
+    ProposeHandoff,
+    AcceptHandoff,
+    SetLargeTransferConfidence,
+    SetAllowlistRoot,
+    SetLongTermCooldown,
+    SetBlockProgramRecipients,
+    AddAllowedRecipientProgram,
+    RemoveAllowedRecipientProgram,
+    SetHoldingStreak,
+    ReassignHolder,
+    SetRegistrationsPaused,
+    SetAccrueOnMinBalance,
+    SetMinHoldingPeriod,
+}
+
+#[account]
+pub struct TransferPair {
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub last_transfer: i64,
+    pub bump: u8,
+}
+
+impl TransferPair {
+    pub const LEN: usize = 32 + 32 + 8 + 1;
+}
+
+#[account]
+pub struct Subscription {
+    pub payer: Pubkey,
+    pub from: Pubkey, // Payer's token account, which delegates to this PDA
+    pub payee: Pubkey, // Recipient token account
+    pub amount: u64,
+    pub interval: i64,
+    pub next_due: i64,
+    pub remaining_payments: u32,
+    pub bump: u8,
+}
+
+impl Subscription {
+    pub const LEN: usize = 32 + 32 + 32 + 8 + 8 + 8 + 4 + 1;
+}
+
+#[event]
+pub struct AdminActionLogged {
+    pub sequence: u64,
+    pub entry: AuditEntry,
+}
+
+#[event]
+pub struct RewardAccrualClamped {
+    pub holder: Pubkey,
+    pub requested: u64,
+    pub credited: u64,
+    pub cap: u64,
+}
+
+#[event]
+pub struct HolderReassigned {
+    pub old_holder: Pubkey,
+    pub new_authority: Pubkey,
+    pub referrals_moved: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ClawbackEvent {
+    pub authority: Pubkey,
+    pub from: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+// USD valuation returned by preview_usd_value, in USD with 6 decimals
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct UsdPreview {
+    pub usd_value: u64,
+    pub meets_minimum: bool,
+}
+
+// Mint supply against the program's accounting, returned by verify_supply
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SupplyReconciliation {
+    pub supply: u64,
+    pub expected_supply: u64,
+    pub discrepancy: i64, // supply - expected_supply
+}
+
+// Per-holder entry returned by get_holders_batch, kept small so a full
+// batch fits in return data
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct HolderSummary {
+    pub authority: Pubkey,
+    pub rewards_earned: u64,
+    pub last_claim: i64,
+    pub last_transfer: i64,
+    pub daily_transactions: u64,
+    pub flagged: bool,
+}
+
+// Sender cooldown returned by in_cooldown
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct CooldownStatus {
+    pub in_cooldown: bool,
+    pub seconds_remaining: i64,
+}
+
+// Reason codes reported by TransferRejected
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RejectionReason {
+    Cooldown,
+    DailyLimit,
+}
+
+impl RejectionReason {
+    pub fn error(self) -> TokenError {
+        match self {
+            RejectionReason::Cooldown => TokenError::TransferCooldownActive,
+            RejectionReason::DailyLimit => TokenError::DailyLimitExceeded,
+        }
+    }
+}
+
+#[event]
+pub struct TransferRejected {
+    pub holder: Pubkey,
+    pub reason: RejectionReason,
+    pub timestamp: i64,
+}
+
+// Validated price returned by read_price, scaled to 6 decimals
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct PriceReading {
+    pub price: u64,
+    pub conf: u64,
+    pub publish_time: i64,
+}
+
 #[error_code]
 pub enum TokenError {
-    #[msg("Transfer amount exceeds 50% of balance")]
+    #[msg("Transfer amount exceeds the allowed share of balance")]
     TransferAmountTooLarge,
     #[msg("Transfer cooldown period is still active")]
     TransferCooldownActive,
@@ -374,54 +4561,737 @@ pub enum TokenError {
     DailyLimitExceeded,
     #[msg("Price feed confidence interval too high")]
     PriceConfidenceTooLow,
+    #[msg("Recipient has no registered holder data")]
+    RecipientNotRegistered,
+    #[msg("Reward multiplier exceeds the maximum")]
+    InvalidRewardMultiplier,
+    #[msg("Per-pair cooldown requires the transfer pair account")]
+    MissingTransferPair,
+    #[msg("Config value is out of range")]
+    InvalidConfigValue,
+    #[msg("Invalid or duplicate holder accounts for distribution")]
+    InvalidDistributionAccounts,
+    #[msg("Holder is flagged for review")]
+    HolderFlagged,
+    #[msg("Program is paused")]
+    ProgramPaused,
+    #[msg("Program must be paused for this action")]
+    NotPaused,
+    #[msg("Account is not a legacy rewards vault")]
+    InvalidVaultLayout,
+    #[msg("Signer is not the vault authority")]
+    Unauthorized,
+    #[msg("Mint rate limit exceeded for the current window")]
+    MintRateLimitExceeded,
+    #[msg("Holders cannot refer themselves")]
+    SelfReferral,
+    #[msg("Referrer holder data is missing or does not match")]
+    MissingReferrer,
+    #[msg("Referrer was referred by this holder")]
+    ReferralLoop,
+    #[msg("Market is halted")]
+    MarketHalted,
+    #[msg("Price status is unknown")]
+    PriceUnknown,
+    #[msg("No bonus reward token is configured")]
+    BonusRewardNotConfigured,
+    #[msg("No bonus rewards have accrued")]
+    NoBonusRewards,
+    #[msg("Holder is locked and cannot transfer yet")]
+    HolderLocked,
+    #[msg("Dust sweeping is disabled")]
+    DustSweepDisabled,
+    #[msg("Claim cooldown period is still active")]
+    ClaimCooldownActive,
+    #[msg("Signer is not an approved transfer authority")]
+    AuthorityNotApproved,
+    #[msg("Approved authority list is full")]
+    ApprovedAuthoritiesFull,
+    #[msg("Price was published too few slots ago")]
+    PriceSlotTooRecent,
+    #[msg("Sender and recipient holder data are the same account")]
+    SelfTransfer,
+    #[msg("Claiming in the reward mint needs the price feed and reward token accounts")]
+    MissingRewardMintAccounts,
+    #[msg("Subscription payment is not due yet")]
+    SubscriptionNotDue,
+    #[msg("Subscription has no payments remaining")]
+    SubscriptionExhausted,
+    #[msg("Signer is neither the token account owner nor its delegate")]
+    InvalidTransferAuthority,
+    #[msg("Transfer exceeds the delegate's approved allowance")]
+    DelegateAllowanceExceeded,
+    #[msg("Maximum number of holders reached")]
+    MaxHoldersReached,
+    #[msg("Recipient balance after the transfer doesn't match the expected balance")]
+    PostBalanceMismatch,
+    #[msg("Internal account list is full")]
+    InternalAccountsFull,
+    #[msg("Period ends before it starts")]
+    InvalidPeriod,
+    #[msg("Trading has not started yet")]
+    TradingNotStarted,
+    #[msg("Emergency claims are only available for native token rewards")]
+    EmergencyClaimUnavailable,
+    #[msg("Transfer would take the recipient over the maximum holder balance")]
+    MaxHolderBalanceExceeded,
+    #[msg("Treasury token account must hold the program's mint")]
+    InvalidTreasuryMint,
+    #[msg("Cannot claim rewards with a zero balance")]
+    NoBalanceToClaim,
+    #[msg("Signer is not the proposed owner")]
+    NotPendingOwner,
+    #[msg("Price confidence is too wide for a transfer this large")]
+    LargeTransferConfidenceTooLow,
+    #[msg("Holder batch must list between 1 and 15 holder accounts of this mint")]
+    InvalidHoldersBatch,
+    #[msg("Allowlist proof does not match the configured root")]
+    InvalidAllowlistProof,
+    #[msg("Recipient token account's owner must be passed as a remaining account")]
+    MissingRecipientOwner,
+    #[msg("Recipient is owned by a program that is not allowed to receive transfers")]
+    RecipientProgramNotAllowed,
+    #[msg("Allowed recipient program list is full")]
+    AllowedRecipientProgramsFull,
+    #[msg("Holding streak at the minimum balance is too short to claim")]
+    HoldingStreakTooShort,
+    #[msg("New holder registrations are paused")]
+    RegistrationsPaused,
+    #[msg("Account is not holder data referred by the reassigned holder")]
+    InvalidReferredHolder,
+    #[msg("Holder data can't be closed while the holder is restricted or has transacted today")]
+    HolderCloseRestricted,
+    #[msg("Account is not legacy holder data")]
+    InvalidHolderLayout,
+    #[msg("Holder data that has earned rewards can't be closed")]
+    HolderHasEarnedRewards,
 }
 
-// Helper function to calculate rewards
-fn calculate_rewards(balance: u64, holding_period: u64) -> Result<u64> {
-    // Annual rate in basis points (e.g., 500 = 5%)
-    let annual_rate = token_config::REWARDS_RATE;
-    
-    // Calculate rewards: balance * (rate/10000) * (holding_period/31536000)
-    // where 31536000 is seconds in a year
-    let rewards = balance
-        .checked_mul(annual_rate as u64)
+// Helper function to calculate rewards from the growth of the reward
+// index, scaled by the holder's multiplier
+fn calculate_rewards(
+    balance: u64,
+    index_delta: u128,
+    multiplier_bps: u16,
+) -> Result<u64> {
+    Ok(calculate_rewards_with_remainder(balance, index_delta, multiplier_bps, 0)?.0)
+}
+
+// Helper function to calculate rewards like calculate_rewards, adding the
+// truncated fraction carried from the last claim and returning the new
+// fraction (in 1 / (REWARD_INDEX_PRECISION * 10000) units) alongside
+fn calculate_rewards_with_remainder(
+    balance: u64,
+    index_delta: u128,
+    multiplier_bps: u16,
+    remainder: u128,
+) -> Result<(u64, u128)> {
+    let scale = token_config::REWARD_INDEX_PRECISION * 10_000;
+    let scaled = (balance as u128)
+        .checked_mul(index_delta)
         .ok_or(TokenError::ArithmeticOverflow)?
-        .checked_mul(holding_period)
+        .checked_mul(multiplier_bps as u128)
         .ok_or(TokenError::ArithmeticOverflow)?
-        .checked_div(10000)
+        .checked_add(remainder)
+        .ok_or(TokenError::ArithmeticOverflow)?;
+
+    Ok((checked_cast(scaled / scale)?, scaled % scale))
+}
+
+// Helper function to calculate how much the reward index grows per token
+// between two times at the vault's rate
+fn reward_index_growth(config: &RewardsVault, period_start: i64, period_end: i64) -> Result<u128> {
+    Ok(reward_index_growth_with_remainder(config, period_start, period_end, 0)?.0)
+}
+
+// Helper function to calculate index growth like reward_index_growth,
+// adding the truncated fraction carried from the last accrual and
+// returning the new fraction (in 1 / (10000 * 10000 * 31536000) units of
+// the index) alongside
+fn reward_index_growth_with_remainder(
+    config: &RewardsVault,
+    period_start: i64,
+    period_end: i64,
+    remainder: u128,
+) -> Result<(u128, u128)> {
+    // Weight the period by the campaign multiplier for the part of it that
+    // overlaps the campaign window, and 1x for the rest
+    let holding_period: u128 = checked_cast(
+        period_end.checked_sub(period_start).ok_or(TokenError::ArithmeticOverflow)?.max(0)
+    )?;
+    let overlap: u128 = checked_cast(
+        period_end.min(config.campaign_end)
+            .checked_sub(period_start.max(config.campaign_start))
+            .ok_or(TokenError::ArithmeticOverflow)?
+            .max(0)
+    )?;
+    let weighted_period = (holding_period - overlap)
+        .checked_mul(10000)
         .ok_or(TokenError::ArithmeticOverflow)?
-        .checked_div(31_536_000)
+        .checked_add(overlap * config.campaign_multiplier_bps as u128)
         .ok_or(TokenError::ArithmeticOverflow)?;
 
-    Ok(rewards)
+    // Growth: (rate/10000) * (weighted_period/10000/31536000) per token,
+    // where 31536000 is seconds in a year, in REWARD_INDEX_PRECISION units
+    let scale: u128 = 10000 * 10000 * 31_536_000;
+    let scaled = (config.reward_rate_bps as u128)
+        .checked_mul(weighted_period)
+        .ok_or(TokenError::ArithmeticOverflow)?
+        .checked_mul(token_config::REWARD_INDEX_PRECISION)
+        .ok_or(TokenError::ArithmeticOverflow)?
+        .checked_add(remainder)
+        .ok_or(TokenError::ArithmeticOverflow)?;
+
+    Ok((scaled / scale, scaled % scale))
+}
+
+// Helper function to project the rewards `supply` would accrue over
+// `horizon_secs` from `now`, at the vault's rate and campaign
+fn projected_emissions(config: &RewardsVault, supply: u64, now: i64, horizon_secs: i64) -> Result<u64> {
+    let end = now.checked_add(horizon_secs).ok_or(TokenError::ArithmeticOverflow)?;
+    let growth = reward_index_growth(config, now, end)?;
+
+    calculate_rewards(supply, growth, 10_000)
+}
+
+// Helper function to calculate bonus token rewards, which accrue linearly
+// on the native balance with no multipliers or campaigns applied
+fn calculate_bonus_rewards(
+    balance: u64,
+    rate_bps: u16,
+    period_start: i64,
+    period_end: i64,
+) -> Result<u64> {
+    let period: u128 = checked_cast((period_end - period_start).max(0))?;
+    let rewards = (balance as u128)
+        .checked_mul(rate_bps as u128)
+        .ok_or(TokenError::ArithmeticOverflow)?
+        .checked_mul(period)
+        .ok_or(TokenError::ArithmeticOverflow)?
+        / 10000
+        / 31_536_000;
+
+    checked_cast(rewards)
+}
+
+// Helper function to blend a holder's acquisition time with newly received
+// tokens, weighting each by its share of the resulting balance
+fn weighted_acquisition_time(balance: u64, acquired_at: i64, received: u64, now: i64) -> Result<i64> {
+    let total = balance as i128 + received as i128;
+    if total == 0 {
+        return Ok(now);
+    }
+    let weighted = (balance as i128 * acquired_at as i128 + received as i128 * now as i128) / total;
+
+    checked_cast(weighted)
+}
+
+// Helper function to read the recipient's holder data from its PDA. None
+// when the recipient never registered, or is the sender, whose holder data
+// the transfer already updates
+fn recipient_holder_data(info: &AccountInfo, holder_data: &Pubkey, to: &TokenAccount) -> Result<Option<HolderData>> {
+    if info.key == holder_data || info.data_is_empty() {
+        return Ok(None);
+    }
+    require_keys_eq!(*info.owner, crate::ID, TokenError::RecipientNotRegistered);
+    let to_holder_data = HolderData::try_deserialize(&mut &info.data.borrow()[..])?;
+    require_keys_eq!(to_holder_data.authority, to.owner, TokenError::RecipientNotRegistered);
+    Ok(Some(to_holder_data))
+}
+
+// Helper function to move a registered recipient's acquisition time
+// towards `now`, weighted by how much of their balance just arrived, and
+// start their holding streak if they were below the floor
+fn record_receipt(
+    to: &mut Account<TokenAccount>,
+    to_holder_data: &mut HolderData,
+    streak_floor: u64,
+    now: i64,
+) -> Result<()> {
+    let balance_before = to.amount;
+    to.reload()?;
+
+    // A streak only starts once the balance reaches the floor; top-ups
+    // from above it leave the streak running
+    if balance_before < streak_floor {
+        to_holder_data.holding_streak_start = now;
+    }
+
+    // Receiving never raises the minimum, but the balance before it counts
+    to_holder_data.record_balance(balance_before);
+
+    let received = to.amount.saturating_sub(balance_before);
+    if received > 0 {
+        to_holder_data.acquired_at = weighted_acquisition_time(
+            balance_before,
+            to_holder_data.acquired_at,
+            received,
+            now,
+        )?;
+    }
+
+    Ok(())
+}
+
+// Helper function to optionally keep tokens out of accounts controlled by
+// unvetted programs. The recipient's owner comes in as a remaining account
+// so its own owner shows what controls it. Only a system-owned key on the
+// curve is a wallet; a PDA with no data is system-owned too, but has no
+// private key and belongs to whichever program derived it
+fn check_recipient_owner(config: &RewardsVault, to: &TokenAccount, remaining_accounts: &[AccountInfo]) -> Result<()> {
+    if !config.block_program_recipients {
+        return Ok(());
+    }
+
+    let recipient_owner = remaining_accounts.iter()
+        .find(|info| info.key() == to.owner)
+        .ok_or(TokenError::MissingRecipientOwner)?;
+    let is_wallet = *recipient_owner.owner == anchor_lang::system_program::ID
+        && is_on_curve(&recipient_owner.key());
+    require!(
+        is_wallet || config.allowed_recipient_programs.contains(recipient_owner.owner),
+        TokenError::RecipientProgramNotAllowed
+    );
+
+    Ok(())
+}
+
+// Helper function to check that a key is an ed25519 point, which a PDA by
+// construction is not. Pubkey::is_on_curve isn't available on-chain, so
+// programs go through the curve syscall
+fn is_on_curve(key: &Pubkey) -> bool {
+    #[cfg(target_os = "solana")]
+    {
+        const CURVE25519_EDWARDS: u64 = 0;
+        let mut result = 0u8;
+        // SAFETY: the syscall reads the key's 32 bytes and writes one byte
+        // to `result`
+        unsafe {
+            anchor_lang::solana_program::syscalls::sol_curve_validate_point(
+                CURVE25519_EDWARDS,
+                key.as_ref().as_ptr(),
+                &mut result,
+            ) == 0
+        }
+    }
+    #[cfg(not(target_os = "solana"))]
+    {
+        key.is_on_curve()
+    }
+}
+
+// Helper function to run secure_transfer. A cooldown or daily-limit
+// rejection fails the transfer unless `record_rejection` is set and the
+// vault records rejections, in which case it's counted and the call succeeds
+fn run_secure_transfer(
+    ctx: Context<SecureTransfer>,
+    amount: u64,
+    as_delegate: bool,
+    expected_to_balance_after: Option<u64>,
+    record_rejection: bool,
+) -> Result<()> {
+    let mut accounts = vec![
+        ctx.accounts.from.to_account_info(),
+        ctx.accounts.to.to_account_info(),
+        ctx.accounts.authority.to_account_info(),
+        ctx.accounts.price_feed.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.mint.to_account_info(),
+        ctx.accounts.treasury.to_account_info(),
+    ];
+    let mut to_holder_data = recipient_holder_data(
+        &ctx.accounts.to_holder_data,
+        &ctx.accounts.holder_data.key(),
+        &ctx.accounts.to,
+    )?;
+    if to_holder_data.is_some() {
+        accounts.push(ctx.accounts.to_holder_data.to_account_info());
+    }
+
+    // A delegate spends from the owner's account, so the token program
+    // caps it at the approved allowance; check up front for a clear error
+    if as_delegate {
+        require!(
+            amount <= ctx.accounts.from.delegated_amount,
+            TokenError::DelegateAllowanceExceeded
+        );
+    }
+
+    check_recipient_owner(&ctx.accounts.rewards_vault, &ctx.accounts.to, ctx.remaining_accounts)?;
+
+    // First transfer without a prior initialize_rewards: set up the
+    // account init_if_needed just created for the token account's owner
+    let owner = ctx.accounts.from.owner;
+    if ctx.accounts.holder_data.authority == Pubkey::default() {
+        require!(!ctx.accounts.rewards_vault.registrations_paused, TokenError::RegistrationsPaused);
+        ctx.accounts.stats.add_holder(ctx.accounts.rewards_vault.max_holders)?;
+        let reward_index = ctx.accounts.rewards_vault.accrue_index(Clock::get()?.unix_timestamp)?;
+        let balance = ctx.accounts.from.amount;
+        ctx.accounts.holder_data.init(owner, Pubkey::default(), ctx.bumps.holder_data, reward_index, balance)?;
+        msg!("Initialized rewards for holder {}", owner);
+    }
+
+    // Locked holders can't send until the lock expires; rewards still accrue
+    let current_time = Clock::get()?.unix_timestamp;
+    require!(
+        current_time >= ctx.accounts.holder_data.locked_until,
+        TokenError::HolderLocked
+    );
+
+    // In per-pair mode the cooldown is tracked per (from, to) pair
+    // instead of globally on the sender; an exempt authority skips it
+    let rewards_vault = &ctx.accounts.rewards_vault;
+    let exempt = rewards_vault.authority_exempt
+        && ctx.accounts.authority.key() == rewards_vault.authority
+        && ctx.accounts.from.owner == rewards_vault.authority;
+    if rewards_vault.per_pair_cooldown && !exempt {
+        let transfer_pair = ctx.accounts.transfer_pair.as_ref()
+            .ok_or(TokenError::MissingTransferPair)?;
+        require!(
+            current_time - transfer_pair.last_transfer
+                >= rewards_vault.transfer_cooldown(ctx.accounts.holder_data.acquired_at, current_time),
+            TokenError::TransferCooldownActive
+        );
+    }
+
+    // Holder data is passed as the deserialized account so every change
+    // is written back by Anchor on exit, and rolled back with any error
+    let burned_before = ctx.accounts.rewards_vault.total_burned;
+    let transferred = Processor::process_transfer(
+        ctx.program_id,
+        &accounts,
+        &mut ctx.accounts.rewards_vault,
+        &mut ctx.accounts.holder_data,
+        amount,
+        record_rejection,
+        &[],
+    )?;
+    if transferred {
+        ctx.accounts.stats.record_burn(ctx.accounts.rewards_vault.total_burned - burned_before)?;
+
+        if let Some(transfer_pair) = &mut ctx.accounts.transfer_pair {
+            transfer_pair.last_transfer = current_time;
+        }
+
+        let streak_floor = ctx.accounts.rewards_vault.streak_balance_floor;
+        record_send(&mut ctx.accounts.from, &mut ctx.accounts.holder_data, streak_floor, current_time)?;
+        if let Some(to_holder_data) = &mut to_holder_data {
+            record_receipt(&mut ctx.accounts.to, to_holder_data, streak_floor, current_time)?;
+            to_holder_data.try_serialize(&mut &mut ctx.accounts.to_holder_data.data.borrow_mut()[..])?;
+        }
+    }
+    
+    // Let the caller pin the recipient's resulting balance, so a send
+    // to the wrong account fails the whole transaction. A recorded
+    // rejection is checked too, against the unchanged balance
+    if let Some(expected) = expected_to_balance_after {
+        ctx.accounts.to.reload()?;
+        require!(ctx.accounts.to.amount == expected, TokenError::PostBalanceMismatch);
+    }
+    
+    if transferred {
+        msg!("Secure transfer of {} tokens completed", amount);
+    } else {
+        msg!("Transfer rejected and recorded");
+    }
+    Ok(())
+}
+
+// Helper function to restart the sender's holding streak when a transfer
+// takes their balance below the streak floor
+fn record_send(
+    from: &mut Account<TokenAccount>,
+    holder_data: &mut HolderData,
+    streak_floor: u64,
+    now: i64,
+) -> Result<()> {
+    from.reload()?;
+    if from.amount < streak_floor {
+        holder_data.holding_streak_start = now;
+    }
+
+    Ok(())
+}
+
+// Helper function to hold transfers worth more than the large-transfer
+// threshold to the tighter confidence band
+fn check_large_transfer_confidence(
+    config: &RewardsVault,
+    amount: u64,
+    price: u64,
+    conf: u64,
+    decimals: u8,
+) -> Result<()> {
+    if config.large_transfer_max_confidence_bps == 0
+        || usd_value_cents(amount, price, decimals)? <= config.large_transfer_threshold_cents
+    {
+        return Ok(());
+    }
+
+    // conf / price <= bps / 10000, cross-multiplied to avoid rounding
+    require!(
+        conf as u128 * 10_000 <= config.large_transfer_max_confidence_bps as u128 * price as u128,
+        TokenError::LargeTransferConfidenceTooLow
+    );
+
+    Ok(())
+}
+
+// Helper function to split a pool pro-rata by balance. Shares are rounded
+// down and the dust goes to the largest balance (first on ties), so the
+// shares always sum to exactly `total`.
+fn pro_rata_shares(total: u64, balances: &[u64]) -> Result<Vec<u64>> {
+    let total_balance: u128 = balances.iter().map(|b| *b as u128).sum();
+    require!(total_balance > 0, TokenError::InvalidDistributionAccounts);
+
+    let mut shares = balances
+        .iter()
+        .map(|balance| checked_cast(total as u128 * *balance as u128 / total_balance))
+        .collect::<Result<Vec<u64>>>()?;
+
+    let distributed: u64 = shares.iter().sum();
+    let largest = balances
+        .iter()
+        .enumerate()
+        .fold(0, |best, (i, balance)| if *balance > balances[best] { i } else { best });
+    shares[largest] += total - distributed;
+
+    Ok(shares)
+}
+
+// Helper function to split a transfer into (net, treasury fee, burned fee).
+// Both divisions round down, so the treasury absorbs the burn's rounding
+// and the three parts always sum to `amount`.
+fn split_transfer_fee(amount: u64, fee_bps: u16, burn_bps: u16) -> Result<(u64, u64, u64)> {
+    let fee: u64 = checked_cast(amount as u128 * fee_bps as u128 / 10_000)?;
+    let burn_fee: u64 = checked_cast(fee as u128 * burn_bps as u128 / 10_000)?;
+
+    Ok((amount - fee, fee - burn_fee, burn_fee))
+}
+
+// Configurable values accepted by the set_* admin instructions
+pub enum ConfigValue {
+    MaxRewardPerClaim(u64, u8),
+    RewardMultiplier(u16),
+    VolatilityWindow(i64),
+    MaxTransferSize(u64),
+    MaxTransferBalanceBps(u16),
+    MintRateLimit(u64, u8),
+    ReferralBonus(u16),
+    CampaignWindow(i64, i64),
+    TransferFee(u16),
+    FeeBurnShare(u16),
+    DailyResetOffset(i64),
+    MaxRewardsPerHolder(u64, u8),
+    BonusRewardRate(u16),
+    MaxConfidenceCents(u64),
+    LockedUntil(i64),
+    DustThreshold(u64, u8),
+    ClaimCooldown(i64),
+    PriceSlotAge(u64, u64),
+    AccrualDelay(i64),
+    EarlyTransferPenalty(u16),
+    RewardRate(u16),
+    EmergencyHaircut(u16),
+    DailyTransactionLimits(u64, u64),
+    LargeTransferConfidence(u16),
+    LongTermCooldown(i64),
+    HoldingStreak(u64, i64, u8),
+    Decimals(u8),
+    MaxHolderBalance(u64, u8),
+    TradingStart(i64, i64),
+    MaxHolders(u64, u64),
+    BurnAddress(Pubkey, Pubkey),
+    MinHoldingPeriod(i64),
+}
+
+// Helper function to bounds-check a config value before it is stored
+fn validate_config_value(value: ConfigValue) -> Result<()> {
+    match value {
+        ConfigValue::MaxRewardPerClaim(max_reward, decimals) => require!(
+            max_reward <= total_supply(decimals)?,
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::RewardMultiplier(multiplier_bps) => require!(
+            multiplier_bps <= token_config::MAX_REWARD_MULTIPLIER_BPS,
+            TokenError::InvalidRewardMultiplier
+        ),
+        ConfigValue::VolatilityWindow(duration) => require!(
+            (0..=token_config::MAX_VOLATILITY_WINDOW).contains(&duration),
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::MaxTransferSize(max_size) => require!(
+            max_size > 0 && max_size <= token_config::TOTAL_SUPPLY_TOKENS,
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::MaxTransferBalanceBps(max_balance_bps) => require!(
+            (1..=10_000).contains(&max_balance_bps),
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::MintRateLimit(limit, decimals) => require!(
+            limit <= total_supply(decimals)?,
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::ReferralBonus(bonus_bps) => require!(
+            bonus_bps <= token_config::MAX_REFERRAL_BONUS_BPS,
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::CampaignWindow(start, end) => require!(
+            0 <= start && start <= end,
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::TransferFee(fee_bps) => require!(
+            fee_bps <= token_config::MAX_TRANSFER_FEE_BPS,
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::FeeBurnShare(burn_bps) => require!(
+            burn_bps <= 10_000,
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::DailyResetOffset(offset) => require!(
+            offset.abs() <= token_config::MAX_DAILY_RESET_OFFSET,
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::MaxRewardsPerHolder(max_rewards, decimals) => require!(
+            max_rewards <= total_supply(decimals)?,
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::BonusRewardRate(rate_bps) => require!(
+            rate_bps <= token_config::MAX_BONUS_REWARD_RATE_BPS,
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::MaxConfidenceCents(max_cents) => require!(
+            max_cents <= token_config::MAX_ABSOLUTE_CONFIDENCE_CENTS,
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::HoldingStreak(balance_floor, min_streak, decimals) => require!(
+            balance_floor <= total_supply(decimals)? && (0..=token_config::MAX_HOLDING_STREAK).contains(&min_streak),
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::LongTermCooldown(cooldown) => require!(
+            (0..=token_config::TRANSFER_COOLDOWN).contains(&cooldown),
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::LockedUntil(locked_until) => require!(
+            locked_until >= 0,
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::DustThreshold(threshold, decimals) => require!(
+            threshold <= token_unit(decimals)?,
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::ClaimCooldown(cooldown) => require!(
+            (0..=token_config::MIN_HOLDING_PERIOD).contains(&cooldown),
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::PriceSlotAge(min_slots, max_slots) => require!(
+            max_slots <= token_config::MAX_PRICE_SLOT_AGE
+                && (max_slots == 0 || min_slots < max_slots),
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::MinHoldingPeriod(period) => require!(
+            (0..=token_config::MAX_HOLDING_PERIOD).contains(&period),
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::AccrualDelay(delay) => require!(
+            (0..=token_config::MAX_ACCRUAL_DELAY).contains(&delay),
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::EarlyTransferPenalty(penalty_bps) => require!(
+            penalty_bps <= token_config::MAX_EARLY_TRANSFER_PENALTY_BPS,
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::RewardRate(rate_bps) => require!(
+            rate_bps <= token_config::MAX_REWARD_RATE_BPS,
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::EmergencyHaircut(haircut_bps) => require!(
+            haircut_bps <= token_config::MAX_EMERGENCY_HAIRCUT_BPS,
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::LargeTransferConfidence(max_confidence_bps) => require!(
+            max_confidence_bps as u64 <= token_config::MAX_CONFIDENCE_BPS,
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::DailyTransactionLimits(unverified, verified) => require!(
+            unverified > 0 && unverified <= verified && verified <= token_config::MAX_DAILY_TRANSACTIONS_LIMIT,
+            TokenError::InvalidConfigValue
+        ),
+        ConfigValue::Decimals(decimals) => require!(
+            decimals <= token_config::MAX_DECIMALS,
+            TokenError::InvalidConfigValue
+        ),
+        // At least one whole token; a cap at the supply is effectively none
+        ConfigValue::MaxHolderBalance(max_balance, decimals) => require!(
+            max_balance >= token_unit(decimals)? && max_balance <= total_supply(decimals)?,
+            TokenError::InvalidConfigValue
+        ),
+        // A past start opens trading now; a future one is bounded so a typo
+        // can't shut everyone but the authority out for years
+        ConfigValue::TradingStart(start, now) => require!(
+            start >= 0 && start <= now.saturating_add(token_config::MAX_TRADING_START_DELAY),
+            TokenError::InvalidConfigValue
+        ),
+        // 0 for no cap, otherwise no lower than the holders already registered
+        ConfigValue::MaxHolders(max_holders, holder_count) => require!(
+            max_holders == 0 || max_holders >= holder_count,
+            TokenError::InvalidConfigValue
+        ),
+        // Fees paid to the treasury must never be burned
+        ConfigValue::BurnAddress(burn_address, treasury) => require!(
+            burn_address != treasury,
+            TokenError::InvalidConfigValue
+        ),
+    }
+
+    Ok(())
 }
 
 // Helper function to validate transaction limits
 fn validate_transaction_limits(
+    config: &RewardsVault,
     amount: u64,
-    price: u64,
+    balance: u64,
+    price: Option<u64>,
+    decimals: u8,
     daily_transactions: u64,
     current_time: i64,
     last_transaction_date: i64,
+    is_verified: bool,
+    skip_usd_minimum: bool,
 ) -> Result<()> {
-    // Check minimum USD value
-    let usd_value = (amount as u128 * price as u128) / 1_000_000;
-    require!(
-        usd_value >= token_config::MIN_PURCHASE_USD as u128,
-        TokenError::BelowMinimumUSD
-    );
+    // Check minimum USD value; without a price the size cap is tightened instead
+    let max_size_tokens = match price {
+        Some(price) => {
+            if !skip_usd_minimum {
+                let usd_value = usd_value_cents(amount, price, decimals)?;
+                require!(
+                    usd_value >= token_config::MIN_PURCHASE_USD,
+                    TokenError::BelowMinimumUSD
+                );
+            }
+            config.max_transfer_size
+        }
+        None => config.max_transfer_size.min(token_config::UNPRICED_MAX_TRANSACTION_SIZE),
+    };
 
-    // Check maximum transaction size
-    require!(
-        amount <= token_config::MAX_TRANSACTION_SIZE,
-        TokenError::ExceedsMaxSize
-    );
+    // Check maximum transaction size: the smaller of the absolute cap and
+    // the allowed share of the sender's balance, reporting whichever binds
+    let max_size = max_size_tokens
+        .checked_mul(token_unit(decimals)?)
+        .ok_or(TokenError::ArithmeticOverflow)?;
+    let max_share: u64 = checked_cast(balance as u128 * config.max_transfer_balance_bps as u128 / 10_000)?;
+    if max_share < max_size {
+        require!(amount <= max_share, TokenError::TransferAmountTooLarge);
+    } else {
+        require!(amount <= max_size, TokenError::ExceedsMaxSize);
+    }
 
-    // Check daily transaction limit
-    let today = (current_time / 86400) as i64;
+    // Check daily transaction limit for the holder's verification level
+    let today = day_index(current_time, config.daily_reset_offset_secs);
     if last_transaction_date == today {
         require!(
-            daily_transactions < token_config::MAX_DAILY_TRANSACTIONS,
+            daily_transactions < config.max_daily_transactions(is_verified),
             TokenError::DailyLimitExceeded
         );
     }
@@ -429,9 +5299,382 @@ fn validate_transaction_limits(
     Ok(())
 }
 
+// Helper function to tell a missing/stale feed apart from other price errors
+fn is_price_unavailable(err: &ProgramError) -> bool {
+    *err == TokenError::StalePrice.into() || *err == TokenError::InvalidPriceFeed.into()
+}
+
+// Helper function to check a price's publish slot falls within
+// [min_age, max_age] slots of the current slot (0 disables either bound)
+fn check_price_slot_age(pub_slot: u64, current_slot: u64, min_age: u64, max_age: u64) -> Result<()> {
+    let age = current_slot.saturating_sub(pub_slot);
+    require!(age >= min_age, TokenError::PriceSlotTooRecent);
+    require!(max_age == 0 || age <= max_age, TokenError::StalePrice);
+    Ok(())
+}
+
+// Helper function to accrue a holder's rewards up to `now` and credit them,
+// advancing last_claim over the share that was paid out and carrying the
+// rest to the next claim. Returns the amount credited
+fn settle_rewards(config: &mut RewardsVault, holder_data: &mut HolderData, balance: u64, now: i64) -> Result<u64> {
+    // In min-balance mode a top-up just before claiming earns nothing extra.
+    // The minimum starts from the balance at registration, so tokens that
+    // arrived without being observed earn nothing until the next claim
+    let current_balance = balance;
+    let balance = if config.accrue_on_min_balance {
+        balance.min(holder_data.min_balance_since_claim)
+    } else {
+        balance
+    };
+
+    // Rewards are the balance times the index growth since the last
+    // claim. They only start accruing accrual_delay after registration,
+    // so the first claim drops the index growth from before that
+    let reward_index = holder_data.accrued_index(config.accrue_index(now)?);
+    let accrual_start = holder_data.last_claim.max(
+        holder_data.registered_at
+            .checked_add(config.accrual_delay)
+            .ok_or(TokenError::ArithmeticOverflow)?,
+    );
+    let holding_period: u64 = checked_cast((now - accrual_start).max(0))?;
+    let mut index_delta = reward_index.checked_sub(holder_data.last_index)
+        .ok_or(TokenError::ArithmeticOverflow)?;
+    if accrual_start > holder_data.last_claim {
+        index_delta = index_delta
+            .checked_mul(holding_period as u128)
+            .ok_or(TokenError::ArithmeticOverflow)?
+            / checked_cast::<i64, u128>((now - holder_data.last_claim).max(1))?;
+    }
+    let (accrued, rounding_remainder) = calculate_rewards_with_remainder(
+        balance,
+        index_delta,
+        holder_data.reward_multiplier_bps,
+        holder_data.rounding_remainder,
+    )?;
+
+    // Cap the payout, advancing last_claim only over the share that was
+    // paid out. The unpaid accrual is carried in rounding_remainder rather
+    // than by holding back the index, so capped claims add up to exactly
+    // what a single uncapped claim would have paid
+    let max_reward = config.max_reward_per_claim;
+    let (rewards, claimed_until, carried) = if max_reward > 0 && accrued > max_reward {
+        let paid_period = (holding_period as u128)
+            .checked_mul(max_reward as u128)
+            .ok_or(TokenError::ArithmeticOverflow)?
+            / accrued as u128;
+        let unpaid = ((accrued - max_reward) as u128)
+            .checked_mul(token_config::REWARD_INDEX_PRECISION * 10_000)
+            .ok_or(TokenError::ArithmeticOverflow)?
+            .checked_add(rounding_remainder)
+            .ok_or(TokenError::ArithmeticOverflow)?;
+        (max_reward, accrual_start + checked_cast::<u128, i64>(paid_period)?, unpaid)
+    } else {
+        (accrued, now, rounding_remainder)
+    };
+
+    // Update holder data; anything over the per-holder cap is forfeited
+    let max_rewards_per_holder = config.max_rewards_per_holder;
+    let rewards = credit_rewards(holder_data, rewards, max_rewards_per_holder)?;
+    holder_data.last_claim = claimed_until;
+    holder_data.last_claim_at = now;
+    holder_data.last_index = reward_index;
+    holder_data.rounding_remainder = carried;
+    holder_data.min_balance_since_claim = current_balance;
+
+    Ok(rewards)
+}
+
+// Helper function to add rewards to a holder without taking rewards_earned
+// past the per-holder cap, returning the amount actually credited
+fn credit_rewards(holder_data: &mut HolderData, amount: u64, max_rewards_per_holder: u64) -> Result<u64> {
+    let mut credited = amount;
+    if max_rewards_per_holder > 0 {
+        credited = credited.min(max_rewards_per_holder.saturating_sub(holder_data.rewards_earned));
+        if credited < amount {
+            emit!(RewardAccrualClamped {
+                holder: holder_data.authority,
+                requested: amount,
+                credited,
+                cap: max_rewards_per_holder,
+            });
+        }
+    }
+
+    holder_data.rewards_earned = holder_data.rewards_earned.checked_add(credited)
+        .ok_or(TokenError::ArithmeticOverflow)?;
+    Ok(credited)
+}
+
+// Helper function to credit rewards the holder's next claim pays out,
+// within the same per-holder cap as accrued rewards
+fn credit_pending_rewards(holder_data: &mut HolderData, amount: u64, max_rewards_per_holder: u64) -> Result<u64> {
+    let credited = credit_rewards(holder_data, amount, max_rewards_per_holder)?;
+    holder_data.pending_rewards = holder_data.pending_rewards.checked_add(credited)
+        .ok_or(TokenError::ArithmeticOverflow)?;
+    Ok(credited)
+}
+
+// Helper function to get the day number used by the daily counters, with
+// days starting at local midnight for the given UTC offset
+fn day_index(timestamp: i64, offset_secs: i64) -> i64 {
+    (timestamp + offset_secs).div_euclid(86400)
+}
+
+// Helper function to check that an account passed in remaining accounts is
+// the holder PDA of `holder_data` under `mint`
+fn require_holder_pda(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    info: &AccountInfo,
+    holder_data: &HolderData,
+    error: TokenError,
+) -> Result<()> {
+    let expected = Pubkey::create_program_address(
+        &[b"holder", mint.as_ref(), holder_data.authority.as_ref(), &[holder_data.bump]],
+        program_id,
+    )
+    .map_err(|_| error)?;
+    require_keys_eq!(expected, info.key(), error);
+
+    Ok(())
+}
+
+// Helper function to close a program account that no longer deserializes,
+// returning its rent to `destination`
+fn close_legacy_account<'info>(account: &AccountInfo<'info>, destination: &AccountInfo<'info>) -> Result<()> {
+    let lamports = destination.lamports().checked_add(account.lamports())
+        .ok_or(TokenError::ArithmeticOverflow)?;
+    **destination.try_borrow_mut_lamports()? = lamports;
+    **account.try_borrow_mut_lamports()? = 0;
+    account.assign(&anchor_lang::system_program::ID);
+    account.realloc(0, false)?;
+
+    Ok(())
+}
+
+// Helper function to convert between integer types, failing with
+// ArithmeticOverflow instead of wrapping or truncating
+fn checked_cast<T, U: TryFrom<T>>(value: T) -> Result<U> {
+    U::try_from(value).map_err(|_| TokenError::ArithmeticOverflow.into())
+}
+
+// Helper function to record a signed offset as an audit param. The offset
+// may be negative, so it is kept as its two's-complement bits rather than
+// range-checked; read it back with i64::from_le_bytes
+fn offset_bits(offset: i64) -> u64 {
+    u64::from_le_bytes(offset.to_le_bytes())
+}
+
+// Helper function to get the divisor for a Pyth price exponent. The
+// exponent is negative for fractional prices, so only its magnitude counts
+fn pow10_exponent(expo: i32) -> Result<u64> {
+    10u64
+        .checked_pow(expo.unsigned_abs())
+        .ok_or(TokenError::ArithmeticOverflow.into())
+}
+
+// Helper function to get the number of base units in one whole token
+fn token_unit(decimals: u8) -> Result<u64> {
+    10u64
+        .checked_pow(decimals as u32)
+        .ok_or(TokenError::ArithmeticOverflow.into())
+}
+
+// Helper function to get the total supply in base units of a mint with
+// the given decimals
+fn total_supply(decimals: u8) -> Result<u64> {
+    token_config::TOTAL_SUPPLY_TOKENS
+        .checked_mul(token_unit(decimals)?)
+        .ok_or(TokenError::ArithmeticOverflow.into())
+}
+
+// Helper function to value `amount` base units in USD with 6 decimals,
+// given a price in USD with 6 decimals per whole token
+fn usd_value_micros(amount: u64, price: u64, decimals: u8) -> Result<u64> {
+    let usd_value = (amount as u128)
+        .checked_mul(price as u128)
+        .ok_or(TokenError::ArithmeticOverflow)?
+        / token_unit(decimals)? as u128;
+
+    checked_cast(usd_value)
+}
+
+// Helper function to convert a USD value with 6 decimals into base units
+// of a dollar-pegged reward mint
+fn reward_units_for_usd_micros(usd_value: u64, decimals: u8) -> Result<u64> {
+    let units = (usd_value as u128)
+        .checked_mul(token_unit(decimals)? as u128)
+        .ok_or(TokenError::ArithmeticOverflow)?
+        / 1_000_000;
+
+    checked_cast(units)
+}
+
+// Helper function to value `amount` base units in USD cents
+fn usd_value_cents(amount: u64, price: u64, decimals: u8) -> Result<u64> {
+    Ok(usd_value_micros(amount, price, decimals)? / 10_000)
+}
+
+// Helper function to get the base units worth `usd_cents`, rounded up so
+// the amount always values at or above the target
+fn tokens_for_usd_cents(usd_cents: u64, price: u64, decimals: u8) -> Result<u64> {
+    require!(price > 0, TokenError::InvalidPriceFeed);
+
+    let numerator = (usd_cents as u128)
+        .checked_mul(10_000)
+        .ok_or(TokenError::ArithmeticOverflow)?
+        .checked_mul(token_unit(decimals)? as u128)
+        .ok_or(TokenError::ArithmeticOverflow)?;
+    let amount = (numerator + price as u128 - 1) / price as u128;
+
+    checked_cast(amount)
+}
+
+// Helper function to check a Merkle proof that `holder` is in the allowlist.
+// Leaves are sha256(pubkey) and each parent hashes its children in sorted
+// order, so proofs carry no left/right flags
+fn verify_merkle_proof(proof: &[[u8; 32]], root: [u8; 32], holder: Pubkey) -> bool {
+    let mut node = hashv(&[holder.as_ref()]).to_bytes();
+    for sibling in proof {
+        node = if node <= *sibling {
+            hashv(&[&node, sibling]).to_bytes()
+        } else {
+            hashv(&[sibling, &node]).to_bytes()
+        };
+    }
+
+    node == root
+}
+
+// Helper function to move a mint or token account authority to `new_authority`
+fn set_token_authority<'info>(
+    token_program: &Program<'info, Token>,
+    account_or_mint: AccountInfo<'info>,
+    current_authority: AccountInfo<'info>,
+    authority_type: AuthorityType,
+    new_authority: Pubkey,
+) -> Result<()> {
+    token::set_authority(
+        CpiContext::new(
+            token_program.to_account_info(),
+            token::SetAuthority {
+                current_authority,
+                account_or_mint,
+            },
+        ),
+        authority_type,
+        Some(new_authority),
+    )
+}
+
 // Helper function to get the next account from an iterator
 fn next_account_info<'a, 'b>(
     iter: &mut std::slice::Iter<'a, AccountInfo<'b>>,
 ) -> Result<&'a AccountInfo<'b>, ProgramError> {
     iter.next().ok_or(ProgramError::NotEnoughAccountKeys)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YEAR: i64 = 31_536_000;
+
+    // A zeroed vault at `rate_bps` with a campaign of `multiplier_bps`
+    // between `campaign_start` and `campaign_end`
+    fn vault(rate_bps: u16, multiplier_bps: u16, campaign_start: i64, campaign_end: i64) -> RewardsVault {
+        let mut vault = RewardsVault::deserialize(&mut &[0u8; RewardsVault::LEN][..]).unwrap();
+        vault.reward_rate_bps = rate_bps;
+        vault.campaign_multiplier_bps = multiplier_bps;
+        vault.campaign_start = campaign_start;
+        vault.campaign_end = campaign_end;
+        vault
+    }
+
+    fn is_overflow<T: std::fmt::Debug>(result: Result<T>) -> bool {
+        result.unwrap_err() == TokenError::ArithmeticOverflow.into()
+    }
+
+    #[test]
+    fn checked_cast_rejects_values_out_of_range() {
+        assert_eq!(checked_cast::<u128, u64>(u64::MAX as u128).unwrap(), u64::MAX);
+        assert!(is_overflow(checked_cast::<u128, u64>(u64::MAX as u128 + 1)));
+        assert_eq!(checked_cast::<i64, u128>(i64::MAX).unwrap(), i64::MAX as u128);
+        assert!(is_overflow(checked_cast::<i64, u64>(-1)));
+        assert!(is_overflow(checked_cast::<u64, i64>(u64::MAX)));
+    }
+
+    #[test]
+    fn offset_bits_round_trip_negative_offsets() {
+        for offset in [-token_config::MAX_DAILY_RESET_OFFSET, -1, 0, token_config::MAX_DAILY_RESET_OFFSET] {
+            assert_eq!(i64::from_le_bytes(offset_bits(offset).to_le_bytes()), offset);
+        }
+    }
+
+    #[test]
+    fn reward_index_growth_over_a_year() {
+        // 5% of REWARD_INDEX_PRECISION, doubled for a campaign covering
+        // the whole year and weighted by the overlap for half of it
+        let precision = token_config::REWARD_INDEX_PRECISION;
+        assert_eq!(reward_index_growth(&vault(500, 10_000, 0, 0), 0, YEAR).unwrap(), precision / 20);
+        assert_eq!(reward_index_growth(&vault(500, 20_000, 0, YEAR), 0, YEAR).unwrap(), precision / 10);
+        assert_eq!(
+            reward_index_growth(&vault(500, 20_000, YEAR / 2, YEAR), 0, YEAR).unwrap(),
+            precision * 3 / 40
+        );
+    }
+
+    #[test]
+    fn reward_index_growth_at_the_extremes() {
+        // Empty and reversed periods grow nothing
+        assert_eq!(reward_index_growth(&vault(500, 10_000, 0, 0), YEAR, YEAR).unwrap(), 0);
+        assert_eq!(reward_index_growth(&vault(500, 10_000, 0, 0), YEAR, 0).unwrap(), 0);
+
+        // Periods whose length, or distance from the campaign, doesn't fit
+        // in an i64 fail instead of wrapping
+        assert!(is_overflow(reward_index_growth(&vault(500, 10_000, 0, 0), i64::MIN, i64::MAX)));
+        assert!(is_overflow(reward_index_growth(&vault(500, 20_000, 0, i64::MAX), i64::MIN, i64::MAX)));
+        assert!(is_overflow(reward_index_growth(&vault(500, 20_000, 100, 200), i64::MIN, i64::MIN + 10)));
+
+        // The longest representable period fits at the default rate, but
+        // not at the highest rate and campaign multiplier
+        assert!(reward_index_growth(&vault(500, 10_000, 0, 0), 0, i64::MAX).is_ok());
+        assert!(is_overflow(reward_index_growth(
+            &vault(token_config::MAX_REWARD_RATE_BPS, 30_000, 0, i64::MAX),
+            0,
+            i64::MAX,
+        )));
+    }
+
+    #[test]
+    fn accrue_index_carries_the_truncated_growth() {
+        // Each 7-second step truncates a fraction of the growth; carrying
+        // it lands on the same index as a single accrual over the span
+        let mut stepped = vault(500, 20_000, 35_000, 1_000_000);
+        for step in 1..=10_000 {
+            stepped.accrue_index(step * 7).unwrap();
+        }
+        let mut single = vault(500, 20_000, 35_000, 1_000_000);
+        single.accrue_index(70_000).unwrap();
+
+        assert_eq!(stepped.reward_index, single.reward_index);
+        assert_eq!(stepped.reward_index_remainder, single.reward_index_remainder);
+        assert!(single.reward_index_remainder > 0);
+    }
+
+    #[test]
+    fn projected_emissions_match_the_rate() {
+        let supply = 1_000_000_000_000_000;
+        assert_eq!(projected_emissions(&vault(500, 10_000, 0, 0), supply, 0, YEAR).unwrap(), supply / 20);
+        assert_eq!(projected_emissions(&vault(500, 10_000, 0, 0), supply, YEAR, 0).unwrap(), 0);
+
+        // A campaign for the second half of the horizon adds half again
+        assert_eq!(
+            projected_emissions(&vault(500, 20_000, YEAR / 2, YEAR), supply, 0, YEAR).unwrap(),
+            supply * 3 / 40
+        );
+
+        // A horizon past the end of time fails rather than wrapping
+        assert!(is_overflow(projected_emissions(&vault(500, 10_000, 0, 0), supply, YEAR, i64::MAX)));
+    }
+}
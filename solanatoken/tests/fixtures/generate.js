@@ -0,0 +1,409 @@
+// Writes the genesis accounts the test validator loads through
+// Anchor.toml, and prints the [[test.validator.account]] entries for them.
+// Rerun with `node tests/fixtures/generate.js` after changing a layout.
+//
+// Legacy accounts are a vault and a holder in the original keypair
+// layouts, for the migration instructions. The v1 vault is a vault PDA,
+// with its audit log, in the layout before version 2, for an in-place
+// migrate_rewards_vault. Price feeds are Pyth v2 price accounts with a
+// fixed aggregate price. Every address comes from a fixed seed the tests
+// derive the same way.
+
+const crypto = require("crypto");
+const fs = require("fs");
+const path = require("path");
+
+const PROGRAM_ID = "7MCEfe5NNGmfv2TiGDthDPF5T4TrsWFLRHAA5WMC7sTo";
+const PYTH_PROGRAM_ID = "FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH";
+
+// Pyth PriceStatus values
+const UNKNOWN = 0;
+const TRADING = 1;
+const HALTED = 2;
+
+// Price feeds by name. price and conf are in 10^-8 USD; stale feeds last
+// published at the Unix epoch, the others at PUBLISH_TIME
+const PUBLISH_TIME = 1_767_225_600;
+const PRICE_FEEDS = [
+  { name: "healthy", price: 100_000_000, conf: 100_000, status: TRADING },
+  {
+    name: "stale",
+    price: 100_000_000,
+    conf: 100_000,
+    status: TRADING,
+    stale: true,
+  },
+  // A 2% confidence interval, wider than the strict check allows
+  { name: "wide", price: 100_000_000, conf: 2_000_000, status: TRADING },
+  // $50,000 with a $100 interval: 0.2%, but large in absolute terms
+  {
+    name: "high-priced",
+    price: 5_000_000_000_000,
+    conf: 10_000_000_000,
+    status: TRADING,
+  },
+  { name: "halted", price: 100_000_000, conf: 100_000, status: HALTED },
+  { name: "unknown", price: 100_000_000, conf: 100_000, status: UNKNOWN },
+];
+
+const ALPHABET = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+const base58 = (bytes) => {
+  const digits = [];
+  for (const byte of bytes) {
+    let carry = byte;
+    for (let i = 0; i < digits.length; i++) {
+      carry += digits[i] << 8;
+      digits[i] = carry % 58;
+      carry = (carry / 58) | 0;
+    }
+    while (carry > 0) {
+      digits.push(carry % 58);
+      carry = (carry / 58) | 0;
+    }
+  }
+  let out = "";
+  for (const byte of bytes) {
+    if (byte !== 0) break;
+    out += "1";
+  }
+  for (let i = digits.length - 1; i >= 0; i--) {
+    out += ALPHABET[digits[i]];
+  }
+  return out;
+};
+
+const base58Decode = (text) => {
+  const bytes = [];
+  for (const char of text) {
+    let carry = ALPHABET.indexOf(char);
+    for (let i = 0; i < bytes.length; i++) {
+      carry += bytes[i] * 58;
+      bytes[i] = carry & 0xff;
+      carry >>= 8;
+    }
+    while (carry > 0) {
+      bytes.push(carry & 0xff);
+      carry >>= 8;
+    }
+  }
+  for (const char of text) {
+    if (char !== "1") break;
+    bytes.push(0);
+  }
+  return Buffer.from(bytes.reverse());
+};
+
+const sha256 = (data) => crypto.createHash("sha256").update(data).digest();
+
+// Whether `bytes` decompress to an ed25519 point: x^2 = (y^2 - 1) /
+// (d y^2 + 1) must be a square mod p. PDAs are the addresses that don't
+const P = 2n ** 255n - 19n;
+const modPow = (base, exp) => {
+  let result = 1n;
+  base %= P;
+  for (; exp > 0n; exp >>= 1n) {
+    if (exp & 1n) result = (result * base) % P;
+    base = (base * base) % P;
+  }
+  return result;
+};
+const isOnCurve = (bytes) => {
+  let y = 0n;
+  for (let i = 31; i >= 0; i--) {
+    y = (y << 8n) | BigInt(i === 31 ? bytes[i] & 0x7f : bytes[i]);
+  }
+  const d = (P - 121_665n) * modPow(121_666n, P - 2n) % P;
+  const y2 = (y * y) % P;
+  const x2 = ((y2 - 1n + P) % P) * modPow((d * y2 + 1n) % P, P - 2n) % P;
+  return x2 === 0n || modPow(x2, (P - 1n) / 2n) === 1n;
+};
+
+// PublicKey.findProgramAddressSync for PROGRAM_ID
+const findProgramAddress = (seeds) => {
+  for (let bump = 255; bump >= 0; bump--) {
+    const address = sha256(
+      Buffer.concat([
+        ...seeds,
+        Buffer.from([bump]),
+        base58Decode(PROGRAM_ID),
+        Buffer.from("ProgramDerivedAddress"),
+      ])
+    );
+    if (!isOnCurve(address)) return [address, bump];
+  }
+  throw new Error("No viable bump");
+};
+
+// Public key of the ed25519 keypair for `seed`, as Keypair.fromSeed derives it
+const seedPublicKey = (label) => {
+  const privateKey = crypto.createPrivateKey({
+    key: Buffer.concat([
+      Buffer.from("302e020100300506032b657004220420", "hex"),
+      sha256(label),
+    ]),
+    format: "der",
+    type: "pkcs8",
+  });
+  const spki = crypto.createPublicKey(privateKey).export({
+    format: "der",
+    type: "spki",
+  });
+  return spki.subarray(spki.length - 32);
+};
+
+const u8 = (v) => Buffer.from([v]);
+const u16 = (v) => {
+  const b = Buffer.alloc(2);
+  b.writeUInt16LE(v);
+  return b;
+};
+const u32 = (v) => {
+  const b = Buffer.alloc(4);
+  b.writeUInt32LE(v);
+  return b;
+};
+const i32 = (v) => {
+  const b = Buffer.alloc(4);
+  b.writeInt32LE(v);
+  return b;
+};
+const u64 = (v) => {
+  const b = Buffer.alloc(8);
+  b.writeBigUInt64LE(BigInt(v));
+  return b;
+};
+const i64 = (v) => {
+  const b = Buffer.alloc(8);
+  b.writeBigInt64LE(BigInt(v));
+  return b;
+};
+const zeros = (n) => Buffer.alloc(n);
+// Pads account data to the space Anchor allocated for it
+const padded = (data, space) => Buffer.concat([data, zeros(space - data.length)]);
+
+// Original keypair layouts, LegacyRewardsVault and LegacyHolderData in
+// lib.rs. Values are arbitrary but distinct, so the tests can check each
+// one carried over
+const legacyVault = (authority) =>
+  Buffer.concat([
+    sha256("account:RewardsVault").subarray(0, 8),
+    authority, // authority
+    u64(123_456), // total_rewards
+    i64(1_700_000_000), // last_update
+  ]);
+
+const legacyHolderData = (authority) =>
+  Buffer.concat([
+    sha256("account:HolderData").subarray(0, 8),
+    authority, // authority
+    u64(42_000), // rewards_earned
+    i64(1_700_000_000), // last_claim
+    i64(1_700_000_600), // last_transfer
+    u64(3), // daily_transactions
+    i64(19_675), // last_transaction_date, the day of last_transfer
+  ]);
+
+// RewardsVault at layout version 1, in field order, with empty vecs.
+// Values that differ from init's defaults show they carried over
+const v1Vault = (authority, bump) =>
+  padded(
+    Buffer.concat([
+      sha256("account:RewardsVault").subarray(0, 8),
+      authority, // authority
+      u64(123_456), // total_rewards
+      i64(1_700_000_000), // last_update
+      u8(bump), // bump
+      u8(0), // require_recipient_registered
+      u64(0), // max_reward_per_claim
+      u8(0), // per_pair_cooldown
+      u8(0), // paused
+      i64(0), // volatility_mode_until
+      u8(0), // record_rejections
+      u64(1_000_000), // max_transfer_size
+      u16(10_000), // max_transfer_balance_bps
+      u8(1), // version
+      u64(0), // mint_rate_limit
+      i64(0), // mint_window_start
+      u64(0), // minted_in_window
+      u16(0), // referral_bonus_bps
+      u8(0), // price_unavailable_policy: Block
+      u64(0), // last_price
+      i64(0), // last_price_time
+      u16(10_000), // campaign_multiplier_bps
+      i64(0), // campaign_start
+      i64(0), // campaign_end
+      u8(0), // halted_uses_last_known
+      zeros(32), // treasury
+      u16(0), // transfer_fee_bps
+      u16(0), // fee_burn_bps
+      zeros(32), // price_feed
+      i64(0), // daily_reset_offset_secs
+      u64(0), // max_rewards_per_holder
+      zeros(32), // bonus_mint
+      u16(0), // bonus_rate_bps
+      u64(0), // max_confidence_cents
+      u64(0), // dust_threshold
+      i64(86_400), // claim_cooldown
+      zeros(32), // burn_address
+      u64(0), // total_burned
+      u32(0), // approved_authorities
+      u64(0), // min_price_slot_age
+      u64(0), // max_price_slot_age
+      i64(0), // accrual_delay
+      u8(0), // authority_exempt
+      u16(0), // early_transfer_penalty_bps
+      zeros(32), // reward_mint
+      u16(750), // reward_rate_bps
+      zeros(16), // reward_index
+      i64(1_700_000_000), // index_updated_at
+      u64(0), // max_holders
+      u32(0), // internal_accounts
+      i64(0), // trading_start_ts
+      u16(1_000), // emergency_haircut_bps
+      u64(0), // max_holder_balance
+      u64(10), // max_daily_transactions_unverified
+      u64(10), // max_daily_transactions_verified
+      zeros(32), // pending_owner
+      u64(0), // large_transfer_threshold_cents
+      u16(0), // large_transfer_max_confidence_bps
+      zeros(32), // allowlist_root
+      i64(300), // long_term_cooldown
+      u8(0), // block_program_recipients
+      u32(0), // allowed_recipient_programs
+      u64(0), // streak_balance_floor
+      i64(0), // min_holding_streak
+      u8(0), // registrations_paused
+      u8(0), // accrue_on_min_balance
+      u8(6), // decimals
+    ]),
+    // 8 + RewardsVault::LEN, less the 24 bytes version 2 added
+    8 + 1_333
+  );
+
+// An empty AuditLog
+const auditLog = (bump) =>
+  padded(
+    Buffer.concat([
+      sha256("account:AuditLog").subarray(0, 8),
+      u32(0), // entries
+      u16(0), // next_index
+      u64(0), // total_entries
+      u8(bump), // bump
+    ]),
+    // 8 + AuditLog::LEN
+    8 + 1_583
+  );
+
+// Layout of PriceAccount in pyth-sdk-solana's state module
+const priceAccount = ({ price, conf, status, stale }) => {
+  const timestamp = stale ? 0 : PUBLISH_TIME;
+  const priceInfo = Buffer.concat([
+    i64(price),
+    u64(conf),
+    u32(status),
+    u32(0), // corp_act
+    u64(1), // pub_slot
+  ]);
+  const data = Buffer.concat([
+    u32(0xa1b2c3d4), // magic
+    u32(2), // ver
+    u32(3), // atype: price
+    u32(3_312), // size
+    u32(1), // ptype: price
+    i32(-8), // expo
+    u32(1), // num
+    u32(1), // num_qt
+    u64(1), // last_slot
+    u64(1), // valid_slot
+    i64(price), // ema_price.val
+    i64(price), // ema_price.numer
+    i64(1), // ema_price.denom
+    i64(conf), // ema_conf.val
+    i64(conf), // ema_conf.numer
+    i64(1), // ema_conf.denom
+    i64(timestamp), // timestamp
+    u8(1), // min_pub
+    u8(0), // drv2
+    u16(0), // drv3
+    u32(0), // drv4
+    Buffer.alloc(32), // prod
+    Buffer.alloc(32), // next
+    u64(1), // prev_slot
+    i64(price), // prev_price
+    u64(conf), // prev_conf
+    i64(timestamp), // prev_timestamp
+    priceInfo, // agg
+  ]);
+  // Publisher components are left empty
+  return Buffer.concat([data, Buffer.alloc(3_312 - data.length)]);
+};
+
+const entries = [];
+
+const writeAccount = (name, pubkey, owner, data) => {
+  const filename = `tests/fixtures/${name}.json`;
+  const account = {
+    pubkey: base58(pubkey),
+    account: {
+      lamports: (data.length + 128) * 6_960,
+      data: [data.toString("base64"), "base64"],
+      owner,
+      executable: false,
+      rentEpoch: 0,
+      space: data.length,
+    },
+  };
+  fs.writeFileSync(
+    path.join(__dirname, "..", "..", filename),
+    JSON.stringify(account, null, 2) + "\n"
+  );
+  entries.push(
+    `[[test.validator.account]]\naddress = "${account.pubkey}"\nfilename = "${filename}"\n`
+  );
+};
+
+writeAccount(
+  "legacy-vault",
+  seedPublicKey("legacy-vault"),
+  PROGRAM_ID,
+  legacyVault(seedPublicKey("legacy-authority"))
+);
+writeAccount(
+  "legacy-holder",
+  seedPublicKey("legacy-holder-data"),
+  PROGRAM_ID,
+  legacyHolderData(seedPublicKey("legacy-holder"))
+);
+
+const [v1VaultAddress, v1VaultBump] = findProgramAddress([
+  Buffer.from("rewards_vault"),
+  seedPublicKey("v1-mint"),
+]);
+writeAccount(
+  "v1-vault",
+  v1VaultAddress,
+  PROGRAM_ID,
+  v1Vault(seedPublicKey("legacy-authority"), v1VaultBump)
+);
+const [v1AuditLogAddress, v1AuditLogBump] = findProgramAddress([
+  Buffer.from("audit_log"),
+  v1VaultAddress,
+]);
+writeAccount(
+  "v1-audit-log",
+  v1AuditLogAddress,
+  PROGRAM_ID,
+  auditLog(v1AuditLogBump)
+);
+
+for (const feed of PRICE_FEEDS) {
+  writeAccount(
+    `price-feed-${feed.name}`,
+    seedPublicKey(`price-feed:${feed.name}`),
+    PYTH_PROGRAM_ID,
+    priceAccount(feed)
+  );
+}
+
+console.log(entries.join("\n"));
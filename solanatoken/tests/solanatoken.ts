@@ -1,5 +1,7 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
+import { assert } from "chai";
+import { createHash } from "crypto";
 import { Solanatoken } from "../target/types/solanatoken";
 
 describe("solanatoken", () => {
@@ -7,10 +9,5459 @@ describe("solanatoken", () => {
   anchor.setProvider(anchor.AnchorProvider.env());
 
   const program = anchor.workspace.Solanatoken as Program<Solanatoken>;
+  const provider = program.provider as anchor.AnchorProvider;
+  const authority = provider.wallet.publicKey;
 
-  it("Is initialized!", async () => {
-    // Add your test here.
-    const tx = await program.methods.initialize().rpc();
-    console.log("Your transaction signature", tx);
+  // Pyth feed address from token_config; it isn't loaded on localnet, so
+  // transfers that get past account validation fail on the price read.
+  const priceFeed = new anchor.web3.PublicKey(
+    "Gv2NQnFfSQgzqFoGGm4bFX5q6oBKPPXRJQDG3voqfWJt"
+  );
+
+  // Keypair of a genesis fixture, from the label tests/fixtures/generate.js
+  // derives it with
+  const fixtureKeypair = (label: string) =>
+    anchor.web3.Keypair.fromSeed(createHash("sha256").update(label).digest());
+
+  // Synthetic Pyth feed loaded at genesis from tests/fixtures
+  const fixtureFeed = (name: string) =>
+    fixtureKeypair(`price-feed:${name}`).publicKey;
+
+  // Holder data PDA for `owner` under `mint`
+  const holderDataAddress = (
+    mint: anchor.web3.PublicKey,
+    owner: anchor.web3.PublicKey
+  ) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("holder"), mint.toBuffer(), owner.toBuffer()],
+      program.programId
+    )[0];
+
+  // Creates a fresh mint with `decimals`, rewards vault, stats and holder
+  // data for the wallet
+  const setupToken = async (decimals = 6) => {
+    const mint = anchor.web3.Keypair.generate();
+    const holderData = holderDataAddress(mint.publicKey, authority);
+    const [rewardsVault] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("rewards_vault"), mint.publicKey.toBuffer()],
+      program.programId
+    );
+    const [auditLog] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("audit_log"), rewardsVault.toBuffer()],
+      program.programId
+    );
+    const [stats] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("stats"), mint.publicKey.toBuffer()],
+      program.programId
+    );
+    const tokenAccount = anchor.utils.token.associatedAddress({
+      mint: mint.publicKey,
+      owner: authority,
+    });
+
+    await program.methods
+      .initializeToken(decimals)
+      .accountsPartial({
+        authority,
+        mint: mint.publicKey,
+        tokenAccount,
+        rewardsVault,
+        auditLog,
+        stats,
+      })
+      .signers([mint])
+      .rpc();
+
+    await program.methods
+      .initializeRewards(null)
+      .accountsPartial({ authority, mint: mint.publicKey, holderData, tokenAccount })
+      .rpc();
+
+    return {
+      mint: mint.publicKey,
+      holderData,
+      rewardsVault,
+      auditLog,
+      stats,
+      tokenAccount,
+    };
+  };
+
+  // Points ctx's vault at `feed` through refresh_price_feed, which
+  // validates it first
+  const useFeed = (
+    ctx: Awaited<ReturnType<typeof setupToken>>,
+    feed: anchor.web3.PublicKey
+  ) =>
+    program.methods
+      .refreshPriceFeed(feed)
+      .accountsPartial({
+        authority,
+        rewardsVault: ctx.rewardsVault,
+        newPriceFeed: feed,
+      })
+      .rpc();
+
+  // Returns the program error code of a failed rpc, or null on success
+  const errorCode = async (rpc: Promise<string>): Promise<string | null> => {
+    try {
+      await rpc;
+      return null;
+    } catch (err) {
+      return err instanceof anchor.AnchorError
+        ? err.error.errorCode.code
+        : String(err);
+    }
+  };
+
+  // update_config with every setting left as is, and `fields` changed
+  const updateConfig = (
+    ctx: Awaited<ReturnType<typeof setupToken>>,
+    fields: object
+  ) =>
+    program.methods
+      .updateConfig(
+        Object.assign(
+          {
+            maxRewardPerClaim: null,
+            maxRewardsPerHolder: null,
+            maxTransferSize: null,
+            maxTransferBalanceBps: null,
+            mintRateLimit: null,
+            referralBonusBps: null,
+            transferFeeBps: null,
+            feeBurnBps: null,
+            dailyResetOffsetSecs: null,
+            claimCooldown: null,
+            minHoldingPeriod: null,
+            accrualDelay: null,
+            dustThreshold: null,
+            maxConfidenceCents: null,
+            priceUnavailablePolicy: null,
+            requireRecipientRegistered: null,
+            perPairCooldown: null,
+            recordRejections: null,
+            haltedUsesLastKnown: null,
+            emergencyHaircutBps: null,
+            maxDailyTransactionsUnverified: null,
+            maxDailyTransactionsVerified: null,
+            maxHolderBalance: null,
+            tradingStartTs: null,
+            maxHolders: null,
+            rewardRateBps: null,
+            campaignMultiplierBps: null,
+            campaignStart: null,
+            campaignEnd: null,
+            rewardMint: null,
+            bonusMint: null,
+            bonusRateBps: null,
+            burnAddress: null,
+            volatilityDuration: null,
+            minPriceSlotAge: null,
+            maxPriceSlotAge: null,
+            longTermCooldown: null,
+            streakBalanceFloor: null,
+            minHoldingStreak: null,
+            allowlistRoot: null,
+            largeTransferThresholdCents: null,
+            largeTransferMaxConfidenceBps: null,
+            earlyTransferPenaltyBps: null,
+            authorityExempt: null,
+            blockProgramRecipients: null,
+            paused: null,
+            accrueOnMinBalance: null,
+            registrationsPaused: null,
+          },
+          fields
+        )
+      )
+      .accountsPartial({
+        authority,
+        mint: ctx.mint,
+        rewardsVault: ctx.rewardsVault,
+      })
+      .rpc();
+
+  // Raw SPL token transfer, used to fund test holders without going
+  // through secure_transfer's price checks
+  const splTransferIx = (
+    source: anchor.web3.PublicKey,
+    destination: anchor.web3.PublicKey,
+    owner: anchor.web3.PublicKey,
+    amount: number
+  ) =>
+    new anchor.web3.TransactionInstruction({
+      programId: anchor.utils.token.TOKEN_PROGRAM_ID,
+      keys: [
+        { pubkey: source, isSigner: false, isWritable: true },
+        { pubkey: destination, isSigner: false, isWritable: true },
+        { pubkey: owner, isSigner: true, isWritable: false },
+      ],
+      data: Buffer.concat([
+        Buffer.from([3]),
+        new anchor.BN(amount).toArrayLike(Buffer, "le", 8),
+      ]),
+    });
+
+  // Creates `owner`'s associated token account for ctx's mint, funded with
+  // `amount` base units sent from the wallet's account
+  const fundTokenAccount = async (
+    ctx: Awaited<ReturnType<typeof setupToken>>,
+    owner: anchor.web3.PublicKey,
+    amount: number
+  ) => {
+    const tokenAccount = anchor.utils.token.associatedAddress({
+      mint: ctx.mint,
+      owner,
+    });
+
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        new anchor.web3.TransactionInstruction({
+          programId: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+          keys: [
+            { pubkey: authority, isSigner: true, isWritable: true },
+            { pubkey: tokenAccount, isSigner: false, isWritable: true },
+            { pubkey: owner, isSigner: false, isWritable: false },
+            { pubkey: ctx.mint, isSigner: false, isWritable: false },
+            {
+              pubkey: anchor.web3.SystemProgram.programId,
+              isSigner: false,
+              isWritable: false,
+            },
+            {
+              pubkey: anchor.utils.token.TOKEN_PROGRAM_ID,
+              isSigner: false,
+              isWritable: false,
+            },
+          ],
+          data: Buffer.alloc(0),
+        }),
+        splTransferIx(ctx.tokenAccount, tokenAccount, authority, amount)
+      )
+    );
+
+    return tokenAccount;
+  };
+
+  // initialize_rewards for a new wallet, funded with SOL for rent
+  const registerIx = async (
+    ctx: Awaited<ReturnType<typeof setupToken>>,
+    owner: anchor.web3.Keypair,
+    referrer: anchor.web3.PublicKey | null = null
+  ) => {
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(
+        owner.publicKey,
+        anchor.web3.LAMPORTS_PER_SOL
+      )
+    );
+
+    return program.methods
+      .initializeRewards(referrer)
+      .accountsPartial({
+        authority: owner.publicKey,
+        mint: ctx.mint,
+        holderData: holderDataAddress(ctx.mint, owner.publicKey),
+        referrerData: referrer && holderDataAddress(ctx.mint, referrer),
+      })
+      .instruction();
+  };
+
+  // Creates a funded wallet with an associated token account holding
+  // `amount` base units sent from the wallet's account, registering its
+  // holder data, with `referrer` if given, unless `register` is false
+  const setupHolder = async (
+    ctx: Awaited<ReturnType<typeof setupToken>>,
+    amount: number,
+    register = true,
+    referrer: anchor.web3.PublicKey | null = null
+  ) => {
+    const owner = anchor.web3.Keypair.generate();
+    const holderData = holderDataAddress(ctx.mint, owner.publicKey);
+
+    if (register) {
+      await provider.sendAndConfirm(
+        new anchor.web3.Transaction().add(
+          await registerIx(ctx, owner, referrer)
+        ),
+        [owner]
+      );
+    } else {
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(
+          owner.publicKey,
+          anchor.web3.LAMPORTS_PER_SOL
+        )
+      );
+    }
+
+    const tokenAccount = await fundTokenAccount(ctx, owner.publicKey, amount);
+    return { owner, holderData, tokenAccount };
+  };
+
+  // `count` holders like setupHolder's, registered in one transaction so
+  // they accrue from the same reward index
+  const setupHolders = async (
+    ctx: Awaited<ReturnType<typeof setupToken>>,
+    count: number,
+    amount: number
+  ) => {
+    const owners = Array.from({ length: count }, () =>
+      anchor.web3.Keypair.generate()
+    );
+    const ixs = [];
+    for (const owner of owners) {
+      ixs.push(await registerIx(ctx, owner));
+    }
+    await provider.sendAndConfirm(new anchor.web3.Transaction().add(...ixs), owners);
+
+    const holders = [];
+    for (const owner of owners) {
+      holders.push({
+        owner,
+        holderData: holderDataAddress(ctx.mint, owner.publicKey),
+        tokenAccount: await fundTokenAccount(ctx, owner.publicKey, amount),
+      });
+    }
+    return holders;
+  };
+
+  // Raw SPL Approve from the holder's account to `delegate`
+  const approve = (
+    holder: Awaited<ReturnType<typeof setupHolder>>,
+    delegate: anchor.web3.PublicKey,
+    amount: number
+  ) =>
+    provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        new anchor.web3.TransactionInstruction({
+          programId: anchor.utils.token.TOKEN_PROGRAM_ID,
+          keys: [
+            { pubkey: holder.tokenAccount, isSigner: false, isWritable: true },
+            { pubkey: delegate, isSigner: false, isWritable: false },
+            { pubkey: holder.owner.publicKey, isSigner: true, isWritable: false },
+          ],
+          data: Buffer.concat([
+            Buffer.from([4]),
+            new anchor.BN(amount).toArrayLike(Buffer, "le", 8),
+          ]),
+        })
+      ),
+      [holder.owner]
+    );
+
+  // Drops ctx's holding period and claim cooldown to zero, so a holder can
+  // claim as soon as they are registered
+  const allowImmediateClaims = (ctx: Awaited<ReturnType<typeof setupToken>>) =>
+    updateConfig(ctx, {
+      minHoldingPeriod: new anchor.BN(0),
+      claimCooldown: new anchor.BN(0),
+    });
+
+  // claim_rewards for `holder` in native mode, as an instruction so several
+  // claims can share a transaction and see the same reward index
+  const claimIx = (
+    ctx: Awaited<ReturnType<typeof setupToken>>,
+    holder: {
+      owner: anchor.web3.Keypair;
+      holderData: anchor.web3.PublicKey;
+      tokenAccount: anchor.web3.PublicKey;
+    },
+    referrerData: anchor.web3.PublicKey | null = null
+  ) =>
+    program.methods
+      .claimRewards()
+      .accountsPartial({
+        authority: holder.owner.publicKey,
+        holderData: holder.holderData,
+        mint: ctx.mint,
+        rewardsVault: ctx.rewardsVault,
+        tokenAccount: holder.tokenAccount,
+        referrerData,
+        mintAuthority: authority,
+      })
+      .instruction();
+
+  // Sends `ixs` in one transaction and returns the amounts each claim in it
+  // paid, read from the program logs
+  const sendClaims = async (
+    ixs: anchor.web3.TransactionInstruction[],
+    signers: anchor.web3.Keypair[]
+  ) => {
+    const sig = await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(...ixs),
+      signers,
+      { commitment: "confirmed" }
+    );
+    const tx = await provider.connection.getTransaction(sig, {
+      commitment: "confirmed",
+    });
+    return tx.meta.logMessages
+      .map((log) => /Claimed (\d+) reward tokens/.exec(log))
+      .filter((match) => match)
+      .map((match) => new anchor.BN(match[1]));
+  };
+
+  it("mints the full supply to the authority on initialize_token", async () => {
+    const ctx = await setupToken(6);
+
+    const mint = await provider.connection.getParsedAccountInfo(ctx.mint);
+    const info = (mint.value.data as anchor.web3.ParsedAccountData).parsed.info;
+    assert.equal(info.decimals, 6);
+    assert.equal(info.supply, "1000000000000000");
+
+    const balance = await provider.connection.getTokenAccountBalance(
+      ctx.tokenAccount
+    );
+    assert.equal(balance.value.amount, "1000000000000000");
+
+    const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+    assert.isTrue(vault.authority.equals(authority));
+    assert.isTrue(vault.treasury.equals(ctx.tokenAccount));
+    const stats = await program.account.stats.fetch(ctx.stats);
+    assert.equal(stats.initialSupply.toString(), "1000000000000000");
+  });
+
+  describe("recipient registration", () => {
+    let ctx: Awaited<ReturnType<typeof setupToken>>;
+    let recipient: Awaited<ReturnType<typeof setupHolder>>;
+    let unregistered: Awaited<ReturnType<typeof setupHolder>>;
+
+    const transfer = (
+      to: Awaited<ReturnType<typeof setupHolder>>,
+      toHolderData = to.holderData
+    ) =>
+      program.methods
+        .secureTransfer(new anchor.BN(1_000_000), false, null)
+        .accountsPartial({
+          authority,
+          from: ctx.tokenAccount,
+          to: to.tokenAccount,
+          mint: ctx.mint,
+          treasury: ctx.tokenAccount,
+          holderData: ctx.holderData,
+          toHolderData,
+          rewardsVault: ctx.rewardsVault,
+          priceFeed,
+        })
+        .rpc();
+
+    const setRequired = (required: boolean) =>
+      program.methods
+        .setRequireRecipientRegistered(required)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+
+    const balance = async (h: Awaited<ReturnType<typeof setupHolder>>) =>
+      (await provider.connection.getTokenAccountBalance(h.tokenAccount)).value
+        .amount;
+
+    before(async () => {
+      ctx = await setupToken();
+      recipient = await setupHolder(ctx, 0);
+      unregistered = await setupHolder(ctx, 0, false);
+
+      // Let the wallet's transfers through the dead feed and the cooldown,
+      // so only the registration check can refuse them
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      await program.methods
+        .setAuthorityExempt(true)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+    });
+
+    it("lets unregistered recipients through when not required", async () => {
+      await setRequired(false);
+      assert.isNull(await errorCode(transfer(unregistered)));
+      assert.isNull(await errorCode(transfer(recipient)));
+
+      assert.equal(await balance(unregistered), "1000000");
+      assert.equal(await balance(recipient), "1000000");
+    });
+
+    it("rejects unregistered recipients when required", async () => {
+      await setRequired(true);
+      assert.equal(await errorCode(transfer(unregistered)), "RecipientNotRegistered");
+      assert.isNull(await errorCode(transfer(recipient)));
+
+      assert.equal(await balance(unregistered), "1000000");
+      assert.equal(await balance(recipient), "2000000");
+    });
+
+    it("requires the recipient's own holder data", async () => {
+      // Leaving a registered recipient's PDA out would skip their receipt.
+      // The program ID is how a client leaves an optional account out
+      assert.equal(
+        await errorCode(transfer(recipient, program.programId)),
+        "ConstraintSeeds"
+      );
+      assert.equal(
+        await errorCode(transfer(recipient, ctx.holderData)),
+        "ConstraintSeeds"
+      );
+      assert.equal(
+        await errorCode(transfer(recipient, unregistered.holderData)),
+        "ConstraintSeeds"
+      );
+    });
+  });
+
+  describe("read_price", () => {
+    let ctx: Awaited<ReturnType<typeof setupToken>>;
+
+    before(async () => {
+      ctx = await setupToken();
+    });
+
+    it("surfaces the validation error for an unusable feed", async () => {
+      const code = await errorCode(
+        program.methods
+          .readPrice()
+          .accountsPartial({ rewardsVault: ctx.rewardsVault, priceFeed })
+          .rpc()
+      );
+      assert.equal(code, "InvalidPriceFeed");
+    });
+
+    it("returns the validated price of a healthy feed", async () => {
+      const feed = fixtureFeed("healthy");
+      await useFeed(ctx, feed);
+
+      // $1.00 with a $0.001 confidence, both in 6-decimal USD
+      const reading = await program.methods
+        .readPrice()
+        .accountsPartial({ rewardsVault: ctx.rewardsVault, priceFeed: feed })
+        .view();
+      assert.equal(reading.price.toNumber(), 1_000_000);
+      assert.equal(reading.conf.toNumber(), 1_000);
+      assert.equal(reading.publishTime.toNumber(), 1_767_225_600);
+    });
+
+    it("rejects a stale feed", async () => {
+      const feed = fixtureFeed("stale");
+      assert.equal(await errorCode(useFeed(ctx, feed)), "StalePrice");
+
+      // The vault keeps reading the healthy feed
+      const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.isTrue(vault.priceFeed.equals(fixtureFeed("healthy")));
+    });
+  });
+
+  describe("reward multipliers", () => {
+    let ctx: Awaited<ReturnType<typeof setupToken>>;
+
+    const setMultiplier = (bps: number) =>
+      program.methods
+        .setRewardMultiplier(authority, bps)
+        .accountsPartial({
+          authority,
+          mint: ctx.mint,
+          rewardsVault: ctx.rewardsVault,
+          holderData: ctx.holderData,
+        })
+        .rpc();
+
+    before(async () => {
+      ctx = await setupToken();
+    });
+
+    it("defaults holders to 1x", async () => {
+      const holder = await program.account.holderData.fetch(ctx.holderData);
+      assert.equal(holder.rewardMultiplierBps, 10_000);
+    });
+
+    it("lets the authority grant up to 3x", async () => {
+      await setMultiplier(20_000);
+      const holder = await program.account.holderData.fetch(ctx.holderData);
+      assert.equal(holder.rewardMultiplierBps, 20_000);
+
+      assert.equal(await errorCode(setMultiplier(30_001)), "InvalidRewardMultiplier");
+    });
+
+    it("pays a 2x holder twice what a 1x holder earns", async () => {
+      const [single, double] = await setupHolders(ctx, 2, 100_000_000_000_000);
+      await allowImmediateClaims(ctx);
+      await program.methods
+        .setRewardMultiplier(double.owner.publicKey, 20_000)
+        .accountsPartial({
+          authority,
+          mint: ctx.mint,
+          rewardsVault: ctx.rewardsVault,
+          holderData: double.holderData,
+        })
+        .rpc();
+      await new Promise((resolve) => setTimeout(resolve, 2000));
+
+      // Equal balances over the same period, claimed at the same index
+      const [singlePaid, doublePaid] = await sendClaims(
+        [await claimIx(ctx, single), await claimIx(ctx, double)],
+        [single.owner, double.owner]
+      );
+      assert.isTrue(singlePaid.gtn(0));
+      // Only the truncation of the 1x claim can differ
+      assert.closeTo(doublePaid.toNumber(), singlePaid.muln(2).toNumber(), 1);
+    });
+  });
+
+  describe("per-pair cooldown", () => {
+    let ctx: Awaited<ReturnType<typeof setupToken>>;
+    let transferPair: anchor.web3.PublicKey;
+
+    const transfer = (pair: anchor.web3.PublicKey | null) =>
+      program.methods
+        .secureTransfer(new anchor.BN(1_000_000), false, null)
+        .accountsPartial({
+          authority,
+          from: ctx.tokenAccount,
+          to: ctx.tokenAccount,
+          mint: ctx.mint,
+          treasury: ctx.tokenAccount,
+          holderData: ctx.holderData,
+          toHolderData: ctx.holderData,
+          rewardsVault: ctx.rewardsVault,
+          transferPair: pair,
+          priceFeed,
+        })
+        .rpc();
+
+    before(async () => {
+      ctx = await setupToken();
+      [transferPair] = anchor.web3.PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("pair"),
+          ctx.tokenAccount.toBuffer(),
+          ctx.tokenAccount.toBuffer(),
+        ],
+        program.programId
+      );
+      await program.methods
+        .initializeTransferPair()
+        .accountsPartial({
+          authority,
+          from: ctx.tokenAccount,
+          to: ctx.tokenAccount,
+          transferPair,
+        })
+        .rpc();
+      await program.methods
+        .setPerPairCooldown(true)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+    });
+
+    it("requires the pair account in per-pair mode", async () => {
+      assert.equal(await errorCode(transfer(null)), "MissingTransferPair");
+    });
+
+    it("checks the cooldown against the pair", async () => {
+      const pair = await program.account.transferPair.fetch(transferPair);
+      assert.equal(pair.lastTransfer.toNumber(), 0);
+      assert.isNull(await errorCode(transfer(transferPair)));
+
+      const updated = await program.account.transferPair.fetch(transferPair);
+      assert.isAbove(updated.lastTransfer.toNumber(), 0);
+      assert.equal(
+        await errorCode(transfer(transferPair)),
+        "TransferCooldownActive"
+      );
+    });
+
+    it("lets a holder pay two recipients inside the global window", async () => {
+      const ctx = await setupToken();
+      const recipients = [
+        await setupHolder(ctx, 0, false),
+        await setupHolder(ctx, 0, false),
+      ];
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+
+      const pairs = [];
+      for (const recipient of recipients) {
+        const [pair] = anchor.web3.PublicKey.findProgramAddressSync(
+          [
+            Buffer.from("pair"),
+            ctx.tokenAccount.toBuffer(),
+            recipient.tokenAccount.toBuffer(),
+          ],
+          program.programId
+        );
+        await program.methods
+          .initializeTransferPair()
+          .accountsPartial({
+            authority,
+            from: ctx.tokenAccount,
+            to: recipient.tokenAccount,
+            transferPair: pair,
+          })
+          .rpc();
+        pairs.push(pair);
+      }
+
+      const send = (i: number) =>
+        program.methods
+          .secureTransfer(new anchor.BN(1_000_000), false, null)
+          .accountsPartial({
+            authority,
+            from: ctx.tokenAccount,
+            to: recipients[i].tokenAccount,
+            mint: ctx.mint,
+            treasury: ctx.tokenAccount,
+            holderData: ctx.holderData,
+            toHolderData: recipients[i].holderData,
+            rewardsVault: ctx.rewardsVault,
+            transferPair: pairs[i],
+            priceFeed,
+          })
+          .rpc();
+      const setPerPair = (perPair: boolean) =>
+        program.methods
+          .setPerPairCooldown(perPair)
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+
+      // Globally throttled, the second recipient waits out the cooldown
+      await send(0);
+      assert.equal(await errorCode(send(1)), "TransferCooldownActive");
+
+      // Per pair, only a repeat to the same recipient is throttled
+      await setPerPair(true);
+      await send(1);
+      assert.equal(await errorCode(send(1)), "TransferCooldownActive");
+
+      for (const recipient of recipients) {
+        const balance = await provider.connection.getTokenAccountBalance(
+          recipient.tokenAccount
+        );
+        assert.equal(balance.value.amount, "1000000");
+      }
+    });
+  });
+
+  describe("config validation", () => {
+    let ctx: Awaited<ReturnType<typeof setupToken>>;
+
+    before(async () => {
+      ctx = await setupToken();
+    });
+
+    it("bounds max_reward_per_claim by the total supply", async () => {
+      const setMax = (max: anchor.BN) =>
+        program.methods
+          .setMaxRewardPerClaim(max)
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+
+      assert.equal(
+        await errorCode(setMax(new anchor.BN("1000000000000001"))),
+        "InvalidConfigValue"
+      );
+      await setMax(new anchor.BN(1_000_000));
+      const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.maxRewardPerClaim.toNumber(), 1_000_000);
+    });
+
+    it("bounds the holder cap, trading start and burn address", async () => {
+      const ctx = await setupToken();
+      const configure = (method) =>
+        errorCode(
+          method
+            .accountsPartial({
+              authority,
+              mint: ctx.mint,
+              rewardsVault: ctx.rewardsVault,
+            })
+            .rpc()
+        );
+      const now = Math.floor(Date.now() / 1000);
+
+      // Under one whole token, or over the supply
+      assert.equal(
+        await configure(program.methods.setMaxHolderBalance(new anchor.BN(0))),
+        "InvalidConfigValue"
+      );
+      assert.equal(
+        await configure(
+          program.methods.setMaxHolderBalance(new anchor.BN("1000000000000001"))
+        ),
+        "InvalidConfigValue"
+      );
+      assert.isNull(
+        await configure(program.methods.setMaxHolderBalance(new anchor.BN(1_000_000)))
+      );
+
+      // More than 90 days out
+      assert.equal(
+        await configure(
+          program.methods.setTradingStart(new anchor.BN(now + 91 * 86_400))
+        ),
+        "InvalidConfigValue"
+      );
+      assert.isNull(
+        await configure(program.methods.setTradingStart(new anchor.BN(now - 60)))
+      );
+
+      // Below the wallet's own registration from setupToken
+      await setupHolder(ctx, 0);
+      assert.equal(
+        await configure(program.methods.setMaxHolders(new anchor.BN(1))),
+        "InvalidConfigValue"
+      );
+      assert.isNull(await configure(program.methods.setMaxHolders(new anchor.BN(2))));
+      assert.isNull(await configure(program.methods.setMaxHolders(new anchor.BN(0))));
+
+      // The treasury can't double as the burn address
+      assert.equal(
+        await configure(program.methods.setBurnAddress(ctx.tokenAccount)),
+        "InvalidConfigValue"
+      );
+
+      const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.maxHolderBalance.toNumber(), 1_000_000);
+      assert.equal(vault.tradingStartTs.toNumber(), now - 60);
+      assert.equal(vault.maxHolders.toNumber(), 0);
+      assert.isTrue(vault.burnAddress.equals(anchor.web3.PublicKey.default));
+    });
+
+    it("pays a large accrual in full across capped claims", async () => {
+      const ctx = await setupToken();
+      const [capped, uncapped] = await setupHolders(ctx, 2, 100_000_000_000_000);
+      const setMax = (max: number) =>
+        program.methods
+          .setMaxRewardPerClaim(new anchor.BN(max))
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .instruction();
+
+      await allowImmediateClaims(ctx);
+      await new Promise((resolve) => setTimeout(resolve, 2000));
+
+      // Three claims capped at 10,000, then the cap is lifted and both
+      // holders claim the rest, all at the same reward index
+      const paid = await sendClaims(
+        [
+          await setMax(10_000),
+          await claimIx(ctx, capped),
+          await claimIx(ctx, capped),
+          await claimIx(ctx, capped),
+          await setMax(0),
+          await claimIx(ctx, capped),
+          await claimIx(ctx, uncapped),
+        ],
+        [capped.owner, uncapped.owner]
+      );
+
+      assert.equal(paid.length, 5);
+      assert.deepEqual(
+        paid.slice(0, 3).map((amount) => amount.toNumber()),
+        [10_000, 10_000, 10_000]
+      );
+      const uncappedAccrual = paid[4];
+      assert.isTrue(uncappedAccrual.gtn(30_000));
+      assert.equal(
+        paid
+          .slice(0, 4)
+          .reduce((sum, amount) => sum.add(amount), new anchor.BN(0))
+          .toString(),
+        uncappedAccrual.toString()
+      );
+    });
+  });
+
+  describe("distribute_pool", () => {
+    it("rejects holder data of another mint", async () => {
+      const ctx = await setupToken();
+      const other = await setupToken();
+      const holder = await setupHolder(ctx, 1_000_000);
+      await provider.sendAndConfirm(
+        new anchor.web3.Transaction().add(await registerIx(other, holder.owner)),
+        [holder.owner]
+      );
+
+      const code = await errorCode(
+        program.methods
+          .distributePool(new anchor.BN(100))
+          .accountsPartial({
+            authority,
+            mint: ctx.mint,
+            rewardsVault: ctx.rewardsVault,
+          })
+          .remainingAccounts([
+            {
+              pubkey: holderDataAddress(other.mint, holder.owner.publicKey),
+              isSigner: false,
+              isWritable: true,
+            },
+            { pubkey: holder.tokenAccount, isSigner: false, isWritable: false },
+          ])
+          .rpc()
+      );
+      assert.equal(code, "InvalidDistributionAccounts");
+    });
+
+    it("splits an uneven pool without over-distributing", async () => {
+      const ctx = await setupToken();
+      const holders = [
+        await setupHolder(ctx, 1_000_000),
+        await setupHolder(ctx, 1_000_000),
+        await setupHolder(ctx, 1_000_000),
+      ];
+
+      await program.methods
+        .distributePool(new anchor.BN(100))
+        .accountsPartial({
+          authority,
+          mint: ctx.mint,
+          rewardsVault: ctx.rewardsVault,
+        })
+        .remainingAccounts(
+          [].concat(
+            ...holders.map((h) => [
+              { pubkey: h.holderData, isSigner: false, isWritable: true },
+              { pubkey: h.tokenAccount, isSigner: false, isWritable: false },
+            ])
+          )
+        )
+        .rpc();
+
+      const earned = await Promise.all(
+        holders.map(async (h) =>
+          (
+            await program.account.holderData.fetch(h.holderData)
+          ).rewardsEarned.toNumber()
+        )
+      );
+      // The dust unit goes to the first of the tied largest balances
+      assert.deepEqual(earned, [34, 33, 33]);
+      const pending = await Promise.all(
+        holders.map(async (h) =>
+          (
+            await program.account.holderData.fetch(h.holderData)
+          ).pendingRewards.toNumber()
+        )
+      );
+      assert.deepEqual(pending, [34, 33, 33]);
+    });
+
+    it("pays a holder's share on their next claim", async () => {
+      const ctx = await setupToken();
+      const holder = await setupHolder(ctx, 1_000_000);
+      await allowImmediateClaims(ctx);
+
+      // The first claim settles the accrual, so the second one, at the same
+      // reward index, pays only the distributed share
+      const paid = await sendClaims(
+        [
+          await claimIx(ctx, holder),
+          await program.methods
+            .distributePool(new anchor.BN(1_000))
+            .accountsPartial({
+              authority,
+              mint: ctx.mint,
+              rewardsVault: ctx.rewardsVault,
+            })
+            .remainingAccounts([
+              { pubkey: holder.holderData, isSigner: false, isWritable: true },
+              {
+                pubkey: holder.tokenAccount,
+                isSigner: false,
+                isWritable: false,
+              },
+            ])
+            .instruction(),
+          await claimIx(ctx, holder),
+        ],
+        [holder.owner]
+      );
+
+      assert.equal(paid.length, 2);
+      assert.equal(paid[1].toNumber(), 1_000);
+      const data = await program.account.holderData.fetch(holder.holderData);
+      assert.equal(data.pendingRewards.toNumber(), 0);
+    });
+  });
+
+  describe("review flag", () => {
+    it("lets only the authority clear a holder's flag", async () => {
+      const ctx = await setupToken();
+      const holder = await setupHolder(ctx, 1_000_000);
+
+      const clear = (signer: anchor.web3.Keypair | null) =>
+        program.methods
+          .clearFlag(holder.owner.publicKey)
+          .accountsPartial({
+            authority: signer ? signer.publicKey : authority,
+            mint: ctx.mint,
+            rewardsVault: ctx.rewardsVault,
+            holderData: holder.holderData,
+          })
+          .signers(signer ? [signer] : [])
+          .rpc();
+
+      assert.equal(await errorCode(clear(holder.owner)), "ConstraintHasOne");
+      await clear(null);
+      const data = await program.account.holderData.fetch(holder.holderData);
+      assert.isFalse(data.flagged);
+      assert.equal(data.consecutiveLimitDays, 0);
+    });
+  });
+
+  describe("clawback", () => {
+    let ctx: Awaited<ReturnType<typeof setupToken>>;
+
+    const clawback = (amount: number) =>
+      program.methods
+        .clawback(ctx.tokenAccount, new anchor.BN(amount))
+        .accountsPartial({
+          authority,
+          mint: ctx.mint,
+          rewardsVault: ctx.rewardsVault,
+          from: ctx.tokenAccount,
+        })
+        .rpc();
+
+    const setPaused = (paused: boolean) =>
+      program.methods
+        .setPaused(paused)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+
+    before(async () => {
+      ctx = await setupToken();
+    });
+
+    it("is rejected while the program is running", async () => {
+      assert.equal(await errorCode(clawback(1_000)), "NotPaused");
+    });
+
+    it("burns from the account while paused", async () => {
+      await setPaused(true);
+      const before = await provider.connection.getTokenSupply(ctx.mint);
+      await clawback(1_000);
+      const after = await provider.connection.getTokenSupply(ctx.mint);
+      assert.equal(
+        new anchor.BN(before.value.amount)
+          .sub(new anchor.BN(after.value.amount))
+          .toNumber(),
+        1_000
+      );
+      await setPaused(false);
+    });
+  });
+
+  describe("volatility mode", () => {
+    let ctx: Awaited<ReturnType<typeof setupToken>>;
+
+    const setVolatility = (duration: number) =>
+      program.methods
+        .setVolatilityMode(new anchor.BN(duration))
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+
+    before(async () => {
+      ctx = await setupToken();
+    });
+
+    it("opens a bounded window and can be switched off", async () => {
+      assert.equal(await errorCode(setVolatility(86_401)), "InvalidConfigValue");
+
+      await setVolatility(3_600);
+      let vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.isAbove(vault.volatilityModeUntil.toNumber(), Date.now() / 1000);
+
+      await setVolatility(0);
+      vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.volatilityModeUntil.toNumber(), 0);
+    });
+
+    it("accepts a wide-confidence feed only inside the window", async () => {
+      const feed = fixtureFeed("wide");
+      const readPrice = () =>
+        program.methods
+          .readPrice()
+          .accountsPartial({ rewardsVault: ctx.rewardsVault, priceFeed: feed })
+          .rpc();
+
+      // A 2% interval fails the strict 1% check
+      assert.equal(await errorCode(useFeed(ctx, feed)), "PriceConfidenceTooLow");
+
+      // and passes the relaxed 5% one
+      await setVolatility(10);
+      await useFeed(ctx, feed);
+      assert.isNull(await errorCode(readPrice()));
+
+      // Once the window lapses the strict check is back on its own
+      let code: string | null = null;
+      for (let i = 0; i < 30 && code === null; i++) {
+        await new Promise((resolve) => setTimeout(resolve, 1_000));
+        code = await errorCode(readPrice());
+      }
+      assert.equal(code, "PriceConfidenceTooLow");
+      const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.notEqual(vault.volatilityModeUntil.toNumber(), 0);
+    });
+  });
+
+  describe("transfer caps", () => {
+    it("accepts in-range caps and rejects out-of-range ones", async () => {
+      const ctx = await setupToken();
+      const setCaps = (maxSize: number, maxBalanceBps: number) =>
+        program.methods
+          .setTransferCaps(new anchor.BN(maxSize), maxBalanceBps)
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+
+      assert.equal(await errorCode(setCaps(0, 5_000)), "InvalidConfigValue");
+      assert.equal(await errorCode(setCaps(1_000, 10_001)), "InvalidConfigValue");
+
+      await setCaps(1_000, 5_000);
+      const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.maxTransferSize.toNumber(), 1_000);
+      assert.equal(vault.maxTransferBalanceBps, 5_000);
+    });
+  });
+
+  describe("audit log", () => {
+    it("records each admin action in order", async () => {
+      const ctx = await setupToken();
+      const configure = { authority, rewardsVault: ctx.rewardsVault };
+
+      await program.methods.setPaused(true).accountsPartial(configure).rpc();
+      await program.methods
+        .setMaxRewardPerClaim(new anchor.BN(5_000))
+        .accountsPartial(configure)
+        .rpc();
+      await program.methods.setPaused(false).accountsPartial(configure).rpc();
+
+      const log = await program.account.auditLog.fetch(ctx.auditLog);
+      assert.equal(log.totalEntries.toNumber(), 3);
+      assert.equal(log.nextIndex, 3);
+      assert.deepEqual(
+        log.entries.map((e) => [Object.keys(e.action)[0], e.param.toNumber()]),
+        [
+          ["setPaused", 1],
+          ["setMaxRewardPerClaim", 5_000],
+          ["setPaused", 0],
+        ]
+      );
+      assert.isTrue(log.entries.every((e) => e.actor.equals(authority)));
+    });
+  });
+
+  describe("migration", () => {
+    // Original keypair accounts loaded at genesis (see generate.js)
+    const legacyAuthority = fixtureKeypair("legacy-authority");
+    const legacyVault = fixtureKeypair("legacy-vault").publicKey;
+    const legacyHolder = fixtureKeypair("legacy-holder");
+    const legacyHolderData = fixtureKeypair("legacy-holder-data").publicKey;
+    const mint = anchor.web3.Keypair.generate();
+    const [rewardsVault] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("rewards_vault"), mint.publicKey.toBuffer()],
+      program.programId
+    );
+    // A version 1 vault PDA and its audit log, also loaded at genesis
+    const v1Mint = fixtureKeypair("v1-mint");
+    const [v1Vault] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("rewards_vault"), v1Mint.publicKey.toBuffer()],
+      program.programId
+    );
+    const [stats] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("stats"), mint.publicKey.toBuffer()],
+      program.programId
+    );
+
+    const migrateVault = (signer: anchor.web3.Keypair | null) =>
+      program.methods
+        .migrateLegacyVault()
+        .accountsPartial({
+          authority: signer ? signer.publicKey : authority,
+          mint: mint.publicKey,
+          legacyVault,
+          rewardsVault,
+          stats,
+        })
+        .signers(signer ? [signer] : [])
+        .rpc();
+
+    before(async () => {
+      for (const owner of [legacyAuthority, legacyHolder]) {
+        await provider.connection.confirmTransaction(
+          await provider.connection.requestAirdrop(
+            owner.publicKey,
+            anchor.web3.LAMPORTS_PER_SOL
+          )
+        );
+      }
+
+      // Mints the legacy authority controls, made with the token program's
+      // InitializeMint2 and no freeze authority
+      for (const m of [mint, v1Mint]) {
+        await provider.sendAndConfirm(
+          new anchor.web3.Transaction().add(
+            anchor.web3.SystemProgram.createAccount({
+              fromPubkey: authority,
+              newAccountPubkey: m.publicKey,
+              lamports:
+                await provider.connection.getMinimumBalanceForRentExemption(82),
+              space: 82,
+              programId: anchor.utils.token.TOKEN_PROGRAM_ID,
+            }),
+            new anchor.web3.TransactionInstruction({
+              programId: anchor.utils.token.TOKEN_PROGRAM_ID,
+              keys: [
+                { pubkey: m.publicKey, isSigner: false, isWritable: true },
+              ],
+              data: Buffer.concat([
+                Buffer.from([20, 6]),
+                legacyAuthority.publicKey.toBuffer(),
+                Buffer.from([0]),
+              ]),
+            })
+          ),
+          [m]
+        );
+      }
+    });
+
+    it("upgrades a version 1 vault PDA in place", async () => {
+      const [auditLog] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("audit_log"), v1Vault.toBuffer()],
+        program.programId
+      );
+      const upgrade = (signer: anchor.web3.Keypair | null) =>
+        program.methods
+          .migrateRewardsVault()
+          .accountsPartial({
+            authority: signer ? signer.publicKey : authority,
+            mint: v1Mint.publicKey,
+            rewardsVault: v1Vault,
+            auditLog,
+          })
+          .signers(signer ? [signer] : [])
+          .rpc();
+
+      // Only the vault's authority can upgrade it
+      assert.equal(await errorCode(upgrade(null)), "Unauthorized");
+
+      await upgrade(legacyAuthority);
+
+      // Fields from version 1 are kept
+      const vault = await program.account.rewardsVault.fetch(v1Vault);
+      assert.equal(vault.version, 2);
+      assert.isTrue(vault.authority.equals(legacyAuthority.publicKey));
+      assert.equal(vault.totalRewards.toNumber(), 123_456);
+      assert.equal(vault.maxTransferSize.toNumber(), 1_000_000);
+      assert.equal(vault.claimCooldown.toNumber(), 86_400);
+      assert.equal(vault.rewardRateBps, 750);
+      assert.equal(vault.decimals, 6);
+      // and fields added in version 2 get their defaults
+      assert.equal(vault.minHoldingPeriod.toNumber(), 2_592_000);
+      assert.equal(vault.rewardIndexRemainder.toNumber(), 0);
+      const info = await provider.connection.getAccountInfo(v1Vault);
+      assert.equal(info.data.length, 8 + 1357);
+
+      // Running it again on an up-to-date vault is a no-op
+      await upgrade(legacyAuthority);
+      const again = await program.account.rewardsVault.fetch(v1Vault);
+      assert.equal(again.version, 2);
+      assert.equal(again.totalRewards.toNumber(), 123_456);
+
+      const log = await program.account.auditLog.fetch(auditLog);
+      assert.equal(log.entries.length, 1);
+    });
+
+    it("moves a legacy vault to the mint's vault PDA", async () => {
+      // Only the mint authority can claim the legacy vault for this mint
+      assert.equal(await errorCode(migrateVault(null)), "Unauthorized");
+
+      await migrateVault(legacyAuthority);
+
+      const vault = await program.account.rewardsVault.fetch(rewardsVault);
+      assert.equal(vault.version, 2);
+      assert.isTrue(vault.authority.equals(legacyAuthority.publicKey));
+      assert.equal(vault.totalRewards.toNumber(), 123_456);
+      assert.equal(vault.decimals, 6);
+      assert.isTrue(
+        vault.treasury.equals(
+          anchor.utils.token.associatedAddress({
+            mint: mint.publicKey,
+            owner: legacyAuthority.publicKey,
+          })
+        )
+      );
+      const counts = await program.account.stats.fetch(stats);
+      assert.equal(counts.holderCount.toNumber(), 0);
+      assert.isNull(await provider.connection.getAccountInfo(legacyVault));
+
+      // The closed legacy account can't be migrated again
+      assert.equal(
+        await errorCode(migrateVault(legacyAuthority)),
+        "ConstraintOwner"
+      );
+    });
+
+    it("moves legacy holder data to the holder's PDA", async () => {
+      const holderData = holderDataAddress(
+        mint.publicKey,
+        legacyHolder.publicKey
+      );
+      const migrate = (signer: anchor.web3.Keypair) =>
+        program.methods
+          .migrateHolderData()
+          .accountsPartial({
+            authority: signer.publicKey,
+            mint: mint.publicKey,
+            rewardsVault,
+            legacyHolderData,
+            holderData: holderDataAddress(mint.publicKey, signer.publicKey),
+            stats,
+          })
+          .signers([signer])
+          .rpc();
+
+      // Only the legacy account's own holder can move it
+      assert.equal(await errorCode(migrate(legacyAuthority)), "Unauthorized");
+
+      await migrate(legacyHolder);
+
+      const data = await program.account.holderData.fetch(holderData);
+      assert.isTrue(data.authority.equals(legacyHolder.publicKey));
+      assert.equal(data.rewardsEarned.toNumber(), 42_000);
+      assert.equal(data.lastClaim.toNumber(), 1_700_000_000);
+      assert.equal(data.registeredAt.toNumber(), 1_700_000_000);
+      assert.equal(data.lastTransfer.toNumber(), 1_700_000_600);
+      assert.equal(data.dailyTransactions.toNumber(), 3);
+      assert.equal(data.lastTransactionDate.toNumber(), 19_675);
+      const counts = await program.account.stats.fetch(stats);
+      assert.equal(counts.holderCount.toNumber(), 1);
+      assert.isNull(await provider.connection.getAccountInfo(legacyHolderData));
+    });
+  });
+
+  describe("referrals", () => {
+    let ctx: Awaited<ReturnType<typeof setupToken>>;
+
+    // Registers a new funded holder with the given referrer
+    const register = async (
+      referrer: anchor.web3.PublicKey,
+      referrerData: anchor.web3.PublicKey | null
+    ) => {
+      const owner = anchor.web3.Keypair.generate();
+      const holderData = holderDataAddress(ctx.mint, owner.publicKey);
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(
+          owner.publicKey,
+          anchor.web3.LAMPORTS_PER_SOL
+        )
+      );
+
+      const code = await errorCode(
+        program.methods
+          .initializeRewards(referrer ?? owner.publicKey)
+          .accountsPartial({
+            authority: owner.publicKey,
+            mint: ctx.mint,
+            holderData,
+            referrerData,
+          })
+          .signers([owner])
+          .rpc()
+      );
+      return { code, holderData };
+    };
+
+    before(async () => {
+      ctx = await setupToken();
+    });
+
+    it("stores the referrer of a referred holder", async () => {
+      const referred = await register(authority, ctx.holderData);
+      assert.isNull(referred.code);
+      const data = await program.account.holderData.fetch(referred.holderData);
+      assert.isTrue(data.referrer.equals(authority));
+    });
+
+    it("rejects self-referral", async () => {
+      // A null referrer here means the new holder refers themselves
+      const referred = await register(null, null);
+      assert.equal(referred.code, "SelfReferral");
+    });
+
+    describe("bonus", () => {
+      let ctx: Awaited<ReturnType<typeof setupToken>>;
+
+      before(async () => {
+        ctx = await setupToken();
+        await allowImmediateClaims(ctx);
+        await program.methods
+          .setReferralBonus(1_000)
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+      });
+
+      it("pays the referrer's bonus on their next claim", async () => {
+        const referrer = await setupHolder(ctx, 1_000_000);
+        const referred = await setupHolder(
+          ctx,
+          100_000_000_000_000,
+          true,
+          referrer.owner.publicKey
+        );
+        await new Promise((resolve) => setTimeout(resolve, 2000));
+
+        // The referrer settles first, so their second claim, at the same
+        // reward index, pays only the bonus
+        const paid = await sendClaims(
+          [
+            await claimIx(ctx, referrer),
+            await claimIx(ctx, referred, referrer.holderData),
+            await claimIx(ctx, referrer),
+          ],
+          [referrer.owner, referred.owner]
+        );
+
+        assert.equal(paid.length, 3);
+        assert.isTrue(paid[1].gtn(0));
+        assert.equal(paid[2].toString(), paid[1].divn(10).toString());
+      });
+
+      it("requires the referrer's holder data on a referred claim", async () => {
+        const referrer = await setupHolder(ctx, 1_000_000);
+        const referred = await setupHolder(
+          ctx,
+          1_000_000,
+          true,
+          referrer.owner.publicKey
+        );
+        const claim = (referrerData: anchor.web3.PublicKey | null) =>
+          program.methods
+            .claimRewards()
+            .accountsPartial({
+              authority: referred.owner.publicKey,
+              holderData: referred.holderData,
+              mint: ctx.mint,
+              rewardsVault: ctx.rewardsVault,
+              tokenAccount: referred.tokenAccount,
+              referrerData,
+              mintAuthority: authority,
+            })
+            .signers([referred.owner])
+            .rpc();
+
+        // Leaving the referrer out, or passing any other holder's data,
+        // can't drop the bonus
+        assert.equal(await errorCode(claim(null)), "MissingReferrer");
+        assert.equal(await errorCode(claim(ctx.holderData)), "ConstraintSeeds");
+        assert.isNull(await errorCode(claim(referrer.holderData)));
+      });
+
+      it("skips the bonus when the referrer has no holder data", async () => {
+        const referrer = await setupHolder(ctx, 0);
+        const orphaned = await setupHolder(
+          ctx,
+          100_000_000_000_000,
+          true,
+          referrer.owner.publicKey
+        );
+        await program.methods
+          .closeHolder()
+          .accountsPartial({
+            authority: referrer.owner.publicKey,
+            mint: ctx.mint,
+            holderData: referrer.holderData,
+          })
+          .signers([referrer.owner])
+          .rpc();
+        await new Promise((resolve) => setTimeout(resolve, 2000));
+
+        // The closed account is still passed, showing there's nothing there
+        const paid = await sendClaims(
+          [await claimIx(ctx, orphaned, referrer.holderData)],
+          [orphaned.owner]
+        );
+        assert.equal(paid.length, 1);
+        assert.isTrue(paid[0].gtn(0));
+        assert.isNull(await provider.connection.getAccountInfo(referrer.holderData));
+      });
+    });
+  });
+
+  describe("price unavailable policy", () => {
+    let ctx: Awaited<ReturnType<typeof setupToken>>;
+
+    const setPolicy = (policy) =>
+      program.methods
+        .setPriceUnavailablePolicy(policy)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+
+    const transfer = () =>
+      program.methods
+        .secureTransfer(new anchor.BN(1_000_000), false, null)
+        .accountsPartial({
+          authority,
+          from: ctx.tokenAccount,
+          to: ctx.tokenAccount,
+          mint: ctx.mint,
+          treasury: ctx.tokenAccount,
+          holderData: ctx.holderData,
+          toHolderData: ctx.holderData,
+          rewardsVault: ctx.rewardsVault,
+          transferPair: null,
+          priceFeed,
+        })
+        .rpc();
+
+    before(async () => {
+      ctx = await setupToken();
+    });
+
+    it("blocks on a dead feed by default", async () => {
+      assert.equal(await errorCode(transfer()), "InvalidPriceFeed");
+    });
+
+    it("needs a cached price to fall back on", async () => {
+      await setPolicy({ useLastKnown: {} });
+      assert.equal(await errorCode(transfer()), "StalePrice");
+    });
+
+    it("lets transfers past the price check with max limits", async () => {
+      await setPolicy({ allowWithMaxLimits: {} });
+      assert.isNull(await errorCode(transfer()));
+    });
+  });
+
+  describe("price status", () => {
+    let ctx: Awaited<ReturnType<typeof setupToken>>;
+
+    before(async () => {
+      ctx = await setupToken();
+      await useFeed(ctx, fixtureFeed("healthy"));
+    });
+
+    it("maps a halted market and an unknown status to their own errors", async () => {
+      assert.equal(
+        await errorCode(useFeed(ctx, fixtureFeed("halted"))),
+        "MarketHalted"
+      );
+      assert.equal(
+        await errorCode(useFeed(ctx, fixtureFeed("unknown"))),
+        "PriceUnknown"
+      );
+    });
+
+    it("never adopts a halted feed, even with the last-known fallback on", async () => {
+      await program.methods
+        .setHaltedUsesLastKnown(true)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+
+      // The fallback covers transfers on the stored feed; a new feed still
+      // has to produce a price of its own
+      assert.equal(
+        await errorCode(useFeed(ctx, fixtureFeed("halted"))),
+        "MarketHalted"
+      );
+      assert.equal(
+        await errorCode(useFeed(ctx, fixtureFeed("unknown"))),
+        "PriceUnknown"
+      );
+      const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.isTrue(vault.priceFeed.equals(fixtureFeed("healthy")));
+    });
+  });
+
+  describe("campaigns", () => {
+    it("stores a valid campaign window and rejects inverted ones", async () => {
+      const ctx = await setupToken();
+      const setCampaign = (bps: number, start: number, end: number) =>
+        program.methods
+          .setCampaign(bps, new anchor.BN(start), new anchor.BN(end))
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+
+      assert.equal(
+        await errorCode(setCampaign(20_000, 2_000, 1_000)),
+        "InvalidConfigValue"
+      );
+      assert.equal(
+        await errorCode(setCampaign(40_000, 1_000, 2_000)),
+        "InvalidRewardMultiplier"
+      );
+
+      await setCampaign(20_000, 1_000, 2_000);
+      const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.campaignMultiplierBps, 20_000);
+      assert.equal(vault.campaignStart.toNumber(), 1_000);
+      assert.equal(vault.campaignEnd.toNumber(), 2_000);
+    });
+  });
+
+  describe("transfer fee", () => {
+    it("defaults the treasury to the initial supply account", async () => {
+      const ctx = await setupToken();
+      const setFee = (feeBps: number, burnBps: number) =>
+        program.methods
+          .setTransferFee(feeBps, burnBps)
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+
+      let vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.isTrue(vault.treasury.equals(ctx.tokenAccount));
+      assert.equal(vault.transferFeeBps, 0);
+
+      assert.equal(await errorCode(setFee(1_001, 0)), "InvalidConfigValue");
+      assert.equal(await errorCode(setFee(100, 10_001)), "InvalidConfigValue");
+
+      await setFee(100, 2_500);
+      vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.transferFeeBps, 100);
+      assert.equal(vault.feeBurnBps, 2_500);
+    });
+
+    it("splits the fee between the treasury and a burn", async () => {
+      const ctx = await setupToken();
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      const balance = async (account: anchor.web3.PublicKey) =>
+        Number((await provider.connection.getTokenAccountBalance(account)).value.amount);
+      const supply = async () =>
+        Number((await provider.connection.getTokenSupply(ctx.mint)).value.amount);
+
+      // [fee bps, burn share bps, recipient, treasury, burned] for 1,000,000
+      const splits = [
+        [100, 0, 990_000, 10_000, 0],
+        [100, 2_500, 990_000, 7_500, 2_500],
+        [1_000, 10_000, 900_000, 0, 100_000],
+      ];
+      for (const [feeBps, burnBps, received, treasuryFee, burned] of splits) {
+        await program.methods
+          .setTransferFee(feeBps, burnBps)
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+        // A fresh sender each time, clear of the cooldown
+        const sender = await setupHolder(ctx, 1_000_000);
+        const recipient = await setupHolder(ctx, 0);
+        const treasuryBefore = await balance(ctx.tokenAccount);
+        const supplyBefore = await supply();
+        const burnedBefore = (
+          await program.account.rewardsVault.fetch(ctx.rewardsVault)
+        ).totalBurned.toNumber();
+
+        await program.methods
+          .secureTransfer(new anchor.BN(1_000_000), false, null)
+          .accountsPartial({
+            authority: sender.owner.publicKey,
+            from: sender.tokenAccount,
+            to: recipient.tokenAccount,
+            mint: ctx.mint,
+            treasury: ctx.tokenAccount,
+            holderData: sender.holderData,
+            toHolderData: recipient.holderData,
+            rewardsVault: ctx.rewardsVault,
+            transferPair: null,
+            priceFeed,
+          })
+          .signers([sender.owner])
+          .rpc();
+
+        assert.equal(await balance(sender.tokenAccount), 0);
+        assert.equal(await balance(recipient.tokenAccount), received);
+        assert.equal(await balance(ctx.tokenAccount) - treasuryBefore, treasuryFee);
+        assert.equal(supplyBefore - (await supply()), burned);
+        const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+        assert.equal(vault.totalBurned.toNumber() - burnedBefore, burned);
+      }
+    });
+  });
+
+  describe("refresh_price_feed", () => {
+    it("keeps the stored feed when the new one fails validation", async () => {
+      const ctx = await setupToken();
+      const newFeed = anchor.web3.Keypair.generate().publicKey;
+
+      const code = await errorCode(
+        program.methods
+          .refreshPriceFeed(newFeed)
+          .accountsPartial({
+            authority,
+            rewardsVault: ctx.rewardsVault,
+            newPriceFeed: newFeed,
+          })
+          .rpc()
+      );
+      assert.equal(code, "InvalidPriceFeed");
+
+      const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.isTrue(vault.priceFeed.equals(priceFeed));
+    });
+  });
+
+  describe("daily reset offset", () => {
+    it("accepts real UTC offsets only", async () => {
+      const ctx = await setupToken();
+      const setOffset = (offset: number) =>
+        program.methods
+          .setDailyResetOffset(new anchor.BN(offset))
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+
+      assert.equal(await errorCode(setOffset(15 * 3_600)), "InvalidConfigValue");
+      await setOffset(-5 * 3_600);
+      const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.dailyResetOffsetSecs.toNumber(), -5 * 3_600);
+    });
+  });
+
+  describe("transfer_checked", () => {
+    // Raw SPL TransferChecked, the instruction secure_transfer now uses
+    const transferCheckedIx = (
+      ctx: Awaited<ReturnType<typeof setupToken>>,
+      destination: anchor.web3.PublicKey,
+      amount: number,
+      decimals: number
+    ) =>
+      new anchor.web3.TransactionInstruction({
+        programId: anchor.utils.token.TOKEN_PROGRAM_ID,
+        keys: [
+          { pubkey: ctx.tokenAccount, isSigner: false, isWritable: true },
+          { pubkey: ctx.mint, isSigner: false, isWritable: false },
+          { pubkey: destination, isSigner: false, isWritable: true },
+          { pubkey: authority, isSigner: true, isWritable: false },
+        ],
+        data: Buffer.concat([
+          Buffer.from([12]),
+          new anchor.BN(amount).toArrayLike(Buffer, "le", 8),
+          Buffer.from([decimals]),
+        ]),
+      });
+
+    it("moves tokens with the mint's decimals and rejects a mismatch", async () => {
+      const ctx = await setupToken();
+      const holder = await setupHolder(ctx, 0);
+      const send = (decimals: number) =>
+        provider.sendAndConfirm(
+          new anchor.web3.Transaction().add(
+            transferCheckedIx(ctx, holder.tokenAccount, 1_000_000, decimals)
+          )
+        );
+
+      await send(6);
+      // The token program's MintDecimalsMismatch
+      assert.include(await errorCode(send(9)), "custom program error: 0x12");
+
+      const balance = await provider.connection.getTokenAccountBalance(
+        holder.tokenAccount
+      );
+      assert.equal(balance.value.amount, "1000000");
+    });
+
+    it("checks the mint passed to secure_transfer against the source", async () => {
+      const ctx = await setupToken();
+      const other = await setupToken();
+      const code = await errorCode(
+        program.methods
+          .secureTransfer(new anchor.BN(1_000_000), false, null)
+          .accountsPartial({
+            authority,
+            from: ctx.tokenAccount,
+            to: ctx.tokenAccount,
+            mint: other.mint,
+            treasury: ctx.tokenAccount,
+            holderData: ctx.holderData,
+            toHolderData: ctx.holderData,
+            rewardsVault: ctx.rewardsVault,
+            priceFeed,
+          })
+          .rpc()
+      );
+      assert.equal(code, "ConstraintAddress");
+    });
+  });
+
+  describe("max rewards per holder", () => {
+    it("clamps a distribution share to the cap", async () => {
+      const ctx = await setupToken();
+      const holder = await setupHolder(ctx, 1_000_000);
+      const distribute = (amount: number) =>
+        program.methods
+          .distributePool(new anchor.BN(amount))
+          .accountsPartial({
+            authority,
+            mint: ctx.mint,
+            rewardsVault: ctx.rewardsVault,
+          })
+          .remainingAccounts([
+            { pubkey: holder.holderData, isSigner: false, isWritable: true },
+            { pubkey: holder.tokenAccount, isSigner: false, isWritable: false },
+          ])
+          .rpc({ commitment: "confirmed" });
+
+      await program.methods
+        .setMaxRewardsPerHolder(new anchor.BN(1_000))
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+
+      await distribute(600);
+      const sig = await distribute(700);
+
+      // Read the clamp event back from the confirmed transaction's logs
+      const tx = await provider.connection.getTransaction(sig, {
+        commitment: "confirmed",
+      });
+      const parser = new anchor.EventParser(program.programId, program.coder);
+      const events = Array.from(parser.parseLogs(tx.meta.logMessages));
+      const clamped = events.find((e) => e.name === "rewardAccrualClamped");
+
+      const data = await program.account.holderData.fetch(holder.holderData);
+      assert.equal(data.rewardsEarned.toNumber(), 1_000);
+      const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.totalRewards.toNumber(), 1_000);
+      assert.isDefined(clamped);
+      assert.equal(clamped.data.requested.toString(), "700");
+      assert.equal(clamped.data.credited.toString(), "400");
+    });
+  });
+
+  describe("bonus rewards", () => {
+    it("pays the bonus token from the reserve without touching native rewards", async () => {
+      const ctx = await setupToken();
+      const bonus = await setupToken();
+      const reserve = anchor.utils.token.associatedAddress({
+        mint: bonus.mint,
+        owner: ctx.rewardsVault,
+      });
+
+      // Create the vault-owned reserve and fund it with bonus tokens
+      await provider.sendAndConfirm(
+        new anchor.web3.Transaction().add(
+          new anchor.web3.TransactionInstruction({
+            programId: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+            keys: [
+              { pubkey: authority, isSigner: true, isWritable: true },
+              { pubkey: reserve, isSigner: false, isWritable: true },
+              { pubkey: ctx.rewardsVault, isSigner: false, isWritable: false },
+              { pubkey: bonus.mint, isSigner: false, isWritable: false },
+              {
+                pubkey: anchor.web3.SystemProgram.programId,
+                isSigner: false,
+                isWritable: false,
+              },
+              {
+                pubkey: anchor.utils.token.TOKEN_PROGRAM_ID,
+                isSigner: false,
+                isWritable: false,
+              },
+            ],
+            data: Buffer.alloc(0),
+          }),
+          splTransferIx(bonus.tokenAccount, reserve, authority, 1_000_000_000_000)
+        )
+      );
+
+      const claim = (
+        holder: {
+          owner: anchor.web3.Keypair | null;
+          holderData: anchor.web3.PublicKey;
+          tokenAccount: anchor.web3.PublicKey;
+        } = {
+          owner: null,
+          holderData: ctx.holderData,
+          tokenAccount: ctx.tokenAccount,
+        }
+      ) =>
+        program.methods
+          .claimBonusRewards()
+          .accountsPartial({
+            authority: holder.owner ? holder.owner.publicKey : authority,
+            holderData: holder.holderData,
+            mint: ctx.mint,
+            rewardsVault: ctx.rewardsVault,
+            tokenAccount: holder.tokenAccount,
+            bonusMint: bonus.mint,
+            bonusReserve: reserve,
+            bonusTokenAccount: bonus.tokenAccount,
+          })
+          .signers(holder.owner ? [holder.owner] : [])
+          .rpc();
+
+      assert.equal(await errorCode(claim()), "BonusRewardNotConfigured");
+
+      await program.methods
+        .setBonusReward(bonus.mint, 10_000)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      assert.equal(await errorCode(claim()), "MinHoldingPeriodNotMet");
+
+      // A holder who received their tokens since registering was seen at a
+      // zero balance, so the bonus accrues on nothing until their next claim
+      await allowImmediateClaims(ctx);
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      const recipient = await setupHolder(ctx, 0);
+      await program.methods
+        .secureTransfer(new anchor.BN(50_000_000_000), false, null)
+        .accountsPartial({
+          authority,
+          from: ctx.tokenAccount,
+          to: recipient.tokenAccount,
+          mint: ctx.mint,
+          treasury: ctx.tokenAccount,
+          holderData: ctx.holderData,
+          toHolderData: recipient.holderData,
+          rewardsVault: ctx.rewardsVault,
+          transferPair: null,
+          priceFeed,
+        })
+        .rpc();
+      await new Promise((resolve) => setTimeout(resolve, 2000));
+      assert.equal(await errorCode(claim(recipient)), "NoBonusRewards");
+      await claim();
+
+      const data = await program.account.holderData.fetch(ctx.holderData);
+      assert.isTrue(data.bonusRewardsEarned.gtn(0));
+      assert.equal(data.rewardsEarned.toNumber(), 0);
+      assert.equal(
+        data.bonusMinBalance.toString(),
+        (await provider.connection.getTokenAccountBalance(ctx.tokenAccount)).value.amount
+      );
+
+      const reserveBalance = await provider.connection.getTokenAccountBalance(reserve);
+      assert.equal(
+        reserveBalance.value.amount,
+        new anchor.BN(1_000_000_000_000).sub(data.bonusRewardsEarned).toString()
+      );
+    });
+  });
+
+  describe("bootstrap", () => {
+    const params = {
+      decimals: 6,
+      maxTransferSize: new anchor.BN(500_000),
+      maxTransferBalanceBps: 5_000,
+      maxRewardPerClaim: new anchor.BN(0),
+      maxRewardsPerHolder: new anchor.BN(0),
+      mintRateLimit: new anchor.BN(0),
+      referralBonusBps: 500,
+      transferFeeBps: 0,
+      feeBurnBps: 0,
+      dailyResetOffsetSecs: new anchor.BN(0),
+      priceUnavailablePolicy: { allowWithMaxLimits: {} },
+      requireRecipientRegistered: false,
+      perPairCooldown: false,
+      recordRejections: false,
+    };
+
+    const bootstrap = async (overrides: object) => {
+      const mint = anchor.web3.Keypair.generate();
+      const holderData = holderDataAddress(mint.publicKey, authority);
+      const [rewardsVault] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("rewards_vault"), mint.publicKey.toBuffer()],
+        program.programId
+      );
+      const tokenAccount = anchor.utils.token.associatedAddress({
+        mint: mint.publicKey,
+        owner: authority,
+      });
+      const code = await errorCode(
+        program.methods
+          .bootstrap(Object.assign({}, params, overrides))
+          .accountsPartial({
+            authority,
+            mint: mint.publicKey,
+            tokenAccount,
+            rewardsVault,
+            holderData,
+          })
+          .signers([mint])
+          .rpc()
+      );
+      return {
+        code,
+        mint: mint.publicKey,
+        holderData,
+        rewardsVault,
+        tokenAccount,
+      };
+    };
+
+    it("sets up a token that can transfer and claim straight away", async () => {
+      const ctx = await bootstrap({});
+      assert.isNull(ctx.code);
+
+      const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.maxTransferSize.toNumber(), 500_000);
+      assert.equal(vault.maxTransferBalanceBps, 5_000);
+      assert.equal(vault.referralBonusBps, 500);
+      assert.deepEqual(vault.priceUnavailablePolicy, { allowWithMaxLimits: {} });
+
+      const holder = await program.account.holderData.fetch(ctx.holderData);
+      assert.isTrue(holder.authority.equals(authority));
+
+      // Both instructions accept the bootstrapped accounts. The transfer
+      // goes through on the max-limits policy; the claim only stops on the
+      // 30-day hold
+      const transferCode = await errorCode(
+        program.methods
+          .secureTransfer(new anchor.BN(1_000_000), false, null)
+          .accountsPartial({
+            authority,
+            from: ctx.tokenAccount,
+            to: ctx.tokenAccount,
+            mint: ctx.mint,
+            treasury: ctx.tokenAccount,
+            holderData: ctx.holderData,
+            toHolderData: ctx.holderData,
+            rewardsVault: ctx.rewardsVault,
+            transferPair: null,
+            priceFeed,
+          })
+          .rpc()
+      );
+      assert.isNull(transferCode);
+
+      const claimCode = await errorCode(
+        program.methods
+          .claimRewards()
+          .accountsPartial({
+            authority,
+            holderData: ctx.holderData,
+            mint: ctx.mint,
+            rewardsVault: ctx.rewardsVault,
+            tokenAccount: ctx.tokenAccount,
+            referrerData: null,
+            mintAuthority: authority,
+          })
+          .rpc()
+      );
+      assert.equal(claimCode, "MinHoldingPeriodNotMet");
+    });
+
+    it("creates nothing when a config value is invalid", async () => {
+      const ctx = await bootstrap({ transferFeeBps: 1_001 });
+      assert.equal(ctx.code, "InvalidConfigValue");
+      assert.isNull(await provider.connection.getAccountInfo(ctx.mint));
+      assert.isNull(await provider.connection.getAccountInfo(ctx.rewardsVault));
+    });
+  });
+
+  describe("absolute confidence cap", () => {
+    let ctx: Awaited<ReturnType<typeof setupToken>>;
+
+    const setCap = (cents: number) =>
+      program.methods
+        .setMaxConfidenceCents(new anchor.BN(cents))
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+
+    beforeEach(async () => {
+      ctx = await setupToken();
+    });
+
+    it("stores the cap alongside the ratio check", async () => {
+      let vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.maxConfidenceCents.toNumber(), 0);
+
+      assert.equal(await errorCode(setCap(1_000_001)), "InvalidConfigValue");
+      await setCap(50);
+      vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.maxConfidenceCents.toNumber(), 50);
+
+      // Price reads still go through feed validation first
+      const code = await errorCode(
+        program.methods
+          .readPrice()
+          .accountsPartial({ rewardsVault: ctx.rewardsVault, priceFeed })
+          .rpc()
+      );
+      assert.equal(code, "InvalidPriceFeed");
+    });
+
+    it("fails a price within the ratio but over the cap", async () => {
+      const feed = fixtureFeed("high-priced");
+      const readPrice = () =>
+        program.methods
+          .readPrice()
+          .accountsPartial({ rewardsVault: ctx.rewardsVault, priceFeed: feed })
+          .rpc();
+      await useFeed(ctx, feed);
+
+      // A 0.2% ratio, but a $100 interval
+      await setCap(5_000);
+      assert.equal(await errorCode(readPrice()), "PriceConfidenceTooLow");
+      await setCap(10_000);
+      assert.isNull(await errorCode(readPrice()));
+    });
+
+    it("fails a price under the cap but over the ratio", async () => {
+      // A $0.02 interval on a $1.00 price is 2%
+      await setCap(50);
+      assert.equal(
+        await errorCode(useFeed(ctx, fixtureFeed("wide"))),
+        "PriceConfidenceTooLow"
+      );
+    });
+  });
+
+  describe("holder lock", () => {
+    it("blocks outbound transfers until the lock expires", async () => {
+      const ctx = await setupToken();
+      const lock = (until: number) =>
+        program.methods
+          .setLockedUntil(authority, new anchor.BN(until))
+          .accountsPartial({
+            authority,
+            mint: ctx.mint,
+            rewardsVault: ctx.rewardsVault,
+            holderData: ctx.holderData,
+          })
+          .rpc();
+      const transfer = () =>
+        program.methods
+          .secureTransfer(new anchor.BN(1_000_000), false, null)
+          .accountsPartial({
+            authority,
+            from: ctx.tokenAccount,
+            to: ctx.tokenAccount,
+            mint: ctx.mint,
+            treasury: ctx.tokenAccount,
+            holderData: ctx.holderData,
+            toHolderData: ctx.holderData,
+            rewardsVault: ctx.rewardsVault,
+            transferPair: null,
+            priceFeed,
+          })
+          .rpc();
+
+      const now = Math.floor(Date.now() / 1000);
+      await lock(now + 3_600);
+      assert.equal(await errorCode(transfer()), "HolderLocked");
+
+      // An expired lock no longer blocks; the transfer then stops at the
+      // price check, which localnet can't satisfy
+      await lock(now - 1);
+      assert.equal(await errorCode(transfer()), "InvalidPriceFeed");
+    });
+  });
+
+  describe("sweep_dust", () => {
+    // Raw SPL Approve, letting the vault move up to `amount` from `source`
+    const approveIx = (
+      source: anchor.web3.PublicKey,
+      delegate: anchor.web3.PublicKey,
+      owner: anchor.web3.PublicKey,
+      amount: number
+    ) =>
+      new anchor.web3.TransactionInstruction({
+        programId: anchor.utils.token.TOKEN_PROGRAM_ID,
+        keys: [
+          { pubkey: source, isSigner: false, isWritable: true },
+          { pubkey: delegate, isSigner: false, isWritable: false },
+          { pubkey: owner, isSigner: true, isWritable: false },
+        ],
+        data: Buffer.concat([
+          Buffer.from([4]),
+          new anchor.BN(amount).toArrayLike(Buffer, "le", 8),
+        ]),
+      });
+
+    it("moves only consenting balances below the threshold", async () => {
+      const ctx = await setupToken();
+      const dust = await setupHolder(ctx, 100);
+      const large = await setupHolder(ctx, 1_000_000);
+
+      for (const holder of [dust, large]) {
+        await program.methods
+          .setDustSweepConsent(true)
+          .accountsPartial({
+            authority: holder.owner.publicKey,
+            mint: ctx.mint,
+            holderData: holder.holderData,
+          })
+          .signers([holder.owner])
+          .rpc();
+        await provider.sendAndConfirm(
+          new anchor.web3.Transaction().add(
+            approveIx(
+              holder.tokenAccount,
+              ctx.rewardsVault,
+              holder.owner.publicKey,
+              1_000_000
+            )
+          ),
+          [holder.owner]
+        );
+      }
+
+      const sweep = () =>
+        program.methods
+          .sweepDust()
+          .accountsPartial({
+            authority,
+            mint: ctx.mint,
+            rewardsVault: ctx.rewardsVault,
+            treasury: ctx.tokenAccount,
+          })
+          .remainingAccounts(
+            [].concat(
+              ...[dust, large].map((h) => [
+                { pubkey: h.holderData, isSigner: false, isWritable: false },
+                { pubkey: h.tokenAccount, isSigner: false, isWritable: true },
+              ])
+            )
+          )
+          .rpc();
+
+      assert.equal(await errorCode(sweep()), "DustSweepDisabled");
+
+      await program.methods
+        .setDustThreshold(new anchor.BN(1_000))
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      const treasuryBefore = await provider.connection.getTokenAccountBalance(
+        ctx.tokenAccount
+      );
+      await sweep();
+
+      const balance = async (account: anchor.web3.PublicKey) =>
+        (await provider.connection.getTokenAccountBalance(account)).value.amount;
+      assert.equal(await balance(dust.tokenAccount), "0");
+      assert.equal(await balance(large.tokenAccount), "1000000");
+      assert.equal(
+        await balance(ctx.tokenAccount),
+        new anchor.BN(treasuryBefore.value.amount).addn(100).toString()
+      );
+    });
+  });
+
+  describe("claim cooldown", () => {
+    it("keeps the holding period gate on the first claim", async () => {
+      const ctx = await setupToken();
+      const setCooldown = (cooldown: number) =>
+        program.methods
+          .setClaimCooldown(new anchor.BN(cooldown))
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+
+      // The default cooldown is shorter than the default holding period
+      let vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.claimCooldown.toNumber(), 604_800);
+      assert.isBelow(
+        vault.claimCooldown.toNumber(),
+        vault.minHoldingPeriod.toNumber()
+      );
+
+      assert.equal(await errorCode(setCooldown(2_592_001)), "InvalidConfigValue");
+      await setCooldown(0);
+      vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.claimCooldown.toNumber(), 0);
+
+      // A zero cooldown doesn't let a new holder skip the holding period
+      const code = await errorCode(
+        program.methods
+          .claimRewards()
+          .accountsPartial({
+            authority,
+            holderData: ctx.holderData,
+            mint: ctx.mint,
+            rewardsVault: ctx.rewardsVault,
+            tokenAccount: ctx.tokenAccount,
+            referrerData: null,
+            mintAuthority: authority,
+          })
+          .rpc()
+      );
+      assert.equal(code, "MinHoldingPeriodNotMet");
+    });
+
+    it("lets the authority shorten the holding period", async () => {
+      const ctx = await setupToken();
+      const setPeriod = (period: number) =>
+        program.methods
+          .setMinHoldingPeriod(new anchor.BN(period))
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+
+      let vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.minHoldingPeriod.toNumber(), 2_592_000);
+
+      assert.equal(await errorCode(setPeriod(-1)), "InvalidConfigValue");
+      assert.equal(await errorCode(setPeriod(31_536_001)), "InvalidConfigValue");
+      await setPeriod(0);
+      vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.minHoldingPeriod.toNumber(), 0);
+
+      // With no holding period the first claim only waits on the cooldown
+      const holder = await setupHolder(ctx, 1_000_000);
+      assert.equal(
+        await errorCode(
+          program.methods
+            .claimRewards()
+            .accountsPartial({
+              authority: holder.owner.publicKey,
+              holderData: holder.holderData,
+              mint: ctx.mint,
+              rewardsVault: ctx.rewardsVault,
+              tokenAccount: holder.tokenAccount,
+              referrerData: null,
+              mintAuthority: authority,
+            })
+            .signers([holder.owner])
+            .rpc()
+        ),
+        "ClaimCooldownActive"
+      );
+    });
+
+    it("runs from the time of a capped claim", async () => {
+      const ctx = await setupToken();
+      const holder = await setupHolder(ctx, 100_000_000_000_000);
+      for (const ix of [
+        program.methods.setMaxRewardPerClaim(new anchor.BN(10_000)),
+        program.methods.setClaimCooldown(new anchor.BN(3_600)),
+        program.methods.setMinHoldingPeriod(new anchor.BN(0)),
+      ]) {
+        await ix
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+      }
+      await new Promise((resolve) => setTimeout(resolve, 2000));
+
+      const paid = await sendClaims([await claimIx(ctx, holder)], [holder.owner]);
+      assert.equal(paid[0].toNumber(), 10_000);
+
+      // The capped claim only moves last_claim over the share it paid, but
+      // the cooldown still starts from the claim itself
+      const data = await program.account.holderData.fetch(holder.holderData);
+      assert.isTrue(data.lastClaim.lt(data.lastClaimAt));
+      assert.isTrue(data.lastClaimAt.gtn(1_700_000_000));
+      assert.equal(
+        await errorCode(
+          program.methods
+            .claimRewards()
+            .accountsPartial({
+              authority: holder.owner.publicKey,
+              holderData: holder.holderData,
+              mint: ctx.mint,
+              rewardsVault: ctx.rewardsVault,
+              tokenAccount: holder.tokenAccount,
+              referrerData: null,
+              mintAuthority: authority,
+            })
+            .signers([holder.owner])
+            .rpc()
+        ),
+        "ClaimCooldownActive"
+      );
+    });
+  });
+
+  describe("burn address", () => {
+    it("is disabled until the authority sets one", async () => {
+      const ctx = await setupToken();
+      const burnAddress = anchor.web3.Keypair.generate().publicKey;
+
+      let vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.isTrue(vault.burnAddress.equals(anchor.web3.PublicKey.default));
+      assert.equal(vault.totalBurned.toNumber(), 0);
+
+      await program.methods
+        .setBurnAddress(burnAddress)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.isTrue(vault.burnAddress.equals(burnAddress));
+    });
+
+    it("burns what is sent to it", async () => {
+      const ctx = await setupToken();
+      const sender = await setupHolder(ctx, 10_000_000);
+      const burnAccount = await setupHolder(ctx, 0);
+      for (const ix of [
+        program.methods.setPriceUnavailablePolicy({ allowWithMaxLimits: {} }),
+        program.methods.setBurnAddress(burnAccount.tokenAccount),
+      ]) {
+        await ix
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+      }
+      const supply = async () =>
+        (await provider.connection.getTokenSupply(ctx.mint)).value.amount;
+      const supplyBefore = await supply();
+
+      await program.methods
+        .secureTransfer(new anchor.BN(1_000_000), false, null)
+        .accountsPartial({
+          authority: sender.owner.publicKey,
+          from: sender.tokenAccount,
+          to: burnAccount.tokenAccount,
+          mint: ctx.mint,
+          treasury: ctx.tokenAccount,
+          holderData: sender.holderData,
+          toHolderData: burnAccount.holderData,
+          rewardsVault: ctx.rewardsVault,
+          transferPair: null,
+          priceFeed,
+        })
+        .signers([sender.owner])
+        .rpc();
+
+      const balance = async (account: anchor.web3.PublicKey) =>
+        (await provider.connection.getTokenAccountBalance(account)).value.amount;
+      assert.equal(await balance(sender.tokenAccount), "9000000");
+      assert.equal(await balance(burnAccount.tokenAccount), "0");
+      assert.equal(
+        new anchor.BN(supplyBefore).sub(new anchor.BN(await supply())).toNumber(),
+        1_000_000
+      );
+      const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.totalBurned.toNumber(), 1_000_000);
+    });
+  });
+
+  describe("tokens_for_usd", () => {
+    it("needs a live price like preview_usd_value", async () => {
+      const ctx = await setupToken();
+      const accounts = { mint: ctx.mint, rewardsVault: ctx.rewardsVault, priceFeed };
+
+      // Both directions read the same feed, so on localnet both stop at it
+      assert.equal(
+        await errorCode(
+          program.methods.tokensForUsd(new anchor.BN(5_000)).accountsPartial(accounts).rpc()
+        ),
+        "InvalidPriceFeed"
+      );
+      assert.equal(
+        await errorCode(
+          program.methods
+            .previewUsdValue(new anchor.BN(1_000_000))
+            .accountsPartial(accounts)
+            .rpc()
+        ),
+        "InvalidPriceFeed"
+      );
+    });
+  });
+
+  describe("9-decimal mint", () => {
+    let ctx: Awaited<ReturnType<typeof setupToken>>;
+    const feed = fixtureFeed("healthy");
+    const unit = 1_000_000_000;
+
+    before(async () => {
+      ctx = await setupToken(9);
+      await useFeed(ctx, feed);
+    });
+
+    it("mints the full supply in 9-decimal base units", async () => {
+      const mint = await provider.connection.getTokenSupply(ctx.mint);
+      assert.equal(mint.value.decimals, 9);
+      assert.equal(mint.value.amount, "1000000000000000000");
+      const treasury = await provider.connection.getTokenAccountBalance(
+        ctx.tokenAccount
+      );
+      assert.equal(treasury.value.amount, "1000000000000000000");
+      const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.decimals, 9);
+    });
+
+    it("refuses more decimals than the supply fits", async () => {
+      const setup = setupToken(10).then((created) => created.mint.toBase58());
+      assert.equal(await errorCode(setup), "InvalidConfigValue");
+    });
+
+    it("values whole tokens at the feed price", async () => {
+      const accounts = { mint: ctx.mint, rewardsVault: ctx.rewardsVault, priceFeed: feed };
+
+      // The healthy feed prices a whole token at $1.00
+      const one = await program.methods
+        .previewUsdValue(new anchor.BN(unit))
+        .accountsPartial(accounts)
+        .view();
+      assert.equal(one.usdValue.toNumber(), 1_000_000);
+      assert.isFalse(one.meetsMinimum);
+      const fifty = await program.methods
+        .previewUsdValue(new anchor.BN(50 * unit))
+        .accountsPartial(accounts)
+        .view();
+      assert.isTrue(fifty.meetsMinimum);
+
+      const tokens = await program.methods
+        .tokensForUsd(new anchor.BN(5_000))
+        .accountsPartial(accounts)
+        .view();
+      assert.equal(tokens.toString(), String(50 * unit));
+    });
+
+    it("transfers with the mint's decimals and USD minimum", async () => {
+      const sender = await setupHolder(ctx, 100 * unit);
+      const recipients = [await setupHolder(ctx, 0), await setupHolder(ctx, 0)];
+      const transfer = (
+        recipient: Awaited<ReturnType<typeof setupHolder>>,
+        amount: number
+      ) =>
+        program.methods
+          .secureTransfer(new anchor.BN(amount), false, null)
+          .accountsPartial({
+            authority: sender.owner.publicKey,
+            from: sender.tokenAccount,
+            to: recipient.tokenAccount,
+            mint: ctx.mint,
+            treasury: ctx.tokenAccount,
+            holderData: sender.holderData,
+            toHolderData: recipient.holderData,
+            rewardsVault: ctx.rewardsVault,
+            transferPair: null,
+            priceFeed: feed,
+          })
+          .signers([sender.owner])
+          .rpc();
+
+      // $1.00 is under the $50 minimum, $60 is over it
+      assert.equal(
+        await errorCode(transfer(recipients[0], unit)),
+        "BelowMinimumUSD"
+      );
+      await transfer(recipients[1], 60 * unit);
+
+      const received = await provider.connection.getTokenAccountBalance(
+        recipients[1].tokenAccount
+      );
+      assert.equal(received.value.amount, String(60 * unit));
+      assert.equal(received.value.decimals, 9);
+    });
+
+    it("bounds base-unit settings by the mint's decimals", async () => {
+      const accounts = { authority, rewardsVault: ctx.rewardsVault };
+
+      // A whole token is the most dust there can be
+      assert.equal(
+        await errorCode(
+          program.methods
+            .setDustThreshold(new anchor.BN(unit + 1))
+            .accountsPartial(accounts)
+            .rpc()
+        ),
+        "InvalidConfigValue"
+      );
+      await program.methods
+        .setDustThreshold(new anchor.BN(unit))
+        .accountsPartial(accounts)
+        .rpc();
+
+      // Reward caps go up to the total supply in base units
+      assert.equal(
+        await errorCode(
+          program.methods
+            .setMaxRewardPerClaim(new anchor.BN("1000000000000000001"))
+            .accountsPartial(accounts)
+            .rpc()
+        ),
+        "InvalidConfigValue"
+      );
+      await program.methods
+        .setMaxRewardPerClaim(new anchor.BN("1000000000000000000"))
+        .accountsPartial(accounts)
+        .rpc();
+
+      // The transfer size cap is in whole tokens whatever the decimals
+      assert.equal(
+        await errorCode(
+          program.methods
+            .setTransferCaps(new anchor.BN(1_000_000_001), 10_000)
+            .accountsPartial(accounts)
+            .rpc()
+        ),
+        "InvalidConfigValue"
+      );
+      await program.methods
+        .setTransferCaps(new anchor.BN(1_000_000_000), 10_000)
+        .accountsPartial(accounts)
+        .rpc();
+
+      const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.dustThreshold.toNumber(), unit);
+      assert.equal(vault.maxRewardPerClaim.toString(), "1000000000000000000");
+      assert.equal(vault.maxTransferSize.toNumber(), 1_000_000_000);
+    });
+  });
+
+  describe("approved authorities", () => {
+    it("restricts transfer signers only while the list is non-empty", async () => {
+      const ctx = await setupToken();
+      const holder = await setupHolder(ctx, 1_000_000);
+      const setApproved = (approved: anchor.web3.PublicKey, add: boolean) =>
+        (add
+          ? program.methods.addApprovedAuthority(approved)
+          : program.methods.removeApprovedAuthority(approved)
+        )
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+      const transfer = (
+        signer: anchor.web3.Keypair | null,
+        from: anchor.web3.PublicKey,
+        holderData: anchor.web3.PublicKey
+      ) =>
+        program.methods
+          .secureTransfer(new anchor.BN(1_000), false, null)
+          .accountsPartial({
+            authority: signer ? signer.publicKey : authority,
+            from,
+            to: ctx.tokenAccount,
+            mint: ctx.mint,
+            treasury: ctx.tokenAccount,
+            holderData,
+            toHolderData: ctx.holderData,
+            rewardsVault: ctx.rewardsVault,
+            transferPair: null,
+            priceFeed,
+          })
+          .signers(signer ? [signer] : [])
+          .rpc();
+      const fromWallet = () => transfer(null, ctx.tokenAccount, ctx.holderData);
+      const fromHolder = () =>
+        transfer(holder.owner, holder.tokenAccount, holder.holderData);
+
+      // Open mode: everyone reaches the price check
+      assert.equal(await errorCode(fromWallet()), "InvalidPriceFeed");
+      assert.equal(await errorCode(fromHolder()), "InvalidPriceFeed");
+
+      await setApproved(authority, true);
+      assert.equal(await errorCode(fromWallet()), "InvalidPriceFeed");
+      assert.equal(await errorCode(fromHolder()), "AuthorityNotApproved");
+
+      await setApproved(authority, false);
+      const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.lengthOf(vault.approvedAuthorities, 0);
+      assert.equal(await errorCode(fromHolder()), "InvalidPriceFeed");
+    });
+  });
+
+  describe("holder state writes", () => {
+    it("persists the counters of a completed transfer", async () => {
+      const ctx = await setupToken();
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+
+      await program.methods
+        .secureTransfer(new anchor.BN(1_000_000), false, null)
+        .accountsPartial({
+          authority,
+          from: ctx.tokenAccount,
+          to: ctx.tokenAccount,
+          mint: ctx.mint,
+          treasury: ctx.tokenAccount,
+          holderData: ctx.holderData,
+          toHolderData: ctx.holderData,
+          rewardsVault: ctx.rewardsVault,
+          transferPair: null,
+          priceFeed,
+        })
+        .rpc();
+
+      const data = await program.account.holderData.fetch(ctx.holderData);
+      assert.equal(data.dailyTransactions.toNumber(), 1);
+      assert.isTrue(data.lastTransfer.gtn(0));
+    });
+
+    it("leaves claim state unchanged when the mint step fails", async () => {
+      const ctx = await setupToken();
+      const before = await program.account.holderData.fetch(ctx.holderData);
+
+      const code = await errorCode(
+        program.methods
+          .claimRewards()
+          .accountsPartial({
+            authority,
+            holderData: ctx.holderData,
+            mint: ctx.mint,
+            rewardsVault: ctx.rewardsVault,
+            tokenAccount: ctx.tokenAccount,
+            referrerData: null,
+            mintAuthority: anchor.web3.Keypair.generate().publicKey,
+          })
+          .rpc()
+      );
+      assert.equal(code, "ConstraintRaw");
+
+      const after = await program.account.holderData.fetch(ctx.holderData);
+      assert.equal(after.lastClaim.toString(), before.lastClaim.toString());
+      assert.equal(after.rewardsEarned.toString(), before.rewardsEarned.toString());
+    });
+
+    it("only settles the claimant's holder data for the mint", async () => {
+      const ctx = await setupToken();
+      const other = await setupToken();
+      await allowImmediateClaims(ctx);
+      await allowImmediateClaims(other);
+
+      // The wallet's holder data of another mint can't stand in for this
+      // one's, on either claim path
+      const accounts = {
+        authority,
+        holderData: other.holderData,
+        mint: ctx.mint,
+        rewardsVault: ctx.rewardsVault,
+        tokenAccount: ctx.tokenAccount,
+        mintAuthority: authority,
+      };
+      for (const claim of [
+        () =>
+          program.methods
+            .claimRewards()
+            .accountsPartial({ ...accounts, referrerData: null })
+            .rpc(),
+        () =>
+          program.methods
+            .emergencyClaim()
+            .accountsPartial({ ...accounts, treasury: ctx.tokenAccount })
+            .rpc(),
+      ]) {
+        assert.equal(await errorCode(claim()), "ConstraintSeeds");
+      }
+    });
+
+    it("only settles against a token account of the mint", async () => {
+      const ctx = await setupToken();
+      const other = await setupToken();
+      await allowImmediateClaims(ctx);
+
+      // The wallet's account of another mint holds a balance the claim
+      // would otherwise be paid on
+      const accounts = {
+        authority,
+        holderData: ctx.holderData,
+        mint: ctx.mint,
+        rewardsVault: ctx.rewardsVault,
+        tokenAccount: other.tokenAccount,
+        mintAuthority: authority,
+      };
+      for (const claim of [
+        () =>
+          program.methods
+            .claimRewards()
+            .accountsPartial({ ...accounts, referrerData: null })
+            .rpc(),
+        () =>
+          program.methods
+            .emergencyClaim()
+            .accountsPartial({ ...accounts, treasury: ctx.tokenAccount })
+            .rpc(),
+      ]) {
+        assert.equal(await errorCode(claim()), "ConstraintTokenMint");
+      }
+    });
+  });
+
+  describe("price slot age", () => {
+    it("requires the minimum age to sit below the maximum", async () => {
+      const ctx = await setupToken();
+      const setAge = (min: number, max: number) =>
+        program.methods
+          .setPriceSlotAge(new anchor.BN(min), new anchor.BN(max))
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+
+      assert.equal(await errorCode(setAge(10, 10)), "InvalidConfigValue");
+      assert.equal(await errorCode(setAge(0, 1_501)), "InvalidConfigValue");
+
+      await setAge(2, 150);
+      const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.minPriceSlotAge.toNumber(), 2);
+      assert.equal(vault.maxPriceSlotAge.toNumber(), 150);
+
+      // A minimum with no maximum is allowed
+      await setAge(2, 0);
+    });
+
+    it("bounds the age of the fixture's publish slot", async () => {
+      const ctx = await setupToken();
+      const feed = fixtureFeed("healthy");
+      const setAge = (min: number, max: number) =>
+        program.methods
+          .setPriceSlotAge(new anchor.BN(min), new anchor.BN(max))
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+      const readPrice = () =>
+        program.methods
+          .readPrice()
+          .accountsPartial({ rewardsVault: ctx.rewardsVault, priceFeed: feed })
+          .rpc();
+      await useFeed(ctx, feed);
+
+      // The fixture price was published at slot 1, so its age is the
+      // current slot less one and only grows from here
+      const age = (await provider.connection.getSlot()) - 1;
+      await setAge(age + 1_000, 0);
+      assert.equal(await errorCode(readPrice()), "PriceSlotTooRecent");
+      await setAge(age, 0);
+      assert.isNull(await errorCode(readPrice()));
+
+      await setAge(0, 1);
+      assert.equal(await errorCode(readPrice()), "StalePrice");
+    });
+  });
+
+  describe("get_holder_state", () => {
+    it("returns a versioned copy of the stored holder data", async () => {
+      const ctx = await setupToken();
+      const state = await program.methods
+        .getHolderState()
+        .accountsPartial({ mint: ctx.mint, holderData: ctx.holderData })
+        .view();
+      const data = await program.account.holderData.fetch(ctx.holderData);
+
+      assert.equal(state.version, 2);
+      assert.isTrue(state.authority.equals(data.authority));
+      assert.equal(state.rewardsEarned.toString(), data.rewardsEarned.toString());
+      assert.equal(state.lastClaim.toString(), data.lastClaim.toString());
+      assert.equal(state.lastTransfer.toString(), data.lastTransfer.toString());
+      assert.equal(
+        state.dailyTransactions.toString(),
+        data.dailyTransactions.toString()
+      );
+      assert.equal(state.rewardMultiplierBps, data.rewardMultiplierBps);
+      assert.equal(state.flagged, data.flagged);
+      assert.equal(state.registeredAt.toString(), data.registeredAt.toString());
+    });
+  });
+
+  describe("holder data on first transfer", () => {
+    let ctx: Awaited<ReturnType<typeof setupToken>>;
+
+    const transfer = (
+      holder: Awaited<ReturnType<typeof setupHolder>>,
+      holderData: anchor.web3.PublicKey
+    ) =>
+      program.methods
+        .secureTransfer(new anchor.BN(1_000_000), false, null)
+        .accountsPartial({
+          authority: holder.owner.publicKey,
+          from: holder.tokenAccount,
+          to: ctx.tokenAccount,
+          mint: ctx.mint,
+          treasury: ctx.tokenAccount,
+          holderData,
+          toHolderData: ctx.holderData,
+          rewardsVault: ctx.rewardsVault,
+          transferPair: null,
+          priceFeed,
+        })
+        .signers([holder.owner])
+        .rpc();
+
+    before(async () => {
+      ctx = await setupToken();
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+    });
+
+    it("creates the sender's holder data without initialize_rewards", async () => {
+      const holder = await setupHolder(ctx, 10_000_000, false);
+      assert.isNull(await provider.connection.getAccountInfo(holder.holderData));
+
+      await transfer(holder, holder.holderData);
+
+      const data = await program.account.holderData.fetch(holder.holderData);
+      assert.isTrue(data.authority.equals(holder.owner.publicKey));
+      assert.equal(data.dailyTransactions.toNumber(), 1);
+    });
+
+    it("won't use another holder's data", async () => {
+      const holder = await setupHolder(ctx, 10_000_000, false);
+      const other = await setupHolder(ctx, 10_000_000);
+      assert.equal(
+        await errorCode(transfer(holder, other.holderData)),
+        "ConstraintSeeds"
+      );
+    });
+  });
+
+  describe("accrual delay", () => {
+    it("bounds the delay and records the holder's registration time", async () => {
+      const ctx = await setupToken();
+      const setDelay = (delay: number) =>
+        program.methods
+          .setAccrualDelay(new anchor.BN(delay))
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+
+      assert.equal(await errorCode(setDelay(-1)), "InvalidConfigValue");
+      assert.equal(await errorCode(setDelay(7_776_001)), "InvalidConfigValue");
+      await setDelay(604_800);
+      const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.accrualDelay.toNumber(), 604_800);
+
+      // Accrual starts from registered_at + accrual_delay
+      const data = await program.account.holderData.fetch(ctx.holderData);
+      assert.equal(data.registeredAt.toString(), data.lastClaim.toString());
+    });
+  });
+
+  describe("update_config", () => {
+    it("applies all provided fields or none of them", async () => {
+      const ctx = await setupToken();
+      const update = (fields: object) => updateConfig(ctx, fields);
+
+      await update({
+        maxTransferSize: new anchor.BN(250_000),
+        transferFeeBps: 50,
+        recordRejections: true,
+      });
+      let vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.maxTransferSize.toNumber(), 250_000);
+      assert.equal(vault.transferFeeBps, 50);
+      assert.isTrue(vault.recordRejections);
+      assert.equal(vault.maxTransferBalanceBps, 10_000);
+
+      // One bad field rejects the whole update
+      const code = await errorCode(
+        update({ transferFeeBps: 100, referralBonusBps: 2_001 })
+      );
+      assert.equal(code, "InvalidConfigValue");
+      vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.transferFeeBps, 50);
+      assert.equal(vault.referralBonusBps, 0);
+    });
+
+    it("checks paired settings against the vault's current values", async () => {
+      const ctx = await setupToken();
+      const update = (fields: object) => errorCode(updateConfig(ctx, fields));
+
+      // Both daily limits default to 10, so a lone unverified limit of 11
+      // would pass the verified one
+      assert.equal(
+        await update({ maxDailyTransactionsUnverified: new anchor.BN(11) }),
+        "InvalidConfigValue"
+      );
+      assert.isNull(
+        await update({
+          maxDailyTransactionsUnverified: new anchor.BN(11),
+          maxDailyTransactionsVerified: new anchor.BN(20),
+        })
+      );
+
+      // A campaign end before the stored start
+      assert.isNull(
+        await update({
+          campaignStart: new anchor.BN(2_000),
+          campaignEnd: new anchor.BN(3_000),
+        })
+      );
+      assert.equal(
+        await update({ campaignEnd: new anchor.BN(1_000) }),
+        "InvalidConfigValue"
+      );
+
+      // The same bounds as the single setters
+      assert.equal(
+        await update({ burnAddress: ctx.tokenAccount }),
+        "InvalidConfigValue"
+      );
+      assert.equal(
+        await update({ maxHolderBalance: new anchor.BN(0) }),
+        "InvalidConfigValue"
+      );
+
+      const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.maxDailyTransactionsUnverified.toNumber(), 11);
+      assert.equal(vault.maxDailyTransactionsVerified.toNumber(), 20);
+      assert.equal(vault.campaignStart.toNumber(), 2_000);
+      assert.equal(vault.campaignEnd.toNumber(), 3_000);
+    });
+
+    it("accrues at the old reward rate before changing it", async () => {
+      const ctx = await setupToken();
+      const before = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      await new Promise((resolve) => setTimeout(resolve, 2000));
+
+      await updateConfig(ctx, { rewardRateBps: 1_000 });
+
+      const after = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(after.rewardRateBps, 1_000);
+      assert.isAbove(
+        after.indexUpdatedAt.toNumber(),
+        before.indexUpdatedAt.toNumber()
+      );
+      assert.isTrue(after.rewardIndex.gt(before.rewardIndex));
+    });
+  });
+
+  describe("authority exemption", () => {
+    it("lets only the vault authority skip transfer limits", async () => {
+      const ctx = await setupToken();
+      const holder = await setupHolder(ctx, 10_000_000);
+      await updateConfig(ctx, {
+        maxTransferSize: new anchor.BN(1),
+        priceUnavailablePolicy: { allowWithMaxLimits: {} },
+      });
+
+      // Two whole tokens against a one-token cap
+      const transfer = (signer: anchor.web3.Keypair | null) =>
+        program.methods
+          .secureTransfer(new anchor.BN(2_000_000), false, null)
+          .accountsPartial({
+            authority: signer ? signer.publicKey : authority,
+            from: signer ? holder.tokenAccount : ctx.tokenAccount,
+            to: signer ? ctx.tokenAccount : holder.tokenAccount,
+            mint: ctx.mint,
+            treasury: ctx.tokenAccount,
+            holderData: signer ? holder.holderData : ctx.holderData,
+            toHolderData: signer ? ctx.holderData : holder.holderData,
+            rewardsVault: ctx.rewardsVault,
+            transferPair: null,
+            priceFeed,
+          })
+          .signers(signer ? [signer] : [])
+          .rpc();
+
+      assert.equal(await errorCode(transfer(null)), "ExceedsMaxSize");
+
+      await program.methods
+        .setAuthorityExempt(true)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      assert.isNull(await errorCode(transfer(null)));
+      assert.equal(await errorCode(transfer(holder.owner)), "ExceedsMaxSize");
+
+      // Spending a holder's tokens as their delegate isn't the authority's
+      // own transfer, so the limits still apply
+      await approve(holder, authority, 2_000_000);
+      assert.equal(
+        await errorCode(
+          program.methods
+            .secureTransfer(new anchor.BN(2_000_000), true, null)
+            .accountsPartial({
+              authority,
+              from: holder.tokenAccount,
+              to: ctx.tokenAccount,
+              mint: ctx.mint,
+              treasury: ctx.tokenAccount,
+              holderData: holder.holderData,
+              toHolderData: ctx.holderData,
+              rewardsVault: ctx.rewardsVault,
+              transferPair: null,
+              priceFeed,
+            })
+            .rpc()
+        ),
+        "ExceedsMaxSize"
+      );
+    });
+  });
+
+  describe("early transfer penalty", () => {
+    it("charges fresh holders' transfers to the treasury when enabled", async () => {
+      const ctx = await setupToken();
+      const recipient = await setupHolder(ctx, 0);
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      const setPenalty = (bps: number) =>
+        program.methods
+          .setEarlyTransferPenalty(bps)
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+      const transfer = (sender: Awaited<ReturnType<typeof setupHolder>>) =>
+        program.methods
+          .secureTransfer(new anchor.BN(1_000_000), false, null)
+          .accountsPartial({
+            authority: sender.owner.publicKey,
+            from: sender.tokenAccount,
+            to: recipient.tokenAccount,
+            mint: ctx.mint,
+            treasury: ctx.tokenAccount,
+            holderData: sender.holderData,
+            toHolderData: recipient.holderData,
+            rewardsVault: ctx.rewardsVault,
+            transferPair: null,
+            priceFeed,
+          })
+          .signers([sender.owner])
+          .rpc();
+      const balance = async (account: anchor.web3.PublicKey) =>
+        Number(
+          (await provider.connection.getTokenAccountBalance(account)).value.amount
+        );
+
+      assert.equal(await errorCode(setPenalty(2_001)), "InvalidConfigValue");
+
+      // Without a penalty the full amount arrives
+      await transfer(await setupHolder(ctx, 10_000_000));
+      assert.equal(await balance(recipient.tokenAccount), 1_000_000);
+
+      // A second fresh sender pays 10% to the treasury
+      await setPenalty(1_000);
+      const treasuryBefore = await balance(ctx.tokenAccount);
+      await transfer(await setupHolder(ctx, 10_000_000));
+      assert.equal(await balance(recipient.tokenAccount), 1_900_000);
+      assert.equal(await balance(ctx.tokenAccount), treasuryBefore + 100_000);
+
+      // The recipient's acquisition time moved with the received tokens
+      const data = await program.account.holderData.fetch(recipient.holderData);
+      assert.isTrue(data.acquiredAt.gte(data.registeredAt));
+    });
+  });
+
+  describe("repair_counters", () => {
+    it("normalizes a daily counter dated in the future", async () => {
+      const ctx = await setupToken();
+      const sender = await setupHolder(ctx, 10_000_000);
+      const recipient = await setupHolder(ctx, 0);
+      const setOffset = (offset: number) =>
+        program.methods
+          .setDailyResetOffset(new anchor.BN(offset))
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+      const repair = (signer: anchor.web3.Keypair | null) =>
+        program.methods
+          .repairCounters()
+          .accountsPartial({
+            authority: signer ? signer.publicKey : authority,
+            mint: ctx.mint,
+            holderData: sender.holderData,
+          })
+          .signers(signer ? [signer] : [])
+          .rpc();
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+
+      // Transfer while days start 14 hours early, then move the reset 28
+      // hours later so the recorded day is ahead of today
+      await setOffset(14 * 3_600);
+      await program.methods
+        .secureTransfer(new anchor.BN(1_000_000), false, null)
+        .accountsPartial({
+          authority: sender.owner.publicKey,
+          from: sender.tokenAccount,
+          to: recipient.tokenAccount,
+          mint: ctx.mint,
+          treasury: ctx.tokenAccount,
+          holderData: sender.holderData,
+          toHolderData: recipient.holderData,
+          rewardsVault: ctx.rewardsVault,
+          transferPair: null,
+          priceFeed,
+        })
+        .signers([sender.owner])
+        .rpc();
+      await setOffset(-14 * 3_600);
+
+      const before = await program.account.holderData.fetch(sender.holderData);
+      assert.equal(before.dailyTransactions.toNumber(), 1);
+      const today = Math.floor((Date.now() / 1000 - 14 * 3_600) / 86_400);
+      assert.isAbove(before.lastTransactionDate.toNumber(), today);
+
+      // Other holders can't repair someone else's counters
+      assert.equal(await errorCode(repair(recipient.owner)), "Unauthorized");
+
+      await repair(sender.owner);
+      const after = await program.account.holderData.fetch(sender.holderData);
+      assert.equal(after.dailyTransactions.toNumber(), 0);
+      assert.isAtMost(after.lastTransactionDate.toNumber(), today + 1);
+      assert.isBelow(
+        after.lastTransactionDate.toNumber(),
+        before.lastTransactionDate.toNumber()
+      );
+
+      // The authority may repair too, and consistent counters are left alone
+      await repair(null);
+      const again = await program.account.holderData.fetch(sender.holderData);
+      assert.equal(
+        again.lastTransactionDate.toNumber(),
+        after.lastTransactionDate.toNumber()
+      );
+    });
+  });
+
+  describe("reward mint", () => {
+    it("routes claims to a stablecoin reserve once configured", async () => {
+      const ctx = await setupToken();
+      const stable = await setupToken();
+      const reserve = anchor.utils.token.associatedAddress({
+        mint: stable.mint,
+        owner: ctx.rewardsVault,
+      });
+      const setRewardMint = (rewardMint: anchor.web3.PublicKey) =>
+        program.methods
+          .setRewardMint(rewardMint)
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+      const claim = (rewardTokenAccount: anchor.web3.PublicKey) =>
+        program.methods
+          .claimRewards()
+          .accountsPartial({
+            authority,
+            holderData: ctx.holderData,
+            mint: ctx.mint,
+            rewardsVault: ctx.rewardsVault,
+            tokenAccount: ctx.tokenAccount,
+            referrerData: null,
+            mintAuthority: authority,
+            priceFeed,
+            rewardMint: stable.mint,
+            rewardReserve: reserve,
+            rewardTokenAccount,
+          })
+          .rpc();
+
+      // Create the vault-owned reserve and fund it with stablecoins
+      await provider.sendAndConfirm(
+        new anchor.web3.Transaction().add(
+          new anchor.web3.TransactionInstruction({
+            programId: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+            keys: [
+              { pubkey: authority, isSigner: true, isWritable: true },
+              { pubkey: reserve, isSigner: false, isWritable: true },
+              { pubkey: ctx.rewardsVault, isSigner: false, isWritable: false },
+              { pubkey: stable.mint, isSigner: false, isWritable: false },
+              {
+                pubkey: anchor.web3.SystemProgram.programId,
+                isSigner: false,
+                isWritable: false,
+              },
+              {
+                pubkey: anchor.utils.token.TOKEN_PROGRAM_ID,
+                isSigner: false,
+                isWritable: false,
+              },
+            ],
+            data: Buffer.alloc(0),
+          }),
+          splTransferIx(stable.tokenAccount, reserve, authority, 1_000_000_000)
+        )
+      );
+
+      let vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.isTrue(vault.rewardMint.equals(anchor.web3.PublicKey.default));
+
+      await setRewardMint(stable.mint);
+      vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.isTrue(vault.rewardMint.equals(stable.mint));
+
+      // Payouts must land in an account of the reward mint
+      assert.equal(await errorCode(claim(ctx.tokenAccount)), "ConstraintRaw");
+      assert.equal(
+        await errorCode(claim(stable.tokenAccount)),
+        "MinHoldingPeriodNotMet"
+      );
+
+      // Clearing the reward mint restores native rewards
+      await setRewardMint(anchor.web3.PublicKey.default);
+      vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.isTrue(vault.rewardMint.equals(anchor.web3.PublicKey.default));
+    });
+  });
+
+  describe("subscriptions", () => {
+    let ctx: Awaited<ReturnType<typeof setupToken>>;
+    let payee: Awaited<ReturnType<typeof setupHolder>>;
+
+    before(async () => {
+      ctx = await setupToken();
+      payee = await setupHolder(ctx, 0);
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+    });
+
+    // Subscribes a fresh payer to `payments` payments of 1 token every
+    // `interval` seconds, the first due a minute ago
+    const subscribe = async (payments: number, interval = 600) => {
+      const payer = await setupHolder(ctx, 10_000_000);
+      const [subscription] = anchor.web3.PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("subscription"),
+          payer.tokenAccount.toBuffer(),
+          payee.tokenAccount.toBuffer(),
+        ],
+        program.programId
+      );
+      const firstDue = Math.floor(Date.now() / 1000) - 60;
+      const code = await errorCode(
+        program.methods
+          .createSubscription(
+            new anchor.BN(1_000_000),
+            new anchor.BN(interval),
+            new anchor.BN(firstDue),
+            payments
+          )
+          .accountsPartial({
+            authority: payer.owner.publicKey,
+            from: payer.tokenAccount,
+            to: payee.tokenAccount,
+            subscription,
+          })
+          .signers([payer.owner])
+          .rpc()
+      );
+      return { payer, subscription, firstDue, code };
+    };
+
+    const execute = (sub: Awaited<ReturnType<typeof subscribe>>) =>
+      program.methods
+        .executeSubscription()
+        .accountsPartial({
+          subscription: sub.subscription,
+          from: sub.payer.tokenAccount,
+          to: payee.tokenAccount,
+          mint: ctx.mint,
+          treasury: ctx.tokenAccount,
+          holderData: sub.payer.holderData,
+          toHolderData: payee.holderData,
+          rewardsVault: ctx.rewardsVault,
+          priceFeed,
+        })
+        .rpc();
+
+    const balance = async (account: anchor.web3.PublicKey) =>
+      Number(
+        (await provider.connection.getTokenAccountBalance(account)).value.amount
+      );
+
+    it("pays a due subscription and rejects the next payment early", async () => {
+      const sub = await subscribe(3);
+      const payeeBefore = await balance(payee.tokenAccount);
+
+      await execute(sub);
+      assert.equal(await balance(payee.tokenAccount), payeeBefore + 1_000_000);
+      assert.equal(await balance(sub.payer.tokenAccount), 9_000_000);
+
+      const data = await program.account.subscription.fetch(sub.subscription);
+      assert.equal(data.remainingPayments, 2);
+      assert.equal(data.nextDue.toNumber(), sub.firstDue + 600);
+
+      assert.equal(await errorCode(execute(sub)), "SubscriptionNotDue");
+    });
+
+    it("stops after the final payment", async () => {
+      const sub = await subscribe(1);
+      await execute(sub);
+
+      const data = await program.account.subscription.fetch(sub.subscription);
+      assert.equal(data.remainingPayments, 0);
+      assert.equal(await errorCode(execute(sub)), "SubscriptionExhausted");
+    });
+
+    it("rejects intervals shorter than the transfer cooldown", async () => {
+      const sub = await subscribe(1, 60);
+      assert.equal(sub.code, "InvalidConfigValue");
+    });
+
+    it("checks the payer against the approved authorities", async () => {
+      const sub = await subscribe(2);
+      const setApproved = (approved: anchor.web3.PublicKey, add: boolean) =>
+        (add
+          ? program.methods.addApprovedAuthority(approved)
+          : program.methods.removeApprovedAuthority(approved)
+        )
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+
+      // The subscription PDA signs, but it's the payer who must be approved
+      await setApproved(authority, true);
+      assert.equal(await errorCode(execute(sub)), "AuthorityNotApproved");
+
+      await setApproved(sub.payer.owner.publicKey, true);
+      await execute(sub);
+      const data = await program.account.subscription.fetch(sub.subscription);
+      assert.equal(data.remainingPayments, 1);
+
+      await setApproved(sub.payer.owner.publicKey, false);
+      await setApproved(authority, false);
+    });
+
+    it("checks the payee's owner when program recipients are blocked", async () => {
+      const sub = await subscribe(1);
+      await program.methods
+        .setBlockProgramRecipients(true)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+
+      assert.equal(await errorCode(execute(sub)), "MissingRecipientOwner");
+      await program.methods
+        .executeSubscription()
+        .accountsPartial({
+          subscription: sub.subscription,
+          from: sub.payer.tokenAccount,
+          to: payee.tokenAccount,
+          mint: ctx.mint,
+          treasury: ctx.tokenAccount,
+          holderData: sub.payer.holderData,
+          toHolderData: payee.holderData,
+          rewardsVault: ctx.rewardsVault,
+          priceFeed,
+        })
+        .remainingAccounts([
+          { pubkey: payee.owner.publicKey, isSigner: false, isWritable: false },
+        ])
+        .rpc();
+      const data = await program.account.subscription.fetch(sub.subscription);
+      assert.equal(data.remainingPayments, 0);
+
+      await program.methods
+        .setBlockProgramRecipients(false)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+    });
+  });
+
+  describe("delegated transfers", () => {
+    let ctx: Awaited<ReturnType<typeof setupToken>>;
+    let recipient: Awaited<ReturnType<typeof setupHolder>>;
+
+    const transfer = (
+      holder: Awaited<ReturnType<typeof setupHolder>>,
+      signer: anchor.web3.Keypair,
+      asDelegate: boolean
+    ) =>
+      program.methods
+        .secureTransfer(new anchor.BN(1_000_000), asDelegate, null)
+        .accountsPartial({
+          authority: signer.publicKey,
+          from: holder.tokenAccount,
+          to: recipient.tokenAccount,
+          mint: ctx.mint,
+          treasury: ctx.tokenAccount,
+          holderData: holder.holderData,
+          toHolderData: recipient.holderData,
+          rewardsVault: ctx.rewardsVault,
+          transferPair: null,
+          priceFeed,
+        })
+        .signers([signer])
+        .rpc();
+
+    const balance = async (account: anchor.web3.PublicKey) =>
+      Number(
+        (await provider.connection.getTokenAccountBalance(account)).value.amount
+      );
+
+    before(async () => {
+      ctx = await setupToken();
+      recipient = await setupHolder(ctx, 0);
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+    });
+
+    it("lets the owner transfer without the delegate flag", async () => {
+      const holder = await setupHolder(ctx, 10_000_000);
+      await transfer(holder, holder.owner, false);
+      assert.equal(await balance(holder.tokenAccount), 9_000_000);
+    });
+
+    it("lets an approved delegate spend within the allowance", async () => {
+      const holder = await setupHolder(ctx, 10_000_000);
+      const delegate = anchor.web3.Keypair.generate();
+      await approve(holder, delegate.publicKey, 2_000_000);
+
+      // The delegate has to opt into the delegate path
+      assert.equal(
+        await errorCode(transfer(holder, delegate, false)),
+        "InvalidTransferAuthority"
+      );
+
+      await transfer(holder, delegate, true);
+      assert.equal(await balance(holder.tokenAccount), 9_000_000);
+
+      // Limits are tracked on the owner's holder data
+      const data = await program.account.holderData.fetch(holder.holderData);
+      assert.equal(data.dailyTransactions.toNumber(), 1);
+    });
+
+    it("rejects a delegate exceeding the allowance", async () => {
+      const holder = await setupHolder(ctx, 10_000_000);
+      const delegate = anchor.web3.Keypair.generate();
+      await approve(holder, delegate.publicKey, 500_000);
+
+      assert.equal(
+        await errorCode(transfer(holder, delegate, true)),
+        "DelegateAllowanceExceeded"
+      );
+      assert.equal(await balance(holder.tokenAccount), 10_000_000);
+    });
+
+    it("rejects a signer that isn't the delegate", async () => {
+      const holder = await setupHolder(ctx, 10_000_000);
+      const stranger = anchor.web3.Keypair.generate();
+      assert.equal(
+        await errorCode(transfer(holder, stranger, true)),
+        "InvalidTransferAuthority"
+      );
+    });
+  });
+
+  describe("reward index", () => {
+    // Index growth per token for `seconds` at `rateBps` outside a campaign
+    const growth = (rateBps: number, seconds: number) =>
+      new anchor.BN(rateBps)
+        .muln(seconds)
+        .mul(new anchor.BN(1_000_000_000_000))
+        .div(new anchor.BN(10_000).muln(31_536_000));
+
+    it("accrues each sub-period at the rate in effect", async () => {
+      const ctx = await setupToken();
+      const setRate = (rateBps: number) =>
+        program.methods
+          .setRewardRate(rateBps)
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+
+      assert.equal(await errorCode(setRate(5_001)), "InvalidConfigValue");
+
+      // Anchor the index, then spend a while at the default 5%
+      await setRate(500);
+      const start = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(start.rewardRateBps, 500);
+      await new Promise((resolve) => setTimeout(resolve, 3000));
+      const early = await setupHolder(ctx, 0);
+
+      // Double the rate; the period before the change still grows at 5%
+      await setRate(1_000);
+      const changed = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      const firstPeriod = changed.indexUpdatedAt.sub(start.indexUpdatedAt).toNumber();
+      assert.isAbove(firstPeriod, 0);
+      assert.equal(
+        changed.rewardIndex.sub(start.rewardIndex).toString(),
+        growth(500, firstPeriod).toString()
+      );
+
+      await new Promise((resolve) => setTimeout(resolve, 3000));
+      const late = await setupHolder(ctx, 0);
+      const end = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      const secondPeriod = end.indexUpdatedAt.sub(changed.indexUpdatedAt).toNumber();
+      assert.isAbove(secondPeriod, 0);
+      assert.equal(
+        end.rewardIndex.sub(changed.rewardIndex).toString(),
+        growth(1_000, secondPeriod).toString()
+      );
+
+      // Holders start from the index at registration, so each is only
+      // owed the growth after they joined
+      const earlyData = await program.account.holderData.fetch(early.holderData);
+      const lateData = await program.account.holderData.fetch(late.holderData);
+      assert.isTrue(earlyData.lastIndex.gte(start.rewardIndex));
+      assert.isTrue(earlyData.lastIndex.lte(changed.rewardIndex));
+      assert.equal(lateData.lastIndex.toString(), end.rewardIndex.toString());
+    });
+  });
+
+  describe("max holders", () => {
+    it("caps registrations and frees a slot when a holder closes", async () => {
+      const ctx = await setupToken();
+      const register = (owner: anchor.web3.Keypair) =>
+        program.methods
+          .initializeRewards(null)
+          .accountsPartial({
+            authority: owner.publicKey,
+            mint: ctx.mint,
+            holderData: holderDataAddress(ctx.mint, owner.publicKey),
+          })
+          .signers([owner])
+          .rpc();
+
+      // The wallet registered in setupToken takes the first slot
+      let stats = await program.account.stats.fetch(ctx.stats);
+      assert.equal(stats.holderCount.toNumber(), 1);
+
+      await program.methods
+        .setMaxHolders(new anchor.BN(3))
+        .accountsPartial({ authority, mint: ctx.mint, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      const first = await setupHolder(ctx, 0);
+      await setupHolder(ctx, 0);
+      stats = await program.account.stats.fetch(ctx.stats);
+      assert.equal(stats.holderCount.toNumber(), 3);
+
+      const extra = anchor.web3.Keypair.generate();
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(
+          extra.publicKey,
+          anchor.web3.LAMPORTS_PER_SOL
+        )
+      );
+      assert.equal(await errorCode(register(extra)), "MaxHoldersReached");
+
+      await program.methods
+        .closeHolder()
+        .accountsPartial({
+          authority: first.owner.publicKey,
+          mint: ctx.mint,
+          holderData: first.holderData,
+        })
+        .signers([first.owner])
+        .rpc();
+      assert.isNull(await provider.connection.getAccountInfo(first.holderData));
+
+      await register(extra);
+      stats = await program.account.stats.fetch(ctx.stats);
+      assert.equal(stats.holderCount.toNumber(), 3);
+    });
+  });
+
+  describe("close_holder", () => {
+    it("refuses while the holder is locked or has transacted today", async () => {
+      const ctx = await setupToken();
+      const holder = await setupHolder(ctx, 10_000_000);
+      const recipient = await setupHolder(ctx, 0);
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      const lock = (until: number) =>
+        program.methods
+          .setLockedUntil(holder.owner.publicKey, new anchor.BN(until))
+          .accountsPartial({
+            authority,
+            mint: ctx.mint,
+            rewardsVault: ctx.rewardsVault,
+            holderData: holder.holderData,
+          })
+          .rpc();
+      const close = () =>
+        program.methods
+          .closeHolder()
+          .accountsPartial({
+            authority: holder.owner.publicKey,
+            mint: ctx.mint,
+            holderData: holder.holderData,
+          })
+          .signers([holder.owner])
+          .rpc();
+
+      // Closing and re-registering would clear the lock
+      await lock(Math.floor(Date.now() / 1000) + 3_600);
+      assert.equal(await errorCode(close()), "HolderCloseRestricted");
+      await lock(0);
+
+      // ...and the cooldown and daily count
+      await program.methods
+        .secureTransfer(new anchor.BN(1_000_000), false, null)
+        .accountsPartial({
+          authority: holder.owner.publicKey,
+          from: holder.tokenAccount,
+          to: recipient.tokenAccount,
+          mint: ctx.mint,
+          treasury: ctx.tokenAccount,
+          holderData: holder.holderData,
+          toHolderData: recipient.holderData,
+          rewardsVault: ctx.rewardsVault,
+          transferPair: null,
+          priceFeed,
+        })
+        .signers([holder.owner])
+        .rpc();
+      assert.equal(await errorCode(close()), "HolderCloseRestricted");
+      assert.isNotNull(await provider.connection.getAccountInfo(holder.holderData));
+    });
+
+    it("refuses once the holder has earned rewards", async () => {
+      const ctx = await setupToken();
+      const holder = await setupHolder(ctx, 1_000_000);
+      await program.methods
+        .distributePool(new anchor.BN(100))
+        .accountsPartial({
+          authority,
+          mint: ctx.mint,
+          rewardsVault: ctx.rewardsVault,
+        })
+        .remainingAccounts([
+          { pubkey: holder.holderData, isSigner: false, isWritable: true },
+          { pubkey: holder.tokenAccount, isSigner: false, isWritable: false },
+        ])
+        .rpc();
+
+      // Closing and re-registering would restart the lifetime total
+      const code = await errorCode(
+        program.methods
+          .closeHolder()
+          .accountsPartial({
+            authority: holder.owner.publicKey,
+            mint: ctx.mint,
+            holderData: holder.holderData,
+          })
+          .signers([holder.owner])
+          .rpc()
+      );
+      assert.equal(code, "HolderHasEarnedRewards");
+      const data = await program.account.holderData.fetch(holder.holderData);
+      assert.equal(data.rewardsEarned.toNumber(), 100);
+    });
+  });
+
+  describe("post-transfer balance assertion", () => {
+    it("fails the transfer when the recipient balance doesn't match", async () => {
+      const ctx = await setupToken();
+      const sender = await setupHolder(ctx, 10_000_000);
+      const recipient = await setupHolder(ctx, 0);
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      const transfer = (expected: number) =>
+        program.methods
+          .secureTransfer(new anchor.BN(1_000_000), false, new anchor.BN(expected))
+          .accountsPartial({
+            authority: sender.owner.publicKey,
+            from: sender.tokenAccount,
+            to: recipient.tokenAccount,
+            mint: ctx.mint,
+            treasury: ctx.tokenAccount,
+            holderData: sender.holderData,
+            toHolderData: recipient.holderData,
+            rewardsVault: ctx.rewardsVault,
+            transferPair: null,
+            priceFeed,
+          })
+          .signers([sender.owner])
+          .rpc();
+      const balance = async (account: anchor.web3.PublicKey) =>
+        Number(
+          (await provider.connection.getTokenAccountBalance(account)).value.amount
+        );
+
+      // A wrong expectation rolls back the transfer and the counters
+      assert.equal(await errorCode(transfer(999_999)), "PostBalanceMismatch");
+      assert.equal(await balance(sender.tokenAccount), 10_000_000);
+      assert.equal(await balance(recipient.tokenAccount), 0);
+      const data = await program.account.holderData.fetch(sender.holderData);
+      assert.equal(data.dailyTransactions.toNumber(), 0);
+
+      await transfer(1_000_000);
+      assert.equal(await balance(sender.tokenAccount), 9_000_000);
+      assert.equal(await balance(recipient.tokenAccount), 1_000_000);
+    });
+
+    it("checks a recorded rejection against the unchanged balance", async () => {
+      const ctx = await setupToken();
+      const sender = await setupHolder(ctx, 10_000_000);
+      const recipient = await setupHolder(ctx, 0);
+      for (const ix of [
+        program.methods.setPriceUnavailablePolicy({ allowWithMaxLimits: {} }),
+        program.methods.setRecordRejections(true),
+      ]) {
+        await ix
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+      }
+      const transfer = (expected: number) =>
+        program.methods
+          .trySecureTransfer(new anchor.BN(1_000_000), false, new anchor.BN(expected))
+          .accountsPartial({
+            authority: sender.owner.publicKey,
+            from: sender.tokenAccount,
+            to: recipient.tokenAccount,
+            mint: ctx.mint,
+            treasury: ctx.tokenAccount,
+            holderData: sender.holderData,
+            toHolderData: recipient.holderData,
+            rewardsVault: ctx.rewardsVault,
+            transferPair: null,
+            priceFeed,
+          })
+          .signers([sender.owner])
+          .rpc();
+      const rejections = async () =>
+        (await program.account.holderData.fetch(sender.holderData))
+          .cooldownRejections;
+
+      await transfer(1_000_000);
+
+      // The second send is in cooldown, so expecting it to land fails
+      // and rolls back the recorded rejection
+      assert.equal(await errorCode(transfer(2_000_000)), "PostBalanceMismatch");
+      assert.equal(await rejections(), 0);
+
+      await transfer(1_000_000);
+      assert.equal(await rejections(), 1);
+    });
+  });
+
+  describe("internal accounts", () => {
+    it("keeps a bounded list of accounts exempt from the USD minimum", async () => {
+      const ctx = await setupToken();
+      const add = (account: anchor.web3.PublicKey) =>
+        program.methods
+          .addInternalAccount(account)
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+
+      await add(ctx.tokenAccount);
+      await add(ctx.tokenAccount);
+      let vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.internalAccounts.length, 1);
+      assert.isTrue(vault.internalAccounts[0].equals(ctx.tokenAccount));
+
+      for (let i = 1; i < 8; i++) {
+        await add(anchor.web3.Keypair.generate().publicKey);
+      }
+      assert.equal(
+        await errorCode(add(anchor.web3.Keypair.generate().publicKey)),
+        "InternalAccountsFull"
+      );
+
+      await program.methods
+        .removeInternalAccount(ctx.tokenAccount)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.internalAccounts.length, 7);
+      assert.isFalse(vault.internalAccounts.some((key) => key.equals(ctx.tokenAccount)));
+    });
+
+    it("still applies the other limits between internal accounts", async () => {
+      const ctx = await setupToken();
+      const sender = await setupHolder(ctx, 10_000_000);
+      const recipient = await setupHolder(ctx, 0);
+      for (const account of [sender.tokenAccount, recipient.tokenAccount]) {
+        await program.methods
+          .addInternalAccount(account)
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+      }
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      const transfer = (amount: number) =>
+        program.methods
+          .secureTransfer(new anchor.BN(amount), false, null)
+          .accountsPartial({
+            authority: sender.owner.publicKey,
+            from: sender.tokenAccount,
+            to: recipient.tokenAccount,
+            mint: ctx.mint,
+            treasury: ctx.tokenAccount,
+            holderData: sender.holderData,
+            toHolderData: recipient.holderData,
+            rewardsVault: ctx.rewardsVault,
+            transferPair: null,
+            priceFeed,
+          })
+          .signers([sender.owner])
+          .rpc();
+
+      // More than the allowed share of the balance is still rejected
+      assert.equal(await errorCode(transfer(11_000_000)), "TransferAmountTooLarge");
+      await transfer(1_000);
+      const balance = await provider.connection.getTokenAccountBalance(
+        recipient.tokenAccount
+      );
+      assert.equal(balance.value.amount, "1000");
+    });
+  });
+
+  describe("rewards_for_period", () => {
+    // Manual accrual: balance * rate * weighted period / year, through the
+    // same fixed-point index the program uses
+    const expected = (
+      balance: number,
+      rateBps: number,
+      weightedSeconds: anchor.BN
+    ) => {
+      const growth = new anchor.BN(rateBps)
+        .mul(weightedSeconds)
+        .mul(new anchor.BN(1_000_000_000_000))
+        .div(new anchor.BN(10_000).mul(new anchor.BN(10_000)).muln(31_536_000));
+      return new anchor.BN(balance)
+        .mul(growth)
+        .div(new anchor.BN(1_000_000_000_000))
+        .toString();
+    };
+
+    it("matches a manual computation across windows and a campaign", async () => {
+      const ctx = await setupToken();
+      const rewards = async (balance: number, start: number, end: number) =>
+        (
+          await program.methods
+            .rewardsForPeriod(new anchor.BN(balance), new anchor.BN(start), new anchor.BN(end))
+            .accountsPartial({ rewardsVault: ctx.rewardsVault })
+            .view()
+        ).toString();
+      const year = 31_536_000;
+
+      // A full year at 5% and a 30-day window, both at 1x
+      assert.equal(await rewards(1_000_000_000, 0, year), "50000000");
+      assert.equal(
+        await rewards(1_000_000_000, 0, 2_592_000),
+        expected(1_000_000_000, 500, new anchor.BN(2_592_000 * 10_000))
+      );
+      assert.equal(await rewards(1_000_000_000, 100, 100), "0");
+
+      // A 2x campaign over the second half of a window doubles that half
+      await program.methods
+        .setCampaign(20_000, new anchor.BN(1_000_000), new anchor.BN(2_000_000))
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      assert.equal(
+        await rewards(1_000_000_000, 0, 2_000_000),
+        expected(
+          1_000_000_000,
+          500,
+          new anchor.BN(1_000_000 * 10_000).add(new anchor.BN(1_000_000 * 20_000))
+        )
+      );
+
+      // The current rate applies to the whole window
+      await program.methods
+        .setRewardRate(1_000)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      assert.equal(await rewards(1_000_000_000, 3_000_000, 3_000_000 + year), "100000000");
+
+      assert.equal(
+        await errorCode(
+          program.methods
+            .rewardsForPeriod(new anchor.BN(1), new anchor.BN(2), new anchor.BN(1))
+            .accountsPartial({ rewardsVault: ctx.rewardsVault })
+            .rpc()
+        ),
+        "InvalidPeriod"
+      );
+    });
+  });
+
+  describe("trading start", () => {
+    it("only lets the authority transfer before trading opens", async () => {
+      const ctx = await setupToken();
+      const holder = await setupHolder(ctx, 10_000_000);
+      const recipient = await setupHolder(ctx, 0);
+      const setTradingStart = (ts: number) =>
+        program.methods
+          .setTradingStart(new anchor.BN(ts))
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      const transfer = (
+        signer: anchor.web3.Keypair | null,
+        from: anchor.web3.PublicKey,
+        holderData: anchor.web3.PublicKey,
+        asDelegate = false
+      ) =>
+        program.methods
+          .secureTransfer(new anchor.BN(1_000_000), asDelegate, null)
+          .accountsPartial({
+            authority: signer ? signer.publicKey : authority,
+            from,
+            to: recipient.tokenAccount,
+            mint: ctx.mint,
+            treasury: ctx.tokenAccount,
+            holderData,
+            toHolderData: recipient.holderData,
+            rewardsVault: ctx.rewardsVault,
+            transferPair: null,
+            priceFeed,
+          })
+          .signers(signer ? [signer] : [])
+          .rpc();
+
+      await setTradingStart(Math.floor(Date.now() / 1000) + 3_600);
+      assert.equal(
+        await errorCode(transfer(holder.owner, holder.tokenAccount, holder.holderData)),
+        "TradingNotStarted"
+      );
+      await transfer(null, ctx.tokenAccount, ctx.holderData);
+
+      // The authority can't move a holder's tokens as their delegate early
+      await approve(holder, authority, 1_000_000);
+      assert.equal(
+        await errorCode(transfer(null, holder.tokenAccount, holder.holderData, true)),
+        "TradingNotStarted"
+      );
+
+      await setTradingStart(Math.floor(Date.now() / 1000) - 3_600);
+      await transfer(holder.owner, holder.tokenAccount, holder.holderData);
+      const balance = await provider.connection.getTokenAccountBalance(
+        recipient.tokenAccount
+      );
+      assert.equal(balance.value.amount, "2000000");
+    });
+  });
+
+  describe("emergency claim", () => {
+    it("bounds the haircut and still requires the holding period", async () => {
+      const ctx = await setupToken();
+      const setHaircut = (bps: number) =>
+        program.methods
+          .setEmergencyHaircut(bps)
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+
+      let vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.emergencyHaircutBps, 1_000);
+
+      assert.equal(await errorCode(setHaircut(5_001)), "InvalidConfigValue");
+      await setHaircut(2_500);
+      vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.emergencyHaircutBps, 2_500);
+
+      // The cooldown is skipped, but a fresh holder is still inside the
+      // minimum holding period
+      const code = await errorCode(
+        program.methods
+          .emergencyClaim()
+          .accountsPartial({
+            authority,
+            holderData: ctx.holderData,
+            mint: ctx.mint,
+            rewardsVault: ctx.rewardsVault,
+            tokenAccount: ctx.tokenAccount,
+            treasury: ctx.tokenAccount,
+            mintAuthority: authority,
+          })
+          .rpc()
+      );
+      assert.equal(code, "MinHoldingPeriodNotMet");
+    });
+  });
+
+  describe("max holder balance", () => {
+    it("rejects transfers that take a recipient over the cap", async () => {
+      const ctx = await setupToken();
+      const recipient = await setupHolder(ctx, 0);
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      await program.methods
+        .setMaxHolderBalance(new anchor.BN(2_000_000))
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      const transfer = async () => {
+        const sender = await setupHolder(ctx, 10_000_000);
+        return errorCode(
+          program.methods
+            .secureTransfer(new anchor.BN(1_000_000), false, null)
+            .accountsPartial({
+              authority: sender.owner.publicKey,
+              from: sender.tokenAccount,
+              to: recipient.tokenAccount,
+              mint: ctx.mint,
+              treasury: ctx.tokenAccount,
+              holderData: sender.holderData,
+              toHolderData: recipient.holderData,
+              rewardsVault: ctx.rewardsVault,
+              transferPair: null,
+              priceFeed,
+            })
+            .signers([sender.owner])
+            .rpc()
+        );
+      };
+
+      // Fill up to the cap exactly, then one more transfer overflows it
+      assert.isNull(await transfer());
+      assert.isNull(await transfer());
+      assert.equal(await transfer(), "MaxHolderBalanceExceeded");
+
+      const balance = await provider.connection.getTokenAccountBalance(
+        recipient.tokenAccount
+      );
+      assert.equal(balance.value.amount, "2000000");
+    });
+  });
+
+  describe("failed token transfers", () => {
+    it("leave the sender's counters untouched", async () => {
+      const ctx = await setupToken();
+      const other = await setupToken();
+      const sender = await setupHolder(ctx, 10_000_000);
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+
+      // The token program rejects a recipient account of another mint
+      // after all of secure_transfer's own checks have passed
+      const code = await errorCode(
+        program.methods
+          .secureTransfer(new anchor.BN(1_000_000), false, null)
+          .accountsPartial({
+            authority: sender.owner.publicKey,
+            from: sender.tokenAccount,
+            to: other.tokenAccount,
+            mint: ctx.mint,
+            treasury: ctx.tokenAccount,
+            holderData: sender.holderData,
+            toHolderData: ctx.holderData,
+            rewardsVault: ctx.rewardsVault,
+            transferPair: null,
+            priceFeed,
+          })
+          .signers([sender.owner])
+          .rpc()
+      );
+      // The token program's MintMismatch
+      assert.include(code, "custom program error: 0x3");
+
+      const data = await program.account.holderData.fetch(sender.holderData);
+      assert.equal(data.dailyTransactions.toNumber(), 0);
+      assert.equal(data.lastTransfer.toNumber(), 0);
+      const balance = await provider.connection.getTokenAccountBalance(
+        sender.tokenAccount
+      );
+      assert.equal(balance.value.amount, "10000000");
+    });
+  });
+
+  describe("in_cooldown", () => {
+    it("reports the remaining cooldown and honors the authority exemption", async () => {
+      const ctx = await setupToken();
+      const holder = await setupHolder(ctx, 10_000_000);
+      const recipient = await setupHolder(ctx, 0);
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      const status = (holderData: anchor.web3.PublicKey) =>
+        program.methods
+          .inCooldown()
+          .accountsPartial({ mint: ctx.mint, holderData })
+          .view();
+      const transfer = (
+        signer: anchor.web3.Keypair | null,
+        from: anchor.web3.PublicKey,
+        holderData: anchor.web3.PublicKey
+      ) =>
+        program.methods
+          .secureTransfer(new anchor.BN(1_000_000), false, null)
+          .accountsPartial({
+            authority: signer ? signer.publicKey : authority,
+            from,
+            to: recipient.tokenAccount,
+            mint: ctx.mint,
+            treasury: ctx.tokenAccount,
+            holderData,
+            toHolderData: recipient.holderData,
+            rewardsVault: ctx.rewardsVault,
+            transferPair: null,
+            priceFeed,
+          })
+          .signers(signer ? [signer] : [])
+          .rpc();
+
+      // A holder who never transferred is clear
+      let result = await status(holder.holderData);
+      assert.isFalse(result.inCooldown);
+      assert.equal(result.secondsRemaining.toNumber(), 0);
+
+      await transfer(holder.owner, holder.tokenAccount, holder.holderData);
+      result = await status(holder.holderData);
+      assert.isTrue(result.inCooldown);
+      assert.isAbove(result.secondsRemaining.toNumber(), 0);
+      assert.isAtMost(result.secondsRemaining.toNumber(), 300);
+
+      // The exempt authority is never in cooldown
+      await program.methods
+        .setAuthorityExempt(true)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      await transfer(null, ctx.tokenAccount, ctx.holderData);
+      result = await status(ctx.holderData);
+      assert.isFalse(result.inCooldown);
+      assert.equal(result.secondsRemaining.toNumber(), 0);
+    });
+  });
+
+
+  describe("recorded rejections", () => {
+    let ctx: Awaited<ReturnType<typeof setupToken>>;
+
+    const pairAddress = (from: anchor.web3.PublicKey, to: anchor.web3.PublicKey) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("pair"), from.toBuffer(), to.toBuffer()],
+        program.programId
+      )[0];
+
+    const transfer = (
+      method: "secureTransfer" | "trySecureTransfer",
+      sender: Awaited<ReturnType<typeof setupHolder>>,
+      recipient: Awaited<ReturnType<typeof setupHolder>>,
+      transferPair: anchor.web3.PublicKey | null = null
+    ) =>
+      program.methods[method](new anchor.BN(1_000_000), false, null)
+        .accountsPartial({
+          authority: sender.owner.publicKey,
+          from: sender.tokenAccount,
+          to: recipient.tokenAccount,
+          mint: ctx.mint,
+          treasury: ctx.tokenAccount,
+          holderData: sender.holderData,
+          toHolderData: recipient.holderData,
+          rewardsVault: ctx.rewardsVault,
+          transferPair,
+          priceFeed,
+        })
+        .signers([sender.owner])
+        .rpc({ commitment: "confirmed" });
+
+    // The TransferRejected events a confirmed transaction emitted
+    const rejections = async (sig: string) => {
+      const tx = await provider.connection.getTransaction(sig, {
+        commitment: "confirmed",
+      });
+      const parser = new anchor.EventParser(program.programId, program.coder);
+      return Array.from(parser.parseLogs(tx.meta.logMessages)).filter(
+        (e) => e.name === "transferRejected"
+      );
+    };
+
+    const balance = async (tokenAccount: anchor.web3.PublicKey) =>
+      (await provider.connection.getTokenAccountBalance(tokenAccount)).value
+        .amount;
+
+    beforeEach(async () => {
+      ctx = await setupToken();
+      for (const ix of [
+        program.methods.setPriceUnavailablePolicy({ allowWithMaxLimits: {} }),
+        program.methods.setRecordRejections(true),
+      ]) {
+        await ix
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+      }
+    });
+
+    it("counts a cooldown rejection only through try_secure_transfer", async () => {
+      const sender = await setupHolder(ctx, 10_000_000);
+      const recipient = await setupHolder(ctx, 0);
+
+      await transfer("secureTransfer", sender, recipient);
+      assert.equal(
+        await errorCode(transfer("secureTransfer", sender, recipient)),
+        "TransferCooldownActive"
+      );
+      const before = await balance(recipient.tokenAccount);
+      const events = await rejections(
+        await transfer("trySecureTransfer", sender, recipient)
+      );
+
+      assert.equal(await balance(recipient.tokenAccount), before);
+      const data = await program.account.holderData.fetch(sender.holderData);
+      assert.equal(data.cooldownRejections, 1);
+      assert.equal(data.dailyLimitRejections, 0);
+      assert.equal(events.length, 1);
+      assert.deepEqual(events[0].data.reason, { cooldown: {} });
+    });
+
+    it("counts a daily-limit rejection only through try_secure_transfer", async () => {
+      // Per-pair mode with fresh recipients leaves only the daily limit
+      await program.methods
+        .setPerPairCooldown(true)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      await program.methods
+        .setDailyTransactionLimits(new anchor.BN(1), new anchor.BN(1))
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      const sender = await setupHolder(ctx, 10_000_000);
+      const recipients = [
+        await setupHolder(ctx, 0),
+        await setupHolder(ctx, 0),
+      ];
+      const pairs = [];
+      for (const recipient of recipients) {
+        const transferPair = pairAddress(sender.tokenAccount, recipient.tokenAccount);
+        await program.methods
+          .initializeTransferPair()
+          .accountsPartial({
+            authority,
+            from: sender.tokenAccount,
+            to: recipient.tokenAccount,
+            transferPair,
+          })
+          .rpc();
+        pairs.push(transferPair);
+      }
+
+      await transfer("secureTransfer", sender, recipients[0], pairs[0]);
+      assert.equal(
+        await errorCode(
+          transfer("secureTransfer", sender, recipients[1], pairs[1])
+        ),
+        "DailyLimitExceeded"
+      );
+      const events = await rejections(
+        await transfer("trySecureTransfer", sender, recipients[1], pairs[1])
+      );
+
+      assert.equal(await balance(recipients[1].tokenAccount), "0");
+      const data = await program.account.holderData.fetch(sender.holderData);
+      assert.equal(data.dailyLimitRejections, 1);
+      assert.equal(data.cooldownRejections, 0);
+      assert.equal(events.length, 1);
+      assert.deepEqual(events[0].data.reason, { dailyLimit: {} });
+    });
+
+    it("fails a rejected try_secure_transfer when recording is off", async () => {
+      await program.methods
+        .setRecordRejections(false)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      const sender = await setupHolder(ctx, 10_000_000);
+      const recipient = await setupHolder(ctx, 0);
+
+      await transfer("trySecureTransfer", sender, recipient);
+      assert.equal(
+        await errorCode(transfer("trySecureTransfer", sender, recipient)),
+        "TransferCooldownActive"
+      );
+      const data = await program.account.holderData.fetch(sender.holderData);
+      assert.equal(data.cooldownRejections, 0);
+    });
+  });
+
+  describe("daily transaction limits by verification", () => {
+    let ctx: Awaited<ReturnType<typeof setupToken>>;
+
+    const pairAddress = (from: anchor.web3.PublicKey, to: anchor.web3.PublicKey) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("pair"), from.toBuffer(), to.toBuffer()],
+        program.programId
+      )[0];
+
+    // Sends to fresh recipients so only the daily limit, not the per-pair cooldown, applies
+    const sendUntilRejected = async (
+      sender: Awaited<ReturnType<typeof setupHolder>>,
+      attempts: number
+    ) => {
+      let sent = 0;
+      for (let i = 0; i < attempts; i++) {
+        const recipient = await setupHolder(ctx, 0);
+        const transferPair = pairAddress(sender.tokenAccount, recipient.tokenAccount);
+        await program.methods
+          .initializeTransferPair()
+          .accountsPartial({
+            authority,
+            from: sender.tokenAccount,
+            to: recipient.tokenAccount,
+            transferPair,
+          })
+          .rpc();
+        const code = await errorCode(
+          program.methods
+            .secureTransfer(new anchor.BN(1_000_000), false, null)
+            .accountsPartial({
+              authority: sender.owner.publicKey,
+              from: sender.tokenAccount,
+              to: recipient.tokenAccount,
+              mint: ctx.mint,
+              treasury: ctx.tokenAccount,
+              holderData: sender.holderData,
+              toHolderData: recipient.holderData,
+              rewardsVault: ctx.rewardsVault,
+              transferPair,
+              priceFeed,
+            })
+            .signers([sender.owner])
+            .rpc()
+        );
+        if (code) {
+          return { sent, code };
+        }
+        sent++;
+      }
+      return { sent, code: null };
+    };
+
+    before(async () => {
+      ctx = await setupToken();
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      await program.methods
+        .setPerPairCooldown(true)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      await program.methods
+        .setDailyTransactionLimits(new anchor.BN(2), new anchor.BN(3))
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+    });
+
+    it("rejects limits where unverified exceeds verified", async () => {
+      assert.equal(
+        await errorCode(
+          program.methods
+            .setDailyTransactionLimits(new anchor.BN(4), new anchor.BN(3))
+            .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+            .rpc()
+        ),
+        "InvalidConfigValue"
+      );
+    });
+
+    it("stops an unverified holder at the unverified limit", async () => {
+      const sender = await setupHolder(ctx, 10_000_000);
+      const result = await sendUntilRejected(sender, 3);
+      assert.equal(result.sent, 2);
+      assert.equal(result.code, "DailyLimitExceeded");
+    });
+
+    it("stops a verified holder at the verified limit", async () => {
+      const sender = await setupHolder(ctx, 10_000_000);
+      await program.methods
+        .setVerified(sender.owner.publicKey, true)
+        .accountsPartial({
+          authority,
+          mint: ctx.mint,
+          rewardsVault: ctx.rewardsVault,
+          holderData: sender.holderData,
+        })
+        .rpc();
+      const data = await program.account.holderData.fetch(sender.holderData);
+      assert.isTrue(data.isVerified);
+
+      const result = await sendUntilRejected(sender, 4);
+      assert.equal(result.sent, 3);
+      assert.equal(result.code, "DailyLimitExceeded");
+    });
+  });
+
+
+  describe("set_treasury", () => {
+    it("routes later fees to the new treasury and rejects other mints", async () => {
+      const ctx = await setupToken();
+      const other = await setupToken();
+      const newTreasury = await setupHolder(ctx, 0);
+      const sender = await setupHolder(ctx, 10_000_000);
+      const recipient = await setupHolder(ctx, 0);
+      const setTreasury = (treasury: anchor.web3.PublicKey) =>
+        program.methods
+          .setTreasury()
+          .accountsPartial({
+            authority,
+            mint: ctx.mint,
+            rewardsVault: ctx.rewardsVault,
+            treasury,
+          })
+          .rpc();
+
+      assert.equal(await errorCode(setTreasury(other.tokenAccount)), "InvalidTreasuryMint");
+      await setTreasury(newTreasury.tokenAccount);
+      const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.isTrue(vault.treasury.equals(newTreasury.tokenAccount));
+
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      await program.methods
+        .setTransferFee(100, 0)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      await program.methods
+        .secureTransfer(new anchor.BN(1_000_000), false, null)
+        .accountsPartial({
+          authority: sender.owner.publicKey,
+          from: sender.tokenAccount,
+          to: recipient.tokenAccount,
+          mint: ctx.mint,
+          treasury: newTreasury.tokenAccount,
+          holderData: sender.holderData,
+          toHolderData: recipient.holderData,
+          rewardsVault: ctx.rewardsVault,
+          transferPair: null,
+          priceFeed,
+        })
+        .signers([sender.owner])
+        .rpc();
+
+      const balance = await provider.connection.getTokenAccountBalance(
+        newTreasury.tokenAccount
+      );
+      assert.equal(balance.value.amount, "10000");
+    });
+  });
+
+
+  describe("accrual pause", () => {
+    it("earns nothing on the index growth while paused", async () => {
+      const ctx = await setupToken();
+      await program.methods
+        .setRewardRate(5_000)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      const setPaused = (paused: boolean) =>
+        program.methods
+          .setAccrualPaused(authority, paused)
+          .accountsPartial({
+            authority,
+            mint: ctx.mint,
+            rewardsVault: ctx.rewardsVault,
+            holderData: ctx.holderData,
+          })
+          .rpc();
+
+      const before = await program.account.holderData.fetch(ctx.holderData);
+      assert.isFalse(before.accrualPaused);
+
+      await setPaused(true);
+      let data = await program.account.holderData.fetch(ctx.holderData);
+      let vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.isTrue(data.accrualPaused);
+      assert.isAbove(data.accrualPausedAt.toNumber(), 0);
+      assert.equal(data.accrualPausedIndex.toString(), vault.rewardIndex.toString());
+      const pausedIndex = data.accrualPausedIndex;
+      // Growth before the pause stays owed to the holder
+      assert.isTrue(pausedIndex.gte(before.lastIndex));
+
+      await new Promise((resolve) => setTimeout(resolve, 2000));
+      await setPaused(false);
+      data = await program.account.holderData.fetch(ctx.holderData);
+      vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.isFalse(data.accrualPaused);
+      assert.isTrue(vault.rewardIndex.gt(pausedIndex));
+
+      // last_index moved forward by exactly the growth while paused
+      assert.equal(
+        data.lastIndex.toString(),
+        before.lastIndex.add(vault.rewardIndex.sub(pausedIndex)).toString()
+      );
+    });
+  });
+
+
+  describe("zero balance claim", () => {
+    it("rejects the claim without touching last_claim", async () => {
+      const ctx = await setupToken();
+      const holder = await setupHolder(ctx, 0);
+      const before = await program.account.holderData.fetch(holder.holderData);
+
+      const code = await errorCode(
+        program.methods
+          .claimRewards()
+          .accountsPartial({
+            authority: holder.owner.publicKey,
+            holderData: holder.holderData,
+            mint: ctx.mint,
+            rewardsVault: ctx.rewardsVault,
+            tokenAccount: holder.tokenAccount,
+            referrerData: null,
+            mintAuthority: authority,
+          })
+          .signers([holder.owner])
+          .rpc()
+      );
+      assert.equal(code, "NoBalanceToClaim");
+
+      const after = await program.account.holderData.fetch(holder.holderData);
+      assert.equal(after.lastClaim.toString(), before.lastClaim.toString());
+      assert.equal(after.lastIndex.toString(), before.lastIndex.toString());
+    });
+  });
+
+
+  describe("full handoff", () => {
+    it("moves the vault, mint and treasury authority on acceptance", async () => {
+      const ctx = await setupToken();
+      const newOwner = anchor.web3.Keypair.generate();
+      const stranger = anchor.web3.Keypair.generate();
+      const mintAuthority = async () => {
+        const info = await provider.connection.getParsedAccountInfo(ctx.mint);
+        return (info.value.data as anchor.web3.ParsedAccountData).parsed.info.mintAuthority;
+      };
+      const treasuryOwner = async () => {
+        const info = await provider.connection.getParsedAccountInfo(ctx.tokenAccount);
+        return (info.value.data as anchor.web3.ParsedAccountData).parsed.info.owner;
+      };
+      const propose = (owner: anchor.web3.PublicKey) =>
+        program.methods
+          .proposeFullHandoff(owner)
+          .accountsPartial({
+            authority,
+            mint: ctx.mint,
+            rewardsVault: ctx.rewardsVault,
+          })
+          .rpc();
+      const accept = (signer: anchor.web3.Keypair) =>
+        program.methods
+          .acceptFullHandoff()
+          .accountsPartial({
+            authority,
+            newOwner: signer.publicKey,
+            mint: ctx.mint,
+            rewardsVault: ctx.rewardsVault,
+            treasury: ctx.tokenAccount,
+          })
+          .signers([signer])
+          .rpc();
+      const setPaused = (signer: anchor.web3.PublicKey, signers: anchor.web3.Keypair[]) =>
+        program.methods
+          .setPaused(false)
+          .accountsPartial({ authority: signer, rewardsVault: ctx.rewardsVault })
+          .signers(signers)
+          .rpc();
+
+      // A proposal moves nothing, so the wallet can still mint claims
+      await propose(newOwner.publicKey);
+      let vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.isTrue(vault.pendingOwner.equals(newOwner.publicKey));
+      assert.isTrue(vault.authority.equals(authority));
+      assert.equal(await mintAuthority(), authority.toBase58());
+      assert.equal(await treasuryOwner(), authority.toBase58());
+
+      // Proposing the current authority cancels
+      await propose(authority);
+      vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.isTrue(vault.pendingOwner.equals(anchor.web3.PublicKey.default));
+      assert.equal(await errorCode(accept(newOwner)), "NotPendingOwner");
+      assert.equal(await mintAuthority(), authority.toBase58());
+
+      await propose(newOwner.publicKey);
+      assert.equal(await errorCode(accept(stranger)), "NotPendingOwner");
+      await accept(newOwner);
+
+      vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.isTrue(vault.authority.equals(newOwner.publicKey));
+      assert.isTrue(vault.pendingOwner.equals(anchor.web3.PublicKey.default));
+      assert.equal(await mintAuthority(), newOwner.publicKey.toBase58());
+      assert.equal(await treasuryOwner(), newOwner.publicKey.toBase58());
+
+      assert.equal(await errorCode(setPaused(authority, [])), "ConstraintHasOne");
+      await setPaused(newOwner.publicKey, [newOwner]);
+    });
+
+    it("leaves claims minting while a handoff is pending", async () => {
+      const ctx = await setupToken();
+      const holder = await setupHolder(ctx, 100_000_000_000_000);
+      await allowImmediateClaims(ctx);
+      await program.methods
+        .proposeFullHandoff(anchor.web3.Keypair.generate().publicKey)
+        .accountsPartial({
+          authority,
+          mint: ctx.mint,
+          rewardsVault: ctx.rewardsVault,
+        })
+        .rpc();
+      await new Promise((resolve) => setTimeout(resolve, 2000));
+
+      const paid = await sendClaims([await claimIx(ctx, holder)], [holder.owner]);
+      assert.isTrue(paid[0].gtn(0));
+    });
+  });
+
+
+  describe("large transfer confidence", () => {
+    it("bounds the band by the normal one and leaves unpriced transfers alone", async () => {
+      const ctx = await setupToken();
+      const sender = await setupHolder(ctx, 10_000_000);
+      const recipient = await setupHolder(ctx, 0);
+      const setBand = (thresholdCents: number, bps: number) =>
+        program.methods
+          .setLargeTransferConfidence(new anchor.BN(thresholdCents), bps)
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+
+      let vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.largeTransferMaxConfidenceBps, 0);
+
+      // The large-transfer band can only be tighter than the 1% default
+      assert.equal(await errorCode(setBand(100_000, 101)), "InvalidConfigValue");
+      await setBand(100_000, 10);
+      vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.largeTransferThresholdCents.toNumber(), 100_000);
+      assert.equal(vault.largeTransferMaxConfidenceBps, 10);
+
+      // Without a live reading there is no confidence to check
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      await program.methods
+        .secureTransfer(new anchor.BN(1_000_000), false, null)
+        .accountsPartial({
+          authority: sender.owner.publicKey,
+          from: sender.tokenAccount,
+          to: recipient.tokenAccount,
+          mint: ctx.mint,
+          treasury: ctx.tokenAccount,
+          holderData: sender.holderData,
+          toHolderData: recipient.holderData,
+          rewardsVault: ctx.rewardsVault,
+          transferPair: null,
+          priceFeed,
+        })
+        .signers([sender.owner])
+        .rpc();
+    });
+  });
+
+
+  describe("get_holders_batch", () => {
+    it("returns the key fields of each holder in order", async () => {
+      const ctx = await setupToken();
+      const other = await setupToken();
+      const holders = [
+        await setupHolder(ctx, 1_000_000),
+        await setupHolder(ctx, 2_000_000),
+        await setupHolder(ctx, 3_000_000),
+      ];
+      const batch = (holderData: anchor.web3.PublicKey[]) =>
+        program.methods
+          .getHoldersBatch()
+          .accountsPartial({ mint: ctx.mint })
+          .remainingAccounts(
+            holderData.map((pubkey) => ({ pubkey, isSigner: false, isWritable: false }))
+          );
+
+      const summaries = await batch(holders.map((h) => h.holderData)).view();
+      assert.equal(summaries.length, 3);
+      for (const [i, holder] of holders.entries()) {
+        const data = await program.account.holderData.fetch(holder.holderData);
+        assert.isTrue(summaries[i].authority.equals(holder.owner.publicKey));
+        assert.equal(summaries[i].lastClaim.toString(), data.lastClaim.toString());
+        assert.equal(summaries[i].rewardsEarned.toString(), data.rewardsEarned.toString());
+        assert.isFalse(summaries[i].flagged);
+      }
+
+      // Holders of another mint and empty batches are rejected
+      assert.equal(
+        await errorCode(batch([holders[0].holderData, other.holderData]).rpc()),
+        "InvalidHoldersBatch"
+      );
+      assert.equal(await errorCode(batch([]).rpc()), "InvalidHoldersBatch");
+    });
+  });
+
+
+  describe("verify_allowlist", () => {
+    const sha256 = (...parts: Buffer[]) =>
+      createHash("sha256").update(Buffer.concat(parts)).digest();
+    // Parents hash their children in sorted order, matching the program
+    const parent = (a: Buffer, b: Buffer) =>
+      Buffer.compare(a, b) <= 0 ? sha256(a, b) : sha256(b, a);
+
+    it("verifies a holder with a valid proof and rejects a bad one", async () => {
+      const ctx = await setupToken();
+      const holder = await setupHolder(ctx, 0);
+      const outsider = await setupHolder(ctx, 0);
+      const leaves = [
+        holder.owner.publicKey,
+        anchor.web3.Keypair.generate().publicKey,
+        anchor.web3.Keypair.generate().publicKey,
+        anchor.web3.Keypair.generate().publicKey,
+      ].map((key) => sha256(key.toBuffer()));
+      const right = parent(leaves[2], leaves[3]);
+      const root = parent(parent(leaves[0], leaves[1]), right);
+      const proof = [leaves[1], right].map((node) => Array.from(node));
+      const verify = (
+        signer: Awaited<ReturnType<typeof setupHolder>>,
+        nodes: number[][]
+      ) =>
+        program.methods
+          .verifyAllowlist(nodes)
+          .accountsPartial({
+            authority: signer.owner.publicKey,
+            mint: ctx.mint,
+            holderData: signer.holderData,
+          })
+          .signers([signer.owner])
+          .rpc();
+
+      // Nothing verifies before a root is set
+      assert.equal(await errorCode(verify(holder, proof)), "InvalidAllowlistProof");
+
+      await program.methods
+        .setAllowlistRoot(Array.from(root))
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+
+      assert.equal(await errorCode(verify(outsider, proof)), "InvalidAllowlistProof");
+      assert.equal(
+        await errorCode(verify(holder, [proof[1], proof[0]])),
+        "InvalidAllowlistProof"
+      );
+      let data = await program.account.holderData.fetch(outsider.holderData);
+      assert.isFalse(data.isVerified);
+
+      await verify(holder, proof);
+      data = await program.account.holderData.fetch(holder.holderData);
+      assert.isTrue(data.isVerified);
+    });
+  });
+
+
+  describe("long-term holder cooldown", () => {
+    it("keeps the full cooldown for newly acquired tokens", async () => {
+      const ctx = await setupToken();
+      const holder = await setupHolder(ctx, 10_000_000);
+      const recipient = await setupHolder(ctx, 0);
+      const setCooldown = (secs: number) =>
+        program.methods
+          .setLongTermCooldown(new anchor.BN(secs))
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+
+      let vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.longTermCooldown.toNumber(), 300);
+
+      // The long-term cooldown can only shorten the normal one
+      assert.equal(await errorCode(setCooldown(301)), "InvalidConfigValue");
+      assert.equal(await errorCode(setCooldown(-1)), "InvalidConfigValue");
+      await setCooldown(10);
+      vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.equal(vault.longTermCooldown.toNumber(), 10);
+
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      await program.methods
+        .secureTransfer(new anchor.BN(1_000_000), false, null)
+        .accountsPartial({
+          authority: holder.owner.publicKey,
+          from: holder.tokenAccount,
+          to: recipient.tokenAccount,
+          mint: ctx.mint,
+          treasury: ctx.tokenAccount,
+          holderData: holder.holderData,
+          toHolderData: recipient.holderData,
+          rewardsVault: ctx.rewardsVault,
+          transferPair: null,
+          priceFeed,
+        })
+        .signers([holder.owner])
+        .rpc();
+
+      // Tokens acquired just now haven't met the holding period
+      const status = await program.methods
+        .inCooldown()
+        .accountsPartial({ mint: ctx.mint, holderData: holder.holderData })
+        .view();
+      assert.isTrue(status.inCooldown);
+      assert.isAbove(status.secondsRemaining.toNumber(), 10);
+    });
+  });
+
+
+  describe("project_emissions", () => {
+    it("integrates the current rate and campaign over the horizon", async () => {
+      const ctx = await setupToken();
+      const year = 31_536_000;
+      const project = (horizon: number) =>
+        program.methods
+          .projectEmissions(new anchor.BN(horizon))
+          .accountsPartial({ mint: ctx.mint, rewardsVault: ctx.rewardsVault })
+          .view();
+      // Mirrors reward_index_growth and calculate_rewards on the full supply
+      const expected = (rateBps: number, weightedPeriod: anchor.BN) => {
+        const growth = new anchor.BN(rateBps)
+          .mul(weightedPeriod)
+          .mul(new anchor.BN(10).pow(new anchor.BN(12)))
+          .div(new anchor.BN(10_000 * 10_000 * year));
+        return new anchor.BN("1000000000000000")
+          .mul(growth)
+          .mul(new anchor.BN(10_000))
+          .div(new anchor.BN(10).pow(new anchor.BN(16)));
+      };
+
+      // 5% of the billion-token supply over a year
+      assert.equal((await project(year)).toString(), "50000000000000");
+      assert.equal(
+        (await project(year)).toString(),
+        expected(500, new anchor.BN(year * 10_000)).toString()
+      );
+
+      // A 2x campaign for 1000s inside the horizon adds that window again
+      const now = Math.floor(Date.now() / 1000);
+      await program.methods
+        .setCampaign(20_000, new anchor.BN(now + 1_000), new anchor.BN(now + 2_000))
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      const weighted = new anchor.BN(year - 1_000).mul(new anchor.BN(10_000))
+        .add(new anchor.BN(1_000 * 20_000));
+      assert.equal((await project(year)).toString(), expected(500, weighted).toString());
+
+      assert.equal(
+        await errorCode(
+          program.methods
+            .projectEmissions(new anchor.BN(-1))
+            .accountsPartial({ mint: ctx.mint, rewardsVault: ctx.rewardsVault })
+            .rpc()
+        ),
+        "InvalidPeriod"
+      );
+    });
+  });
+
+
+  describe("program-owned recipients", () => {
+    it("blocks recipients owned by programs that are not allowed", async () => {
+      const ctx = await setupToken();
+      const senders = [
+        await setupHolder(ctx, 10_000_000),
+        await setupHolder(ctx, 10_000_000),
+      ];
+      const wallet = await setupHolder(ctx, 0);
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+
+      // Creates the associated token account of `owner`, which may be off-curve
+      const createAta = async (owner: anchor.web3.PublicKey) => {
+        const ata = anchor.utils.token.associatedAddress({ mint: ctx.mint, owner });
+        await provider.sendAndConfirm(
+          new anchor.web3.Transaction().add(
+            new anchor.web3.TransactionInstruction({
+              programId: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+              keys: [
+                { pubkey: authority, isSigner: true, isWritable: true },
+                { pubkey: ata, isSigner: false, isWritable: true },
+                { pubkey: owner, isSigner: false, isWritable: false },
+                { pubkey: ctx.mint, isSigner: false, isWritable: false },
+                {
+                  pubkey: anchor.web3.SystemProgram.programId,
+                  isSigner: false,
+                  isWritable: false,
+                },
+                {
+                  pubkey: anchor.utils.token.TOKEN_PROGRAM_ID,
+                  isSigner: false,
+                  isWritable: false,
+                },
+              ],
+              data: Buffer.alloc(0),
+            })
+          )
+        );
+        return ata;
+      };
+      // A token account owned by the stats PDA, which this program owns
+      const escrow = await createAta(ctx.stats);
+      // A PDA that holds no data is owned by the system program, but it's
+      // off-curve, so it's an escrow rather than a wallet
+      const [emptyPda] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow")],
+        program.programId
+      );
+      const emptyPdaEscrow = await createAta(emptyPda);
+
+      const transfer = (
+        sender: Awaited<ReturnType<typeof setupHolder>>,
+        to: anchor.web3.PublicKey,
+        toHolderData: anchor.web3.PublicKey,
+        recipientOwner: anchor.web3.PublicKey | null
+      ) =>
+        program.methods
+          .secureTransfer(new anchor.BN(1_000_000), false, null)
+          .accountsPartial({
+            authority: sender.owner.publicKey,
+            from: sender.tokenAccount,
+            to,
+            mint: ctx.mint,
+            treasury: ctx.tokenAccount,
+            holderData: sender.holderData,
+            toHolderData,
+            rewardsVault: ctx.rewardsVault,
+            transferPair: null,
+            priceFeed,
+          })
+          .remainingAccounts(
+            recipientOwner
+              ? [{ pubkey: recipientOwner, isSigner: false, isWritable: false }]
+              : []
+          )
+          .signers([sender.owner])
+          .rpc();
+
+      await program.methods
+        .setBlockProgramRecipients(true)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+
+      assert.equal(
+        await errorCode(transfer(senders[0], wallet.tokenAccount, wallet.holderData, null)),
+        "MissingRecipientOwner"
+      );
+      assert.equal(
+        await errorCode(transfer(senders[0], escrow, holderDataAddress(ctx.mint, ctx.stats), ctx.stats)),
+        "RecipientProgramNotAllowed"
+      );
+      assert.equal(
+        await errorCode(transfer(senders[0], emptyPdaEscrow, holderDataAddress(ctx.mint, emptyPda), emptyPda)),
+        "RecipientProgramNotAllowed"
+      );
+      // A system-owned wallet is always allowed
+      await transfer(senders[0], wallet.tokenAccount, wallet.holderData, wallet.owner.publicKey);
+
+      await program.methods
+        .addAllowedRecipientProgram(program.programId)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.isTrue(vault.allowedRecipientPrograms[0].equals(program.programId));
+      await transfer(senders[1], escrow, holderDataAddress(ctx.mint, ctx.stats), ctx.stats);
+    });
+  });
+
+
+  describe("holding streak", () => {
+    it("restarts the streak only when the balance dips below the floor", async () => {
+      const ctx = await setupToken();
+      const holder = await setupHolder(ctx, 10_000_000);
+      const whale = await setupHolder(ctx, 10_000_000);
+      const sender = await setupHolder(ctx, 10_000_000);
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      const transfer = (
+        from: Awaited<ReturnType<typeof setupHolder>>,
+        to: Awaited<ReturnType<typeof setupHolder>>,
+        amount: number,
+        toHolderData = to.holderData
+      ) =>
+        program.methods
+          .secureTransfer(new anchor.BN(amount), false, null)
+          .accountsPartial({
+            authority: from.owner.publicKey,
+            from: from.tokenAccount,
+            to: to.tokenAccount,
+            mint: ctx.mint,
+            treasury: ctx.tokenAccount,
+            holderData: from.holderData,
+            toHolderData,
+            rewardsVault: ctx.rewardsVault,
+            transferPair: null,
+            priceFeed,
+          })
+          .signers([from.owner])
+          .rpc();
+      const claim = () =>
+        program.methods
+          .claimRewards()
+          .accountsPartial({
+            authority: holder.owner.publicKey,
+            holderData: holder.holderData,
+            mint: ctx.mint,
+            rewardsVault: ctx.rewardsVault,
+            tokenAccount: holder.tokenAccount,
+            referrerData: null,
+            mintAuthority: authority,
+          })
+          .signers([holder.owner])
+          .rpc();
+      const emergencyClaim = () =>
+        program.methods
+          .emergencyClaim()
+          .accountsPartial({
+            authority: holder.owner.publicKey,
+            holderData: holder.holderData,
+            mint: ctx.mint,
+            rewardsVault: ctx.rewardsVault,
+            tokenAccount: holder.tokenAccount,
+            treasury: ctx.tokenAccount,
+            mintAuthority: authority,
+          })
+          .signers([holder.owner])
+          .rpc();
+      const streakStart = async (h: Awaited<ReturnType<typeof setupHolder>>) =>
+        (await program.account.holderData.fetch(h.holderData)).holdingStreakStart.toNumber();
+
+      assert.equal(
+        await errorCode(
+          program.methods
+            .setHoldingStreak(new anchor.BN(5_000_000), new anchor.BN(-1))
+            .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+            .rpc()
+        ),
+        "InvalidConfigValue"
+      );
+      await program.methods
+        .setHoldingStreak(new anchor.BN(5_000_000), new anchor.BN(3_600))
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      assert.equal(await errorCode(claim()), "HoldingStreakTooShort");
+      // Skipping the cooldown doesn't skip the streak
+      assert.equal(await errorCode(emergencyClaim()), "HoldingStreakTooShort");
+
+      const holderStart = await streakStart(holder);
+      const whaleStart = await streakStart(whale);
+      await new Promise((resolve) => setTimeout(resolve, 2000));
+
+      // Dropping to 4 tokens breaks the holder's streak; the whale's top-up
+      // from above the floor leaves theirs alone
+      await transfer(holder, whale, 6_000_000);
+      assert.isAbove(await streakStart(holder), holderStart);
+      assert.equal(await streakStart(whale), whaleStart);
+
+      // Climbing back over the floor starts a new streak that still has to
+      // reach the minimum
+      const dippedStart = await streakStart(holder);
+      await new Promise((resolve) => setTimeout(resolve, 2000));
+
+      // The top-up can't leave out the holder's PDA, which would skip the
+      // restart; the program ID is how a client omits an optional account
+      assert.equal(
+        await errorCode(transfer(sender, holder, 2_000_000, program.programId)),
+        "ConstraintSeeds"
+      );
+      assert.equal(await streakStart(holder), dippedStart);
+      await transfer(sender, holder, 2_000_000);
+      assert.isAbove(await streakStart(holder), dippedStart);
+      assert.equal(await errorCode(claim()), "HoldingStreakTooShort");
+    });
+  });
+
+
+  describe("reassign_holder", () => {
+    it("moves holder data to the new key and refuses existing holders", async () => {
+      const ctx = await setupToken();
+      const lost = await setupHolder(ctx, 10_000_000);
+      const existing = await setupHolder(ctx, 0);
+      // A fresh funded wallet with no holder data yet
+      const recovered = await setupHolder(ctx, 10_000_000, false);
+      const before = await program.account.holderData.fetch(lost.holderData);
+      const reassign = (newAuthority: anchor.web3.PublicKey) =>
+        program.methods
+          .reassignHolder(lost.owner.publicKey, newAuthority)
+          .accountsPartial({ authority, mint: ctx.mint, rewardsVault: ctx.rewardsVault })
+          .rpc();
+      const claim = (h: Awaited<ReturnType<typeof setupHolder>>) =>
+        program.methods
+          .claimRewards()
+          .accountsPartial({
+            authority: h.owner.publicKey,
+            holderData: h.holderData,
+            mint: ctx.mint,
+            rewardsVault: ctx.rewardsVault,
+            tokenAccount: h.tokenAccount,
+            referrerData: null,
+            mintAuthority: authority,
+          })
+          .signers([h.owner])
+          .rpc();
+
+      assert.include(
+        await errorCode(reassign(existing.owner.publicKey)),
+        "already in use"
+      );
+      await reassign(recovered.owner.publicKey);
+
+      assert.isNull(await program.account.holderData.fetchNullable(lost.holderData));
+      const after = await program.account.holderData.fetch(recovered.holderData);
+      assert.isTrue(after.authority.equals(recovered.owner.publicKey));
+      assert.equal(after.registeredAt.toString(), before.registeredAt.toString());
+      assert.equal(after.lastIndex.toString(), before.lastIndex.toString());
+
+      // The new key gets as far as the holding period; the old one has no
+      // holder data left to claim with
+      assert.equal(await errorCode(claim(recovered)), "MinHoldingPeriodNotMet");
+      assert.equal(await errorCode(claim(lost)), "AccountNotInitialized");
+    });
+
+    it("repoints referrals and emits the reassignment", async () => {
+      const ctx = await setupToken();
+      const lost = await setupHolder(ctx, 10_000_000);
+      const unrelated = await setupHolder(ctx, 0);
+      const recovered = await setupHolder(ctx, 0, false);
+
+      // A holder the lost key referred
+      const referred = await setupHolder(ctx, 0, false);
+      await program.methods
+        .initializeRewards(lost.owner.publicKey)
+        .accountsPartial({
+          authority: referred.owner.publicKey,
+          mint: ctx.mint,
+          holderData: referred.holderData,
+          referrerData: lost.holderData,
+        })
+        .signers([referred.owner])
+        .rpc();
+
+      const reassign = (referrals: anchor.web3.PublicKey[]) =>
+        program.methods
+          .reassignHolder(lost.owner.publicKey, recovered.owner.publicKey)
+          .accountsPartial({ authority, mint: ctx.mint, rewardsVault: ctx.rewardsVault })
+          .remainingAccounts(
+            referrals.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true }))
+          )
+          .rpc({ commitment: "confirmed" });
+
+      assert.equal(
+        await errorCode(reassign([referred.holderData, unrelated.holderData])),
+        "InvalidReferredHolder"
+      );
+      const sig = await reassign([referred.holderData]);
+
+      const data = await program.account.holderData.fetch(referred.holderData);
+      assert.isTrue(data.referrer.equals(recovered.owner.publicKey));
+
+      const tx = await provider.connection.getTransaction(sig, {
+        commitment: "confirmed",
+      });
+      const parser = new anchor.EventParser(program.programId, program.coder);
+      const event = Array.from(parser.parseLogs(tx.meta.logMessages)).find(
+        (e) => e.name === "holderReassigned"
+      );
+      assert.isDefined(event);
+      assert.isTrue(event.data.oldHolder.equals(lost.owner.publicKey));
+      assert.isTrue(event.data.newAuthority.equals(recovered.owner.publicKey));
+      assert.equal(event.data.referralsMoved, 1);
+    });
+  });
+
+
+  describe("registrations pause", () => {
+    it("blocks only new registrations", async () => {
+      const ctx = await setupToken();
+      const holder = await setupHolder(ctx, 10_000_000);
+      const sender = await setupHolder(ctx, 10_000_000);
+      const recipient = await setupHolder(ctx, 0);
+      const newcomer = await setupHolder(ctx, 10_000_000, false);
+      const setPaused = (paused: boolean) =>
+        program.methods
+          .setRegistrationsPaused(paused)
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+      const register = () =>
+        program.methods
+          .initializeRewards(null)
+          .accountsPartial({
+            authority: newcomer.owner.publicKey,
+            mint: ctx.mint,
+            holderData: newcomer.holderData,
+          })
+          .signers([newcomer.owner])
+          .rpc();
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+
+      const transfer = (
+        from: Awaited<ReturnType<typeof setupHolder>>,
+        to: Awaited<ReturnType<typeof setupHolder>>
+      ) =>
+        program.methods
+          .secureTransfer(new anchor.BN(1_000_000), false, null)
+          .accountsPartial({
+            authority: from.owner.publicKey,
+            from: from.tokenAccount,
+            to: to.tokenAccount,
+            mint: ctx.mint,
+            treasury: ctx.tokenAccount,
+            holderData: from.holderData,
+            toHolderData: to.holderData,
+            rewardsVault: ctx.rewardsVault,
+            transferPair: null,
+            priceFeed,
+          })
+          .signers([from.owner])
+          .rpc();
+
+      await setPaused(true);
+      assert.equal(await errorCode(register()), "RegistrationsPaused");
+
+      // Registered holders keep transferring, including to a recipient
+      // with no holder data, who stays unregistered
+      await transfer(holder, recipient);
+      await transfer(sender, newcomer);
+      assert.isNull(
+        await provider.connection.getAccountInfo(newcomer.holderData)
+      );
+
+      // The newcomer can't register through a first transfer either
+      assert.equal(
+        await errorCode(transfer(newcomer, recipient)),
+        "RegistrationsPaused"
+      );
+      assert.isNull(
+        await provider.connection.getAccountInfo(newcomer.holderData)
+      );
+
+      await setPaused(false);
+      await register();
+      const data = await program.account.holderData.fetch(newcomer.holderData);
+      assert.isTrue(data.authority.equals(newcomer.owner.publicKey));
+    });
+  });
+
+
+  describe("minimum balance accrual", () => {
+    it("tracks the dip rather than the balance after a top-up", async () => {
+      const ctx = await setupToken();
+      const holder = await setupHolder(ctx, 10_000_000, false);
+      const sink = await setupHolder(ctx, 0);
+      const sender = await setupHolder(ctx, 10_000_000);
+      const register = (tokenAccount: anchor.web3.PublicKey) =>
+        program.methods
+          .initializeRewards(null)
+          .accountsPartial({
+            authority: holder.owner.publicKey,
+            mint: ctx.mint,
+            holderData: holder.holderData,
+            tokenAccount,
+          })
+          .signers([holder.owner])
+          .rpc();
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      await program.methods
+        .setAccrueOnMinBalance(true)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      const vault = await program.account.rewardsVault.fetch(ctx.rewardsVault);
+      assert.isTrue(vault.accrueOnMinBalance);
+      const transfer = (
+        from: Awaited<ReturnType<typeof setupHolder>>,
+        to: Awaited<ReturnType<typeof setupHolder>>,
+        amount: number
+      ) =>
+        program.methods
+          .secureTransfer(new anchor.BN(amount), false, null)
+          .accountsPartial({
+            authority: from.owner.publicKey,
+            from: from.tokenAccount,
+            to: to.tokenAccount,
+            mint: ctx.mint,
+            treasury: ctx.tokenAccount,
+            holderData: from.holderData,
+            toHolderData: to.holderData,
+            rewardsVault: ctx.rewardsVault,
+            transferPair: null,
+            priceFeed,
+          })
+          .signers([from.owner])
+          .rpc();
+      const minBalance = async () =>
+        (await program.account.holderData.fetch(holder.holderData)).minBalanceSinceClaim.toString();
+
+      // The sink registered before it was funded, so it starts from zero;
+      // the holder registers with their token account and its balance
+      const sinkData = await program.account.holderData.fetch(sink.holderData);
+      assert.equal(sinkData.minBalanceSinceClaim.toString(), "0");
+      assert.equal(sinkData.bonusMinBalance.toString(), "0");
+      assert.equal(await errorCode(register(sink.tokenAccount)), "ConstraintRaw");
+      await register(holder.tokenAccount);
+      assert.equal(await minBalance(), "10000000");
+
+      await transfer(holder, sink, 6_000_000);
+      assert.equal(await minBalance(), "4000000");
+
+      // Topping back up doesn't lift the minimum claims will accrue on
+      await transfer(sender, holder, 5_000_000);
+      assert.equal(await minBalance(), "4000000");
+      const balance = await provider.connection.getTokenAccountBalance(holder.tokenAccount);
+      assert.equal(balance.value.amount, "9000000");
+    });
+  });
+
+
+  describe("verify_supply", () => {
+    it("balances program mints and burns and surfaces external mints", async () => {
+      const ctx = await setupToken();
+      const sender = await setupHolder(ctx, 10_000_000);
+      const recipient = await setupHolder(ctx, 0);
+      const verify = () =>
+        program.methods
+          .verifySupply()
+          .accountsPartial({ mint: ctx.mint })
+          .view();
+
+      let report = await verify();
+      assert.equal(report.supply.toString(), "1000000000000000");
+      assert.equal(report.discrepancy.toNumber(), 0);
+
+      // A transfer whose whole fee is burned, then a clawback burn
+      await program.methods
+        .setPriceUnavailablePolicy({ allowWithMaxLimits: {} })
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      await program.methods
+        .setTransferFee(100, 10_000)
+        .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+        .rpc();
+      await program.methods
+        .secureTransfer(new anchor.BN(1_000_000), false, null)
+        .accountsPartial({
+          authority: sender.owner.publicKey,
+          from: sender.tokenAccount,
+          to: recipient.tokenAccount,
+          mint: ctx.mint,
+          treasury: ctx.tokenAccount,
+          holderData: sender.holderData,
+          toHolderData: recipient.holderData,
+          rewardsVault: ctx.rewardsVault,
+          transferPair: null,
+          priceFeed,
+        })
+        .signers([sender.owner])
+        .rpc();
+      const setPaused = (paused: boolean) =>
+        program.methods
+          .setPaused(paused)
+          .accountsPartial({ authority, rewardsVault: ctx.rewardsVault })
+          .rpc();
+      await setPaused(true);
+      await program.methods
+        .clawback(ctx.tokenAccount, new anchor.BN(1_000))
+        .accountsPartial({
+          authority,
+          mint: ctx.mint,
+          rewardsVault: ctx.rewardsVault,
+          from: ctx.tokenAccount,
+        })
+        .rpc();
+      await setPaused(false);
+
+      report = await verify();
+      assert.equal(report.supply.toString(), "999999999989000");
+      assert.equal(report.expectedSupply.toString(), report.supply.toString());
+      assert.equal(report.discrepancy.toNumber(), 0);
+      const stats = await program.account.stats.fetch(ctx.stats);
+      assert.equal(stats.totalBurned.toNumber(), 11_000);
+
+      // Minting with the wallet directly bypasses the program's accounting
+      await provider.sendAndConfirm(
+        new anchor.web3.Transaction().add(
+          new anchor.web3.TransactionInstruction({
+            programId: anchor.utils.token.TOKEN_PROGRAM_ID,
+            keys: [
+              { pubkey: ctx.mint, isSigner: false, isWritable: true },
+              { pubkey: ctx.tokenAccount, isSigner: false, isWritable: true },
+              { pubkey: authority, isSigner: true, isWritable: false },
+            ],
+            // SPL token MintTo
+            data: Buffer.concat([
+              Buffer.from([7]),
+              new anchor.BN(5_000).toArrayLike(Buffer, "le", 8),
+            ]),
+          })
+        )
+      );
+      report = await verify();
+      assert.equal(report.discrepancy.toNumber(), 5_000);
+    });
   });
 });